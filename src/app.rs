@@ -1,10 +1,28 @@
-use crate::window::{Window, WindowUpdateContext};
+use crate::game::GameState;
+use crate::renderer::Renderer;
+use crate::system::System;
+use crate::window::{
+	FixedTimestep, FocusPolicy, FramePacing, Gamepads, GestureConfig, InputRecorder, Window,
+	WindowUpdateContext,
+};
 
 pub trait App {
 	fn setup(&mut self, _window: &mut Window) -> anyhow::Result<()> {
 		Ok(())
 	}
 	fn teardown(&mut self) {}
+	// called for errors (and panics) from the app, return Ok to keep running
+	fn on_error(&mut self, error: anyhow::Error) -> anyhow::Result<()> {
+		Err(error)
+	}
+	// called before update
+	fn on_focus_changed(&mut self, _has_focus: bool) {}
+	// minimised, occluded, or sent to the background
+	fn on_suspended(&mut self) {}
+	fn on_resumed(&mut self) {}
+	// e.g. moved to a monitor with a different dpi, pixels in WindowUpdateContext are physical
+	fn on_scale_factor_changed(&mut self, _scale_factor: f64) {}
+
 	fn is_done(&self) -> bool {
 		true
 	}
@@ -12,7 +30,52 @@ pub trait App {
 		Ok(())
 	}
 	fn fixed_update(&mut self, _time_step: f64) {}
-	fn render(&mut self) {}
+	// alpha is 0..1 into the next fixed update, for interpolating between states
+	// while the GameStateStack isn't empty Game begins the frame before this, and ends it after rendering the states,
+	// so there is no need to call Renderer::begin_frame and end_frame then, nested calls are ignored
+	fn render(&mut self, _alpha: f64) {}
+
+	// pushed onto the GameStateStack after setup, from then on Game begins and ends the frames, see render
+	fn initial_state(&mut self) -> Option<Box<dyn GameState>> {
+		None
+	}
+	// game states render through this, apps driven by states usually leave render empty
+	fn renderer_mut(&mut self) -> Option<&mut Renderer> {
+		None
+	}
+
+	// records, or replays the input passed to update
+	fn input_recorder(&mut self) -> Option<&mut InputRecorder> {
+		None
+	}
+
+	// polled into WindowUpdateContext before input_recorder, so recordings include them
+	fn gamepads(&mut self) -> Option<&mut Gamepads> {
+		None
+	}
+
+	fn fixed_timestep(&self) -> FixedTimestep {
+		FixedTimestep::default()
+	}
+
+	fn frame_pacing(&self) -> FramePacing {
+		FramePacing::default()
+	}
+
+	// pausing, and throttling while in the background, nothing by default, see FocusPolicy::pause_in_background
+	fn focus_policy(&self) -> FocusPolicy {
+		FocusPolicy::default()
+	}
+
+	// timings for double-click, long-press, and drag detection
+	fn gesture_config(&self) -> GestureConfig {
+		GestureConfig::default()
+	}
+
+	// lets mouse only code work on touch screens
+	fn emulate_mouse_from_touch(&self) -> bool {
+		false
+	}
 
 	fn remember_window_layout(&self) -> bool {
 		false
@@ -21,7 +84,15 @@ pub trait App {
 	fn app_name(&self) -> &str {
 		"oml-game"
 	}
+	// in the per-user config directory, next to the executable when there is none
 	fn layout_filename(&self) -> String {
+		match System::get_config_dir(self.app_name()) {
+			Some(dir) => format!("{}/window_layout.yaml", dir),
+			None => self.old_layout_filename(),
+		}
+	}
+	// where older versions saved it, loaded while there is nothing at layout_filename yet
+	fn old_layout_filename(&self) -> String {
 		format!("{}_layout.yaml", self.app_name())
 	}
 }
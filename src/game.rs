@@ -1,13 +1,197 @@
+use std::any::Any;
+use std::cell::Cell;
+use std::panic::{self, AssertUnwindSafe};
 use std::path::Path;
+use std::rc::Rc;
 
 use chrono::prelude::*;
 use tracing::*;
 
-use crate::window::{Window, WindowCallbacks, WindowLayout, WindowUserData};
+use crate::math::Vector2;
+use crate::window::{
+	Window, WindowCallbacks, WindowLayout, WindowLayoutSaver, WindowUpdateContext, WindowUserData,
+};
 use crate::App;
 
+mod game_state;
+pub use game_state::{GameState, GameStateCommand, GameStateTransition};
+mod game_state_stack;
+pub use game_state_stack::GameStateStack;
+mod golden_images;
+pub use golden_images::{GoldenImages, UPDATE_GOLDEN_IMAGES_ENV};
+
+const HEADLESS_FRAME_TIME: f64 = 1.0 / 60.0;
+
+struct AppLifecycle {
+	error:        Option<anyhow::Error>,
+	is_torn_down: bool,
+	states:       GameStateStack,
+	window_size:  Option<Rc<Cell<Vector2>>>, // shared with the Window, see Renderer::setup
+}
+
+impl AppLifecycle {
+	fn new() -> Self {
+		Self {
+			error:        None,
+			is_torn_down: false,
+			states:       GameStateStack::new(),
+			window_size:  None,
+		}
+	}
+
+	fn is_running(&self) -> bool {
+		self.error.is_none() && !self.is_torn_down
+	}
+
+	fn panic_message(payload: &Box<dyn Any + Send>) -> String {
+		if let Some(s) = payload.downcast_ref::<&str>() {
+			s.to_string()
+		} else if let Some(s) = payload.downcast_ref::<String>() {
+			s.clone()
+		} else {
+			"unknown panic".to_string()
+		}
+	}
+
+	fn catch<F>(f: F) -> anyhow::Result<()>
+	where
+		F: FnOnce() -> anyhow::Result<()>,
+	{
+		match panic::catch_unwind(AssertUnwindSafe(f)) {
+			Ok(r) => r,
+			Err(payload) => Err(anyhow::anyhow!(
+				"App panicked: {}",
+				Self::panic_message(&payload)
+			)),
+		}
+	}
+
+	// runs f, and routes errors (and panics) through App::on_error, tearing down if the app can't recover
+	fn guarded<F>(&mut self, app: &mut dyn App, f: F)
+	where
+		F: FnOnce(&mut dyn App, &mut GameStateStack) -> anyhow::Result<()>,
+	{
+		if !self.is_running() {
+			return;
+		}
+		let states = &mut self.states;
+		if let Err(e) = Self::catch(|| f(app, states)) {
+			if let Err(e) = Self::catch(|| app.on_error(e)) {
+				error!("App failed: {:?}", e);
+				self.error = Some(e);
+				self.teardown(app);
+			}
+		}
+	}
+
+	fn setup(&mut self, app: &mut dyn App, window: &mut Window) {
+		self.window_size = Some(window.physical_size_handle());
+		self.guarded(app, |app, states| {
+			app.setup(window)?;
+			if let Some(state) = app.initial_state() {
+				states.push(state, GameStateTransition::Cut);
+			}
+			Ok(())
+		});
+	}
+
+	// returns true once the app is done, or failed
+	fn update(&mut self, app: &mut dyn App, wuc: &mut WindowUpdateContext) -> bool {
+		if let Some(window_size) = &self.window_size {
+			window_size.set(wuc.window_size);
+		}
+		self.guarded(app, |app, states| {
+			if let Some(gamepads) = app.gamepads() {
+				gamepads.poll(wuc);
+			}
+			if let Some(recorder) = app.input_recorder() {
+				recorder.process(wuc);
+			}
+			wuc.recognize_gestures();
+			if wuc.was_focus_gained() || wuc.was_focus_lost() {
+				app.on_focus_changed(wuc.has_focus());
+			}
+			if wuc.was_suspended() {
+				app.on_suspended();
+			} else if wuc.was_resumed() {
+				app.on_resumed();
+			}
+			if wuc.was_scale_factor_changed() {
+				app.on_scale_factor_changed(wuc.scale_factor());
+			}
+			if let Some(renderer) = app.renderer_mut() {
+				renderer.set_window_size(&wuc.window_size);
+			}
+			app.update(wuc)?;
+			states.update(wuc)
+		});
+		if self.is_running() && app.is_done() {
+			println!("App is done, tearing down");
+			self.teardown(app);
+		}
+		!self.is_running()
+	}
+
+	fn fixed_update(&mut self, app: &mut dyn App, time_step: f64) {
+		self.guarded(app, |app, states| {
+			app.fixed_update(time_step);
+			states.fixed_update(time_step);
+			Ok(())
+		});
+	}
+
+	fn render(&mut self, app: &mut dyn App, alpha: f64) {
+		self.guarded(app, |app, states| {
+			if states.is_empty() {
+				app.render(alpha);
+				return Ok(());
+			}
+			// one frame for the app and its states
+			let has_renderer = match app.renderer_mut() {
+				Some(renderer) => {
+					renderer.begin_frame();
+					true
+				},
+				None => false,
+			};
+			app.render(alpha);
+			if has_renderer {
+				if let Some(renderer) = app.renderer_mut() {
+					states.render(renderer);
+					renderer.end_frame();
+				}
+			}
+			Ok(())
+		});
+	}
+
+	fn teardown(&mut self, app: &mut dyn App) {
+		if self.is_torn_down {
+			return;
+		}
+		self.is_torn_down = true;
+		let states = &mut self.states;
+		if let Err(e) = Self::catch(|| {
+			states.clear();
+			app.teardown();
+			Ok(())
+		}) {
+			error!("App teardown failed: {:?}", e);
+		}
+	}
+
+	fn finish(&mut self, app: &mut dyn App) -> anyhow::Result<()> {
+		self.teardown(app);
+		match self.error.take() {
+			Some(e) => Err(e),
+			None => Ok(()),
+		}
+	}
+}
+
 pub struct Game {
-	app: Box<dyn App>,
+	app:       Box<dyn App>,
+	lifecycle: AppLifecycle,
 }
 
 impl WindowUserData for Game {
@@ -19,104 +203,482 @@ impl WindowUserData for Game {
 	}
 }
 
+impl Drop for Game {
+	fn drop(&mut self) {
+		// ensure teardown even if we never made it back from the window
+		self.lifecycle.teardown(self.app.as_mut());
+	}
+}
+
 impl Game {
 	fn new(app: Box<dyn App>) -> Self {
-		Self { app }
+		Self {
+			app,
+			lifecycle: AppLifecycle::new(),
+		}
+	}
+
+	fn setup(&mut self, window: &mut Window) {
+		self.lifecycle.setup(self.app.as_mut(), window);
+	}
+
+	fn update(&mut self, wuc: &mut WindowUpdateContext) -> bool {
+		self.lifecycle.update(self.app.as_mut(), wuc)
 	}
 
-	fn app_mut(&mut self) -> &mut Box<dyn App> {
-		&mut self.app
+	fn fixed_update(&mut self, time_step: f64) {
+		self.lifecycle.fixed_update(self.app.as_mut(), time_step);
 	}
-	fn app(&self) -> &Box<dyn App> {
-		&self.app
+
+	fn render(&mut self, alpha: f64) {
+		self.lifecycle.render(self.app.as_mut(), alpha);
+	}
+
+	fn finish(&mut self) -> anyhow::Result<()> {
+		self.lifecycle.finish(self.app.as_mut())
 	}
 
-	pub fn run(mut app: impl App + 'static) -> anyhow::Result<()> {
+	// returns the first error the app didn't recover from, teardown is always called
+	pub fn run(app: impl App + 'static) -> anyhow::Result<()> {
 		debug!("oml-game::Game::run()");
 
 		let mut window = Window::new();
 
+		let mut layout_saver = None;
 		if app.remember_window_layout() {
 			let filename = app.layout_filename();
+			let old_filename = app.old_layout_filename();
 			let mut layout = WindowLayout::default();
-			match layout.load(&Path::new(&filename)) {
+			// always saved to the new filename
+			match layout.load_with_fallback(Path::new(&filename), Path::new(&old_filename)) {
 				Ok(_) => {
 					if let Some(main_layout) = layout.get_window("main") {
-						println!("{:#?}", &main_layout);
+						debug!("Restoring window layout {:?}", &main_layout);
 						window.set_position(main_layout.pos());
 						window.set_size(main_layout.size());
+						window.set_mode(main_layout.mode().clone());
 					}
 				},
-				// ignore errors
-				_ => {},
+				// a missing file is expected on the first run
+				Err(e) => debug!("No window layout loaded from {}: {:?}", &filename, e),
 			}
+			layout_saver = Some(WindowLayoutSaver::new(layout, Path::new(&filename), "main"));
 		}
 
+		window.set_fixed_timestep(app.fixed_timestep());
+		window.set_frame_pacing(app.frame_pacing());
+		window.set_focus_policy(app.focus_policy());
+		window.set_gesture_config(app.gesture_config());
+		window.set_emulate_mouse_from_touch(app.emulate_mouse_from_touch());
 		window.setup()?;
 
+		let mut game = Game::new(Box::new(app));
+
 		let start_time: DateTime<Utc> = Utc::now();
-		app.setup(&mut window)?;
+		game.setup(&mut window);
 		let end_time: DateTime<Utc> = Utc::now();
 		let load_duration = end_time.signed_duration_since(start_time);
 		let load_time = load_duration.num_milliseconds() as f64 / 1000.0;
 		info!("App setup took {} seconds", load_time);
 
+		if !game.lifecycle.is_running() {
+			window.teardown();
+			return game.finish();
+		}
+
 		let callbacks = WindowCallbacks::default()
-			.with_update(Box::new(|wud, wuc| {
+			.with_update(Box::new(move |wud, wuc| {
 				//debug!("Update");
 				match wud.as_any_mut().downcast_mut::<Game>() {
 					Some(game) => {
-						if wuc.window_changed && game.app().remember_window_layout() {
-							let mut layout = WindowLayout::default();
-							layout.set_window("main", &wuc.window_pos, &wuc.window_size);
-							let filename = game.app().layout_filename();
-							match layout.save(&Path::new(&filename)) {
-								// :TODO: handle errors
-								_ => {},
-							}
-						}
-						match game.app_mut().update(wuc) {
-							Ok(_) => {},
-							Err(_e) => {
-								return true;
-							},
-						}
-
-						if game.app().is_done() {
-							println!("App is done, tearing down");
-							game.app_mut().teardown();
-							return true;
+						if let Some(layout_saver) = &mut layout_saver {
+							layout_saver.update(wuc);
 						}
+						// pending changes are saved when the callbacks are dropped
+						game.update(wuc)
 					},
-					None => {},
+					None => true,
 				}
-				false
 			}))
 			.with_fixed_update(Box::new(|wud, time_step| {
 				//debug!("Fixed Update {}", time_step);
 				match wud.as_any_mut().downcast_mut::<Game>() {
 					Some(game) => {
-						game.app_mut().fixed_update(time_step);
+						game.fixed_update(time_step);
 					},
 					None => {},
 				}
 			}))
-			.with_render(Box::new(|wud| {
+			.with_render(Box::new(|wud, alpha| {
 				//debug!("Render");
 				match wud.as_any_mut().downcast_mut::<Game>() {
 					Some(game) => {
-						game.app_mut().render();
+						game.render(alpha);
 					},
 					None => {},
 				}
 			}));
 
-		let game = Box::new(Game::new(Box::new(app)));
+		let mut game = window.run(None, Box::new(game), callbacks);
+
+		window.teardown();
+
+		match game.as_any_mut().downcast_mut::<Game>() {
+			Some(game) => game.finish(),
+			None => Ok(()),
+		}
+	}
+	// runs the app for the given number of frames without opening a window, or creating a GL context
+	pub fn run_headless(app: &mut impl App, frames: usize) -> anyhow::Result<()> {
+		Self::run_headless_with_input(app, frames, |_frame, _wuc| {})
+	}
+
+	// input is called at the start of every frame, and can fake input, or change the time_step
+	pub fn run_headless_with_input<F>(
+		app: &mut impl App,
+		frames: usize,
+		input: F,
+	) -> anyhow::Result<()>
+	where
+		F: FnMut(usize, &mut WindowUpdateContext),
+	{
+		Self::run_headless_with_callbacks(app, frames, input, |_frame, _wuc, _app| {})
+	}
+
+	// rendered is called after every frame was rendered, e.g. to read back the pixels
+	pub(crate) fn run_headless_with_callbacks<F, R>(
+		app: &mut impl App,
+		frames: usize,
+		mut input: F,
+		mut rendered: R,
+	) -> anyhow::Result<()>
+	where
+		F: FnMut(usize, &mut WindowUpdateContext),
+		R: FnMut(usize, &WindowUpdateContext, &mut dyn App),
+	{
+		debug!("oml-game::Game::run_headless() for {} frames", frames);
+
+		let mut window = Window::new_headless();
+		window.set_fixed_timestep(app.fixed_timestep());
+		window.set_focus_policy(app.focus_policy());
+		window.set_gesture_config(app.gesture_config());
+		window.set_emulate_mouse_from_touch(app.emulate_mouse_from_touch());
+		window.setup()?;
+
+		let app: &mut dyn App = app;
+		let mut lifecycle = AppLifecycle::new();
+		lifecycle.setup(app, &mut window);
+
+		let mut fixed_timestep = *window.fixed_timestep();
+		let mut wuc = window.create_update_context();
+
+		for frame in 0..frames {
+			if !lifecycle.is_running() {
+				break;
+			}
+			wuc.time_step = HEADLESS_FRAME_TIME;
+			input(frame, &mut wuc);
+
+			if lifecycle.update(app, &mut wuc) {
+				debug!("App is done after {} frames", frame + 1);
+				break;
+			}
+			let focus_policy = window.focus_policy();
+			if !focus_policy.is_paused(&wuc) {
+				let ticks = fixed_timestep.advance(wuc.time_step);
+				for _ in 0..ticks {
+					lifecycle.fixed_update(app, fixed_timestep.time_step());
+				}
+			}
+
+			if focus_policy.should_render(&wuc) {
+				lifecycle.render(app, fixed_timestep.alpha());
+				rendered(frame, &wuc, app);
+			}
 
-		window.run(None, game, callbacks); // never returns since thread is hijacked for good.
+			wuc.update();
+			// after update, so window_changed is seen next frame
+			if let Some(mode) = wuc.take_requested_window_mode() {
+				wuc.set_current_window_mode(mode);
+			}
+		}
 
 		window.teardown();
 
+		lifecycle.finish(app)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::renderer::{Color, Effect, Renderer, VirtualResolution};
+	use crate::system::System;
+	use crate::window::FocusPolicy;
+
+	#[derive(Default)]
+	struct CountingApp {
+		setups:        usize,
+		updates:       usize,
+		fixed_updates: usize,
+		renders:       usize,
+		teardowns:     usize,
+	}
+
+	impl App for CountingApp {
+		fn setup(&mut self, window: &mut Window) -> anyhow::Result<()> {
+			assert!(window.is_headless());
+			self.setups += 1;
+			Ok(())
+		}
+		fn teardown(&mut self) {
+			self.teardowns += 1;
+		}
+		fn is_done(&self) -> bool {
+			false
+		}
+		fn update(&mut self, _wuc: &mut WindowUpdateContext) -> anyhow::Result<()> {
+			self.updates += 1;
+			Ok(())
+		}
+		fn fixed_update(&mut self, _time_step: f64) {
+			self.fixed_updates += 1;
+		}
+		fn render(&mut self, _alpha: f64) {
+			self.renders += 1;
+		}
+	}
+
+	#[test]
+	fn run_headless_runs_all_frames() -> anyhow::Result<()> {
+		let mut app = CountingApp::default();
+		Game::run_headless_with_input(&mut app, 60, |_frame, wuc| {
+			wuc.time_step = 1.0 / 30.0;
+		})?;
+
+		assert_eq!(1, app.setups);
+		assert_eq!(60, app.updates);
+		assert_eq!(60, app.renders);
+		assert_eq!(1, app.teardowns);
+		// two seconds at the default 120Hz
+		assert!((239..=240).contains(&app.fixed_updates));
+
+		Ok(())
+	}
+
+	#[derive(Default)]
+	struct FailingApp {
+		fail_in_frame: usize,
+		panic:         bool,
+		frame:         usize,
+		errors:        usize,
+		teardowns:     usize,
+	}
+
+	impl App for FailingApp {
+		fn is_done(&self) -> bool {
+			false
+		}
+		fn update(&mut self, _wuc: &mut WindowUpdateContext) -> anyhow::Result<()> {
+			self.frame += 1;
+			if self.frame == self.fail_in_frame {
+				if self.panic {
+					panic!("boom");
+				}
+				anyhow::bail!("failed in frame {}", self.frame);
+			}
+			Ok(())
+		}
+		fn teardown(&mut self) {
+			self.teardowns += 1;
+		}
+		fn on_error(&mut self, error: anyhow::Error) -> anyhow::Result<()> {
+			self.errors += 1;
+			Err(error)
+		}
+	}
+
+	#[test]
+	fn errors_are_returned_and_teardown_is_called() {
+		let mut app = FailingApp {
+			fail_in_frame: 3,
+			..Default::default()
+		};
+		let r = Game::run_headless(&mut app, 10);
+
+		assert_eq!("failed in frame 3", r.unwrap_err().to_string());
+		assert_eq!(3, app.frame);
+		assert_eq!(1, app.errors);
+		assert_eq!(1, app.teardowns);
+	}
+
+	#[test]
+	fn panics_are_returned_and_teardown_is_called() {
+		let mut app = FailingApp {
+			fail_in_frame: 2,
+			panic: true,
+			..Default::default()
+		};
+		let r = Game::run_headless(&mut app, 10);
+
+		assert_eq!("App panicked: boom", r.unwrap_err().to_string());
+		assert_eq!(2, app.frame);
+		assert_eq!(1, app.errors);
+		assert_eq!(1, app.teardowns);
+	}
+
+	#[derive(Default)]
+	struct FocusApp {
+		counts:        CountingApp,
+		focus_changes: Vec<bool>,
+		suspends:      usize,
+		resumes:       usize,
+	}
+
+	impl App for FocusApp {
+		fn is_done(&self) -> bool {
+			false
+		}
+		fn on_focus_changed(&mut self, has_focus: bool) {
+			self.focus_changes.push(has_focus);
+		}
+		fn on_suspended(&mut self) {
+			self.suspends += 1;
+		}
+		fn on_resumed(&mut self) {
+			self.resumes += 1;
+		}
+		fn fixed_update(&mut self, time_step: f64) {
+			self.counts.fixed_update(time_step);
+		}
+		fn render(&mut self, alpha: f64) {
+			self.counts.render(alpha);
+		}
+		fn focus_policy(&self) -> FocusPolicy {
+			FocusPolicy::auto_pause()
+		}
+	}
+
+	#[test]
+	fn background_frames_are_paused_and_reported() -> anyhow::Result<()> {
+		let mut app = FocusApp::default();
+		Game::run_headless_with_input(&mut app, 40, |frame, wuc| {
+			wuc.time_step = 1.0 / 60.0;
+			wuc.set_has_focus(!(10..20).contains(&frame));
+			wuc.set_minimized((30..35).contains(&frame));
+		})?;
+
+		assert_eq!(vec![false, true], app.focus_changes);
+		assert_eq!(1, app.suspends);
+		assert_eq!(1, app.resumes);
+		assert_eq!(35, app.counts.renders);
+		// 25 active frames at 120Hz
+		assert!((49..=50).contains(&app.counts.fixed_updates));
+
+		Ok(())
+	}
+
+	struct StateApp {
+		renderer:     Option<Renderer>,
+		renders:      usize,
+		begins_frame: bool, // like apps from before game states
+	}
+
+	struct GreenState;
+
+	impl GameState for GreenState {
+		fn name(&self) -> &str {
+			"green"
+		}
+		fn render(&mut self, renderer: &mut Renderer) {
+			renderer.set_color(&Color::from_rgba(0.0, 1.0, 0.0, 1.0));
+			renderer.render_quad(&Vector2::zero(), &Vector2::new(4.0, 4.0));
+		}
+	}
+
+	impl App for StateApp {
+		fn setup(&mut self, _window: &mut Window) -> anyhow::Result<()> {
+			self.renderer = Some(Renderer::software_for_tests(4, 4));
+			Ok(())
+		}
+		fn is_done(&self) -> bool {
+			false
+		}
+		fn initial_state(&mut self) -> Option<Box<dyn GameState>> {
+			Some(Box::new(GreenState))
+		}
+		fn renderer_mut(&mut self) -> Option<&mut Renderer> {
+			self.renderer.as_mut()
+		}
+		fn render(&mut self, _alpha: f64) {
+			self.renders += 1;
+			if let Some(renderer) = &mut self.renderer {
+				if self.begins_frame {
+					renderer.begin_frame();
+				}
+				renderer.clear(&Color::black());
+				if self.begins_frame {
+					renderer.end_frame();
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn states_render_in_the_apps_frame() -> anyhow::Result<()> {
+		for begins_frame in [false, true] {
+			let mut app = StateApp {
+				renderer: None,
+				renders: 0,
+				begins_frame,
+			};
+			Game::run_headless(&mut app, 5)?;
+
+			assert_eq!(5, app.renders);
+			let mut renderer = app.renderer.unwrap();
+			assert_eq!(5, renderer.frame());
+			assert_eq!([0, 255, 0, 255], renderer.pixel_for_tests(0, 0));
+		}
+		Ok(())
+	}
+
+	// doesn't override App::renderer_mut
+	struct VirtualResolutionApp {
+		renderer: Renderer,
+	}
+
+	impl App for VirtualResolutionApp {
+		fn setup(&mut self, window: &mut Window) -> anyhow::Result<()> {
+			window.set_size(&Vector2::new(100.0, 100.0));
+			self.renderer.setup(window, &mut System::new())?;
+			self.renderer
+				.register_effect(Effect::from_source(0, "colored", "", ""));
+			self.renderer
+				.set_virtual_resolution(Some(VirtualResolution::new(&Vector2::new(100.0, 100.0))));
+			Ok(())
+		}
+		fn is_done(&self) -> bool {
+			false
+		}
+		fn render(&mut self, _alpha: f64) {
+			self.renderer.begin_frame();
+			self.renderer.end_frame();
+		}
+	}
+
+	#[test]
+	fn virtual_resolution_follows_the_window() -> anyhow::Result<()> {
+		let mut app = VirtualResolutionApp {
+			renderer: Renderer::new(),
+		};
+		Game::run_headless_with_input(&mut app, 5, |frame, wuc| {
+			if frame == 3 {
+				wuc.window_size = Vector2::new(200.0, 100.0);
+			}
+		})?;
+
+		assert_eq!(&Vector2::new(50.0, 0.0), app.renderer.viewport_pos());
+		assert_eq!(&Vector2::new(100.0, 100.0), app.renderer.viewport_size());
 		Ok(())
 	}
 }
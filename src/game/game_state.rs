@@ -0,0 +1,78 @@
+use crate::renderer::{Color, Renderer};
+use crate::window::WindowUpdateContext;
+
+#[derive(Debug, Default, Copy, Clone)]
+pub enum GameStateTransition {
+	#[default]
+	Cut,
+	// crossfades from the states before the change to the ones after, tinted towards color at the midpoint,
+	// e.g. transparent black for a plain crossfade, or black to dip through black
+	// both are rendered offscreen, and composited on layer_id and layer_id + 1 with an identity mvp for the frame,
+	// effect_id has to be a textured one with alpha blending
+	Fade {
+		duration:  f64,
+		color:     Color,
+		layer_id:  u8,
+		effect_id: u16,
+	},
+}
+
+impl GameStateTransition {
+	pub fn fade(duration: f64, color: &Color, layer_id: u8, effect_id: u16) -> Self {
+		GameStateTransition::Fade {
+			duration,
+			color: *color,
+			layer_id,
+			effect_id,
+		}
+	}
+
+	pub fn duration(&self) -> f64 {
+		match self {
+			GameStateTransition::Cut => 0.0,
+			GameStateTransition::Fade { duration, .. } => *duration,
+		}
+	}
+}
+
+pub enum GameStateCommand {
+	None,
+	Push(Box<dyn GameState>, GameStateTransition),
+	Pop(GameStateTransition),
+	Replace(Box<dyn GameState>, GameStateTransition),
+}
+
+impl std::fmt::Debug for GameStateCommand {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match self {
+			GameStateCommand::None => write!(f, "None"),
+			GameStateCommand::Push(s, t) => write!(f, "Push({}, {:?})", s.name(), t),
+			GameStateCommand::Pop(t) => write!(f, "Pop({:?})", t),
+			GameStateCommand::Replace(s, t) => write!(f, "Replace({}, {:?})", s.name(), t),
+		}
+	}
+}
+
+pub trait GameState {
+	fn name(&self) -> &str;
+
+	// called when the state becomes part of the stack, and when it leaves it
+	fn enter(&mut self) {}
+	fn exit(&mut self) {}
+	// called when another state is pushed on top, and when that one is popped again
+	fn pause(&mut self) {}
+	fn resume(&mut self) {}
+
+	// only the top state is updated, and not while a transition is running
+	fn update(&mut self, _wuc: &mut WindowUpdateContext) -> anyhow::Result<GameStateCommand> {
+		Ok(GameStateCommand::None)
+	}
+	fn fixed_update(&mut self, _time_step: f64) {}
+	// during a transition states in both the old and the new view are rendered once for each
+	fn render(&mut self, _renderer: &mut Renderer) {}
+
+	// overlays, e.g. a pause menu, let the state below render too
+	fn is_overlay(&self) -> bool {
+		false
+	}
+}
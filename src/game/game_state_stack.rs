@@ -0,0 +1,509 @@
+use std::collections::VecDeque;
+
+use tracing::*;
+
+use crate::game::{GameState, GameStateCommand, GameStateTransition};
+use crate::math::{Matrix44, Vector2};
+use crate::renderer::{Color, RenderTarget, Renderer};
+use crate::window::WindowUpdateContext;
+
+// the views before, and after the change while transitioning
+const FROM_RENDER_TARGET: &str = "[game_state_from]";
+const TO_RENDER_TARGET: &str = "[game_state_to]";
+
+enum StackChange {
+	Push(Box<dyn GameState>),
+	Pop,
+	Replace(Box<dyn GameState>),
+}
+
+// the change is applied when it starts, the old view is states[..from_len] with removed on top
+struct RunningTransition {
+	transition: GameStateTransition,
+	elapsed:    f64,
+	from_len:   usize,
+	removed:    Option<Box<dyn GameState>>, // exited when done
+}
+
+#[derive(Default)]
+pub struct GameStateStack {
+	states:     Vec<Box<dyn GameState>>,
+	transition: Option<RunningTransition>,
+	queued:     VecDeque<(StackChange, GameStateTransition)>,
+}
+
+impl std::fmt::Debug for GameStateStack {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		let names = self.states.iter().map(|s| s.name()).collect::<Vec<_>>();
+		write!(
+			f,
+			"GameStateStack: {:?} transition: {:?} queued: {}",
+			names,
+			self.transition.as_ref().map(|t| &t.transition),
+			self.queued.len()
+		)
+	}
+}
+
+impl GameStateStack {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn len(&self) -> usize {
+		self.states.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.states.is_empty()
+	}
+
+	pub fn top(&self) -> Option<&dyn GameState> {
+		self.states.last().map(|s| s.as_ref())
+	}
+
+	pub fn is_transitioning(&self) -> bool {
+		self.transition.is_some()
+	}
+
+	pub fn push(&mut self, state: Box<dyn GameState>, transition: GameStateTransition) {
+		self.queue(StackChange::Push(state), transition);
+	}
+
+	pub fn pop(&mut self, transition: GameStateTransition) {
+		self.queue(StackChange::Pop, transition);
+	}
+
+	pub fn replace(&mut self, state: Box<dyn GameState>, transition: GameStateTransition) {
+		self.queue(StackChange::Replace(state), transition);
+	}
+
+	// exits all states, top first, e.g. on teardown
+	pub fn clear(&mut self) {
+		self.queued.clear();
+		self.finish_transition();
+		while let Some(mut state) = self.states.pop() {
+			state.exit();
+		}
+	}
+
+	fn queue(&mut self, change: StackChange, transition: GameStateTransition) {
+		self.queued.push_back((change, transition));
+		self.start_next_transition();
+	}
+
+	fn start_next_transition(&mut self) {
+		while self.transition.is_none() {
+			let (change, transition) = match self.queued.pop_front() {
+				Some(q) => q,
+				None => return,
+			};
+			match transition {
+				GameStateTransition::Cut => {
+					self.apply(change, false);
+				},
+				_ => {
+					let len = self.states.len();
+					let removed = self.apply(change, true);
+					self.transition = Some(RunningTransition {
+						transition,
+						elapsed: 0.0,
+						from_len: len - usize::from(removed.is_some()),
+						removed,
+					});
+				},
+			}
+		}
+	}
+
+	fn finish_transition(&mut self) {
+		if let Some(mut removed) = self.transition.take().and_then(|t| t.removed) {
+			debug!("Exiting game state {}", removed.name());
+			removed.exit();
+		}
+	}
+
+	// returns the removed state instead of exiting it, if it is kept for a transition
+	fn apply(&mut self, change: StackChange, keep_removed: bool) -> Option<Box<dyn GameState>> {
+		let exit = |mut state: Box<dyn GameState>| {
+			if keep_removed {
+				return Some(state);
+			}
+			debug!("Exiting game state {}", state.name());
+			state.exit();
+			None
+		};
+		match change {
+			StackChange::Push(mut state) => {
+				if let Some(top) = self.states.last_mut() {
+					top.pause();
+				}
+				debug!("Entering game state {}", state.name());
+				state.enter();
+				self.states.push(state);
+				None
+			},
+			StackChange::Pop => match self.states.pop() {
+				Some(state) => {
+					let removed = exit(state);
+					if let Some(top) = self.states.last_mut() {
+						top.resume();
+					}
+					removed
+				},
+				None => {
+					warn!("Tried to pop from empty GameStateStack");
+					None
+				},
+			},
+			StackChange::Replace(mut state) => {
+				let removed = self.states.pop().and_then(exit);
+				debug!("Entering game state {}", state.name());
+				state.enter();
+				self.states.push(state);
+				removed
+			},
+		}
+	}
+
+	fn apply_command(&mut self, command: GameStateCommand) {
+		match command {
+			GameStateCommand::None => {},
+			GameStateCommand::Push(state, transition) => self.push(state, transition),
+			GameStateCommand::Pop(transition) => self.pop(transition),
+			GameStateCommand::Replace(state, transition) => self.replace(state, transition),
+		}
+	}
+
+	fn advance_transition(&mut self, time_step: f64) {
+		let done = match &mut self.transition {
+			Some(t) => {
+				t.elapsed += time_step;
+				t.elapsed >= t.transition.duration()
+			},
+			None => false,
+		};
+		if done {
+			self.finish_transition();
+			self.start_next_transition();
+		}
+	}
+
+	pub fn update(&mut self, wuc: &mut WindowUpdateContext) -> anyhow::Result<()> {
+		if self.transition.is_some() {
+			self.advance_transition(wuc.time_step());
+			return Ok(());
+		}
+		let command = match self.states.last_mut() {
+			Some(top) => top.update(wuc)?,
+			None => GameStateCommand::None,
+		};
+		self.apply_command(command);
+		Ok(())
+	}
+
+	pub fn fixed_update(&mut self, time_step: f64) {
+		if self.transition.is_some() {
+			return;
+		}
+		if let Some(top) = self.states.last_mut() {
+			top.fixed_update(time_step);
+		}
+	}
+
+	// the top state, and the ones below it down to the first that isn't an overlay
+	fn render_view(
+		states: &mut [Box<dyn GameState>],
+		top: Option<&mut Box<dyn GameState>>,
+		renderer: &mut Renderer,
+	) {
+		let covered = matches!(&top, Some(s) if !s.is_overlay());
+		if !covered {
+			let mut first = states.len();
+			while first > 0 {
+				first -= 1;
+				if !states[first].is_overlay() {
+					break;
+				}
+			}
+			for state in states[first..].iter_mut() {
+				state.render(renderer);
+			}
+		}
+		if let Some(top) = top {
+			top.render(renderer);
+		}
+	}
+
+	fn prepare_render_targets(renderer: &mut Renderer) -> anyhow::Result<()> {
+		let size = *renderer.viewport_size();
+		for name in [FROM_RENDER_TARGET, TO_RENDER_TARGET] {
+			if renderer.render_target(name).map(|rt| *rt.size()) != Some(size) {
+				renderer.register_render_target(
+					RenderTarget::new(name, &size)
+						.with_clear_color(Some(Color::from_rgba(0.0, 0.0, 0.0, 0.0))),
+				)?;
+			}
+		}
+		Ok(())
+	}
+
+	pub fn render(&mut self, renderer: &mut Renderer) {
+		let Some(t) = &mut self.transition else {
+			Self::render_view(&mut self.states, None, renderer);
+			return;
+		};
+		let GameStateTransition::Fade {
+			duration,
+			color,
+			layer_id,
+			effect_id,
+		} = t.transition
+		else {
+			Self::render_view(&mut self.states, None, renderer);
+			return;
+		};
+		if let Err(e) = Self::prepare_render_targets(renderer) {
+			warn!("Transition rendered as a cut: {:?}", e);
+			Self::render_view(&mut self.states, None, renderer);
+			return;
+		}
+
+		renderer.begin_render_target(FROM_RENDER_TARGET);
+		Self::render_view(&mut self.states[..t.from_len], t.removed.as_mut(), renderer);
+		renderer.end_render_target();
+		renderer.begin_render_target(TO_RENDER_TARGET);
+		Self::render_view(&mut self.states, None, renderer);
+		renderer.end_render_target();
+
+		let f = (t.elapsed / duration.max(f64::EPSILON)).clamp(0.0, 1.0) as f32;
+		// 0 -> 1 until the midpoint, then back to 0
+		let tint = (1.0 - (2.0 * f - 1.0).abs()) * color.a;
+		let tinted = |a| {
+			Color::from_rgba(
+				1.0 + (color.r - 1.0) * tint,
+				1.0 + (color.g - 1.0) * tint,
+				1.0 + (color.b - 1.0) * tint,
+				a,
+			)
+		};
+		// full-screen, independent of the states' matrices
+		let identity = Matrix44::identity();
+		for (layer_id, name, alpha) in [
+			(layer_id, FROM_RENDER_TARGET, 1.0),
+			(layer_id.saturating_add(1), TO_RENDER_TARGET, f),
+		] {
+			renderer.set_mvp_matrix_for_layer_this_frame(layer_id, &identity);
+			renderer.use_layer(layer_id);
+			renderer.use_effect(effect_id);
+			renderer.use_texture(name);
+			renderer.set_color(&tinted(alpha));
+			renderer.render_textured_quad(&Vector2::zero(), &Vector2::new(2.0, 2.0));
+		}
+		renderer.set_color(&Color::white());
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::cell::RefCell;
+	use std::rc::Rc;
+
+	use super::*;
+	use crate::renderer::{BlendFactor, Effect};
+
+	struct LoggingState {
+		name: String,
+		log:  Rc<RefCell<Vec<String>>>,
+		next: Option<GameStateCommand>,
+	}
+
+	impl LoggingState {
+		fn new(name: &str, log: &Rc<RefCell<Vec<String>>>) -> Box<Self> {
+			Box::new(Self {
+				name: name.to_string(),
+				log:  log.clone(),
+				next: None,
+			})
+		}
+		fn log(&self, what: &str) {
+			self.log
+				.borrow_mut()
+				.push(format!("{} {}", self.name, what));
+		}
+	}
+
+	impl GameState for LoggingState {
+		fn name(&self) -> &str {
+			&self.name
+		}
+		fn enter(&mut self) {
+			self.log("enter");
+		}
+		fn exit(&mut self) {
+			self.log("exit");
+		}
+		fn pause(&mut self) {
+			self.log("pause");
+		}
+		fn resume(&mut self) {
+			self.log("resume");
+		}
+		fn update(&mut self, _wuc: &mut WindowUpdateContext) -> anyhow::Result<GameStateCommand> {
+			self.log("update");
+			Ok(self.next.take().unwrap_or(GameStateCommand::None))
+		}
+	}
+
+	#[test]
+	fn push_pop_and_replace_call_hooks() -> anyhow::Result<()> {
+		let log = Rc::new(RefCell::new(Vec::new()));
+		let mut stack = GameStateStack::new();
+		let mut wuc = WindowUpdateContext::new();
+
+		stack.push(LoggingState::new("menu", &log), GameStateTransition::Cut);
+		let mut game = LoggingState::new("game", &log);
+		game.next = Some(GameStateCommand::Pop(GameStateTransition::Cut));
+		stack.push(game, GameStateTransition::Cut);
+		assert_eq!(2, stack.len());
+
+		stack.update(&mut wuc)?;
+		assert_eq!(1, stack.len());
+
+		stack.replace(LoggingState::new("credits", &log), GameStateTransition::Cut);
+		stack.clear();
+
+		assert_eq!(
+			vec![
+				"menu enter",
+				"menu pause",
+				"game enter",
+				"game update",
+				"game exit",
+				"menu resume",
+				"menu exit",
+				"credits enter",
+				"credits exit",
+			],
+			*log.borrow()
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn fade_keeps_the_old_state_until_done() -> anyhow::Result<()> {
+		let log = Rc::new(RefCell::new(Vec::new()));
+		let mut stack = GameStateStack::new();
+		let mut wuc = WindowUpdateContext::new();
+		wuc.time_step = 0.25;
+
+		stack.push(LoggingState::new("menu", &log), GameStateTransition::Cut);
+		let fade = GameStateTransition::fade(1.0, &Color::black(), 8, 1);
+		stack.replace(LoggingState::new("game", &log), fade);
+		assert_eq!("game", stack.top().unwrap().name());
+		assert!(stack.is_transitioning());
+		assert_eq!(vec!["menu enter", "game enter"], *log.borrow());
+
+		for _ in 0..3 {
+			stack.update(&mut wuc)?;
+		}
+		assert!(stack.is_transitioning());
+		stack.update(&mut wuc)?;
+		assert!(!stack.is_transitioning());
+		assert_eq!("menu exit", log.borrow().last().unwrap());
+		assert!(!log.borrow().iter().any(|l| l.ends_with("update")));
+
+		stack.update(&mut wuc)?;
+		assert_eq!("game update", log.borrow().last().unwrap());
+		Ok(())
+	}
+
+	struct QuadState {
+		color: Color,
+	}
+
+	impl GameState for QuadState {
+		fn name(&self) -> &str {
+			"quad"
+		}
+		fn render(&mut self, renderer: &mut Renderer) {
+			renderer.use_layer(0);
+			renderer.use_effect(0);
+			renderer.set_color(&self.color);
+			renderer.render_quad(&Vector2::zero(), &Vector2::new(16.0, 16.0));
+		}
+	}
+
+	// a red quad over the center pixels, on the fade's layer, over green
+	struct LayerState;
+
+	impl GameState for LayerState {
+		fn name(&self) -> &str {
+			"layer"
+		}
+		fn render(&mut self, renderer: &mut Renderer) {
+			QuadState {
+				color: Color::from_rgba(0.0, 1.0, 0.0, 1.0),
+			}
+			.render(renderer);
+			renderer.use_layer(8);
+			renderer.set_color(&Color::from_rgba(1.0, 0.0, 0.0, 1.0));
+			renderer.render_quad(&Vector2::zero(), &Vector2::new(8.0, 8.0));
+		}
+	}
+
+	fn render(stack: &mut GameStateStack, renderer: &mut Renderer) -> [u8; 4] {
+		renderer.begin_frame();
+		stack.render(renderer);
+		renderer.end_frame();
+		renderer.pixel_for_tests(0, 0)
+	}
+
+	#[test]
+	fn fade_blends_both_states_full_screen() -> anyhow::Result<()> {
+		let mut renderer = Renderer::software_for_tests(4, 4);
+		renderer.register_effect(
+			Effect::from_source(1, "textured", "", "")
+				.with_textured(true)
+				.with_blend_func(BlendFactor::SrcAlpha, BlendFactor::OneMinusSrcAlpha),
+		);
+		// the states' matrix doesn't apply to the transition
+		renderer.set_mvp_matrix(&Matrix44::ortho(-8.0, 8.0, -8.0, 8.0, 1.0, -1.0));
+		let mut stack = GameStateStack::new();
+		let mut wuc = WindowUpdateContext::new();
+		wuc.time_step = 0.25;
+
+		let red = Color::from_rgba(1.0, 0.0, 0.0, 1.0);
+		let green = Color::from_rgba(0.0, 1.0, 0.0, 1.0);
+		stack.push(Box::new(QuadState { color: red }), GameStateTransition::Cut);
+		let crossfade = GameStateTransition::fade(1.0, &Color::from_rgba(0.0, 0.0, 0.0, 0.0), 8, 1);
+		stack.replace(Box::new(QuadState { color: green }), crossfade);
+
+		assert_eq!([255, 0, 0], render(&mut stack, &mut renderer)[0..3]);
+		stack.update(&mut wuc)?;
+		assert_eq!([191, 64, 0], render(&mut stack, &mut renderer)[0..3]);
+		stack.update(&mut wuc)?;
+		assert_eq!([128, 128, 0], render(&mut stack, &mut renderer)[0..3]);
+		stack.update(&mut wuc)?;
+		stack.update(&mut wuc)?;
+		assert!(!stack.is_transitioning());
+		assert_eq!([0, 255, 0], render(&mut stack, &mut renderer)[0..3]);
+
+		// through black
+		let fade = GameStateTransition::fade(1.0, &Color::black(), 8, 1);
+		stack.replace(Box::new(QuadState { color: red }), fade);
+		stack.update(&mut wuc)?;
+		stack.update(&mut wuc)?;
+		assert_eq!([0, 0, 0], render(&mut stack, &mut renderer)[0..3]);
+
+		// the layers are back to the shared matrix afterwards
+		stack.replace(Box::new(LayerState), fade);
+		for _ in 0..6 {
+			stack.update(&mut wuc)?;
+		}
+		assert!(!stack.is_transitioning());
+		assert_eq!([0, 255, 0], render(&mut stack, &mut renderer)[0..3]);
+		assert_eq!([255, 0, 0], renderer.pixel_for_tests(2, 2)[0..3]);
+		Ok(())
+	}
+}
@@ -0,0 +1,285 @@
+use std::path::{Path, PathBuf};
+
+use tracing::*;
+
+use crate::renderer::Renderer;
+use crate::{App, Game};
+
+// set to 1 to write the reference images instead of comparing against them
+pub const UPDATE_GOLDEN_IMAGES_ENV: &str = "OML_GAME_UPDATE_GOLDEN_IMAGES";
+
+#[derive(Debug, Clone)]
+struct Capture {
+	frame: usize,
+	name:  String,
+}
+
+#[derive(Debug)]
+struct CapturedFrame {
+	name:   String,
+	width:  usize,
+	height: usize,
+	rgba:   Vec<u8>, // top row first
+}
+
+// renders frames of an app headless, see Game::run_headless, and compares chosen frames
+// against reference pngs, e.g. to catch regressions in batching, text layout, or blending
+#[derive(Debug)]
+pub struct GoldenImages {
+	reference_dir:        PathBuf,
+	captures:             Vec<Capture>,
+	tolerance:            u8, // per channel
+	max_differing_pixels: usize,
+	update_references:    bool,
+}
+
+impl GoldenImages {
+	pub fn new(reference_dir: &Path) -> Self {
+		let update_references = std::env::var(UPDATE_GOLDEN_IMAGES_ENV)
+			.map(|v| !v.is_empty() && v != "0")
+			.unwrap_or(false);
+		Self {
+			reference_dir: reference_dir.to_path_buf(),
+			captures: Vec::new(),
+			tolerance: 0,
+			max_differing_pixels: 0,
+			update_references,
+		}
+	}
+
+	// compares frame (counting from 0) against <reference_dir>/<name>.png
+	pub fn with_capture(mut self, frame: usize, name: &str) -> Self {
+		self.captures.push(Capture {
+			frame,
+			name: name.to_string(),
+		});
+		self
+	}
+
+	// how much each channel may be off before a pixel counts as different
+	pub fn with_tolerance(mut self, tolerance: u8) -> Self {
+		self.tolerance = tolerance;
+		self
+	}
+
+	pub fn with_max_differing_pixels(mut self, max_differing_pixels: usize) -> Self {
+		self.max_differing_pixels = max_differing_pixels;
+		self
+	}
+
+	pub fn with_update_references(mut self, update_references: bool) -> Self {
+		self.update_references = update_references;
+		self
+	}
+
+	pub fn reference_path(&self, name: &str) -> PathBuf {
+		self.reference_dir.join(format!("{}.png", name))
+	}
+
+	// the frames are read back from App::renderer_mut,
+	// on failure <name>.actual.png and <name>.diff.png are written next to the reference
+	pub fn run(&self, app: &mut impl App, frames: usize) -> anyhow::Result<()> {
+		let mut captured = Vec::new();
+		Game::run_headless_with_callbacks(
+			app,
+			frames,
+			|_frame, _wuc| {},
+			|frame, wuc, app| {
+				for c in self.captures.iter().filter(|c| c.frame == frame) {
+					let width = wuc.window_size.x as usize;
+					let height = wuc.window_size.y as usize;
+					if let Some(renderer) = app.renderer_mut() {
+						captured.push(CapturedFrame {
+							name: c.name.clone(),
+							width,
+							height,
+							rgba: renderer.read_pixels(width, height),
+						});
+					}
+				}
+			},
+		)?;
+
+		let mut failures = Vec::new();
+		for c in self.captures.iter() {
+			match captured.iter().find(|f| f.name == c.name) {
+				Some(f) => {
+					if let Err(e) = self.check(f) {
+						failures.push(format!("{}: {}", c.name, e));
+					}
+				},
+				None => failures.push(format!(
+					"{}: frame {} was not rendered, or App::renderer_mut returned None",
+					c.name, c.frame
+				)),
+			}
+		}
+
+		if !failures.is_empty() {
+			anyhow::bail!(
+				"{} of {} golden images failed\n{}",
+				failures.len(),
+				self.captures.len(),
+				failures.join("\n")
+			);
+		}
+		Ok(())
+	}
+
+	fn check(&self, frame: &CapturedFrame) -> anyhow::Result<()> {
+		let path = self.reference_path(&frame.name);
+		if self.update_references {
+			std::fs::create_dir_all(&self.reference_dir)?;
+			let png = Renderer::encode_png(frame.width, frame.height, &frame.rgba)?;
+			std::fs::write(&path, png)?;
+			info!("Updated golden image {:?}", &path);
+			return Ok(());
+		}
+
+		if !path.exists() {
+			anyhow::bail!(
+				"no reference at {:?}, run with {}=1 to create it",
+				&path,
+				UPDATE_GOLDEN_IMAGES_ENV
+			);
+		}
+		let reference = image::open(&path)?.to_rgba8();
+		let (w, h) = (reference.width() as usize, reference.height() as usize);
+		let (differing, diff) = if (w, h) == (frame.width, frame.height) {
+			Self::diff(reference.as_raw(), &frame.rgba, self.tolerance)
+		} else {
+			(frame.width * frame.height, None)
+		};
+		if differing <= self.max_differing_pixels {
+			return Ok(());
+		}
+
+		let actual_path = self
+			.reference_dir
+			.join(format!("{}.actual.png", frame.name));
+		let png = Renderer::encode_png(frame.width, frame.height, &frame.rgba)?;
+		std::fs::write(&actual_path, png)?;
+		if let Some(diff) = diff {
+			let diff_path = self.reference_dir.join(format!("{}.diff.png", frame.name));
+			std::fs::write(&diff_path, Renderer::encode_png(w, h, &diff)?)?;
+		}
+		if (w, h) != (frame.width, frame.height) {
+			anyhow::bail!(
+				"size {}x{} doesn't match the reference {}x{}, see {:?}",
+				frame.width,
+				frame.height,
+				w,
+				h,
+				&actual_path
+			);
+		}
+		anyhow::bail!(
+			"{} pixels differ by more than {}, see {:?}",
+			differing,
+			self.tolerance,
+			&actual_path
+		);
+	}
+
+	// returns the number of differing pixels, and an image with them in red over a faded reference
+	fn diff(reference: &[u8], actual: &[u8], tolerance: u8) -> (usize, Option<Vec<u8>>) {
+		let mut differing = 0;
+		let mut diff = Vec::with_capacity(reference.len());
+		for (r, a) in reference.chunks_exact(4).zip(actual.chunks_exact(4)) {
+			if r.iter()
+				.zip(a.iter())
+				.any(|(r, a)| r.abs_diff(*a) > tolerance)
+			{
+				differing += 1;
+				diff.extend_from_slice(&[255, 0, 0, 255]);
+			} else {
+				let l = ((r[0] as u16 + r[1] as u16 + r[2] as u16) / 12) as u8;
+				diff.extend_from_slice(&[l, l, l, 255]);
+			}
+		}
+		(differing, Some(diff))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::math::Vector2;
+	use crate::renderer::{BlendFactor, Color, Effect};
+	use crate::window::Window;
+
+	struct QuadApp {
+		renderer: Option<Renderer>,
+		color:    Color,
+	}
+
+	impl App for QuadApp {
+		fn setup(&mut self, window: &mut Window) -> anyhow::Result<()> {
+			window.set_size(&Vector2::new(8.0, 4.0));
+			let mut renderer = Renderer::software_for_tests(8, 4);
+			renderer.register_effect(
+				Effect::from_source(0, "default", "", "")
+					.with_blend_func(BlendFactor::SrcAlpha, BlendFactor::OneMinusSrcAlpha),
+			);
+			self.renderer = Some(renderer);
+			Ok(())
+		}
+		fn is_done(&self) -> bool {
+			false
+		}
+		fn renderer_mut(&mut self) -> Option<&mut Renderer> {
+			self.renderer.as_mut()
+		}
+		fn render(&mut self, _alpha: f64) {
+			if let Some(renderer) = &mut self.renderer {
+				renderer.begin_frame();
+				renderer.clear(&Color::black());
+				renderer.set_color(&self.color);
+				renderer.render_quad(&Vector2::new(-2.0, 0.0), &Vector2::new(4.0, 4.0));
+				renderer.end_frame();
+			}
+		}
+	}
+
+	#[test]
+	fn frames_are_compared_against_references() -> anyhow::Result<()> {
+		let dir = std::env::temp_dir().join(format!("oml-game-golden-{}", std::process::id()));
+		let _ = std::fs::remove_dir_all(&dir);
+		let golden = GoldenImages::new(&dir)
+			.with_capture(2, "half_red")
+			.with_tolerance(2)
+			.with_update_references(false);
+		let mut app = QuadApp {
+			renderer: None,
+			color:    Color::from_rgba(1.0, 0.0, 0.0, 0.5),
+		};
+
+		let r = golden.run(&mut app, 3);
+		assert!(r
+			.unwrap_err()
+			.to_string()
+			.contains(UPDATE_GOLDEN_IMAGES_ENV));
+		let golden = golden.with_update_references(true);
+		golden.run(&mut app, 3)?;
+		let golden = golden.with_update_references(false);
+		golden.run(&mut app, 3)?;
+
+		// within the tolerance
+		app.color = Color::from_rgba(0.99, 0.0, 0.0, 0.5);
+		golden.run(&mut app, 3)?;
+
+		app.color = Color::from_rgba(0.0, 1.0, 0.0, 0.5);
+		let r = golden.run(&mut app, 3);
+		assert!(r.unwrap_err().to_string().contains("16 pixels differ"));
+		let diff = image::open(dir.join("half_red.diff.png"))?.to_rgba8();
+		assert_eq!([255, 0, 0, 255], diff.get_pixel(1, 1).0);
+		assert_eq!([0, 0, 0, 255], diff.get_pixel(6, 1).0);
+
+		// too few frames
+		let r = golden.run(&mut app, 2);
+		assert!(r.unwrap_err().to_string().contains("was not rendered"));
+
+		std::fs::remove_dir_all(&dir)?;
+		Ok(())
+	}
+}
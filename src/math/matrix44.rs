@@ -65,6 +65,24 @@ impl Matrix44 {
 		}
 	}
 
+	pub fn scaling_xy(x: f32, y: f32) -> Self {
+		Self {
+			m: [
+				x, 0.0, 0.0, 0.0, 0.0, y, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0,
+			],
+		}
+	}
+
+	// counter clockwise, in radians
+	pub fn rotation_z(angle: f32) -> Self {
+		let (s, c) = angle.sin_cos();
+		Self {
+			m: [
+				c, -s, 0.0, 0.0, s, c, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0,
+			],
+		}
+	}
+
 	pub fn multiply_vector4(&self, rhs: &Vector4) -> Vector4 {
 		let x = rhs.x;
 		let y = rhs.y;
@@ -292,6 +310,19 @@ mod tests {
 		assert_eq!(16.0, m[16]);
 	}
 
+	#[test]
+	fn rotation_and_scaling_transform_points() {
+		let p = Vector2::new(1.0, 0.0);
+		let r = Matrix44::rotation_z(std::f32::consts::FRAC_PI_2) * p;
+		assert!((r.x - 0.0).abs() < 0.0001);
+		assert!((r.y - 1.0).abs() < 0.0001);
+
+		// scale first, then move
+		let m = Matrix44::translation(&Vector3::new(10.0, 0.0, 0.0))
+			.multiply(&Matrix44::scaling_xy(2.0, 3.0));
+		assert_eq!(Vector2::new(12.0, 3.0), m * Vector2::new(1.0, 1.0));
+	}
+
 	#[test]
 	fn multiplication_works() -> anyhow::Result<()> {
 		let m0 = Matrix44::new(
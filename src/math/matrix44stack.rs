@@ -26,6 +26,11 @@ impl Matrix44Stack {
 		self.stack.push(t);
 	}
 
+	// number of pushes without a matching pop
+	pub fn depth(&self) -> usize {
+		self.stack.len()
+	}
+
 	pub fn pop(&mut self) {
 		let t = match self.stack.pop() {
 			Some(t) => t,
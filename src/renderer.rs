@@ -1,5 +1,7 @@
-use std::collections::{HashMap, VecDeque};
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::io::Cursor;
+use std::rc::Rc;
 use std::sync::mpsc;
 
 use backtrace::Backtrace;
@@ -80,9 +82,12 @@ struct ReadyScreenshot {
 }
 
 const MAX_TEXTURE_CHANNELS: usize = 4;
+// the frame, and the passes of the PostProcessChain ping pong between these
+const POST_PROCESS_TARGETS: [&str; 2] = ["[post_process_a]", "[post_process_b]"];
 #[derive(Debug)]
 pub struct Renderer {
 	frame:             u64,
+	frame_depth:       usize, // nested begin_frame calls do nothing
 	material_manager:  Manager<Material>,
 	texture_manager:   Manager<Texture>,
 	font_manager:      FontManager,
@@ -107,12 +112,36 @@ pub struct Renderer {
 
 	//	layer_matrix: [Option<Matrix44Stack>; 256],
 	//	layer_matrix: Vec< Matrix44Stack >,
-	layer_matrix: HashMap<u8, Matrix44Stack>,
+	layer_matrix:           HashMap<u8, Matrix44Stack>,
+	// used on the gpu instead of mvp_matrix for the layer
+	layer_mvp_matrix:       HashMap<u8, Matrix44>,
+	// like layer_mvp_matrix, but only until the next begin_frame
+	frame_layer_mvp_matrix: HashMap<u8, Matrix44>,
+	open_transforms:        usize, // push_transform without pop_transform this frame
+
+	// in normalized device coordinates, -1..1, the index + 1 is the clip id
+	clip_rects:      Vec<(Vector2, Vector2)>, // this frame
+	clip_rect_stack: Vec<u16>,
 
 	size:          Vector2,
 	viewport_pos:  Vector2,
 	viewport_size: Vector2,
 
+	window_size:        Vector2,
+	window_size_source: Option<Rc<Cell<Vector2>>>, // from setup, followed in begin_frame
+	virtual_resolution: Option<VirtualResolution>,
+
+	render_targets:         HashMap<String, RenderTarget>,
+	layer_render_targets:   HashMap<u8, String>,
+	section_render_target:  Option<String>, // between begin_render_target and end_render_target
+	bound_render_target:    Option<String>,
+	cleared_render_targets: HashSet<String>, // this frame
+
+	post_process:        PostProcessChain,
+	scene_render_target: Option<String>, // what the screen renders to while post processing
+
+	backend: Option<Box<dyn RendererBackend>>, // from setup
+
 	backtrace_on_missing: bool,
 	// very tempted to move this whole logic into seperate struct
 	command_rx:           Option<mpsc::Receiver<Command>>,
@@ -126,6 +155,7 @@ impl Renderer {
 	pub fn new() -> Self {
 		Self {
 			frame:             0,
+			frame_depth:       0,
 			material_manager:  Manager::new(),
 			texture_manager:   Manager::new(),
 			font_manager:      FontManager::new(),
@@ -147,12 +177,33 @@ impl Renderer {
 
 			//layer_matrix:  [None; 256],//[Matrix44Stack::default(); 256],
 			//layer_matrix: Vec::with_capacity(256),
-			layer_matrix: HashMap::new(),
+			layer_matrix:           HashMap::new(),
+			layer_mvp_matrix:       HashMap::new(),
+			frame_layer_mvp_matrix: HashMap::new(),
+			open_transforms:        0,
+
+			clip_rects:      Vec::new(),
+			clip_rect_stack: Vec::new(),
 
 			size:          Vector2::zero(),
 			viewport_pos:  Vector2::zero(),
 			viewport_size: Vector2::zero(),
 
+			window_size:        Vector2::zero(),
+			window_size_source: None,
+			virtual_resolution: None,
+
+			render_targets:         HashMap::new(),
+			layer_render_targets:   HashMap::new(),
+			section_render_target:  None,
+			bound_render_target:    None,
+			cleared_render_targets: HashSet::new(),
+
+			post_process:        PostProcessChain::new(),
+			scene_render_target: None,
+
+			backend: None,
+
 			backtrace_on_missing: false,
 
 			command_rx:         None,
@@ -252,7 +303,9 @@ impl Renderer {
 		}
 	}
 
-	pub fn register_effect(&mut self, effect: Effect) {
+	// compiles the effect's shaders, after setup
+	pub fn register_effect(&mut self, mut effect: Effect) {
+		effect.link(self.backend_mut());
 		if self.effects.len() == 0 {
 			self.default_effect_id = effect.id();
 		}
@@ -272,7 +325,9 @@ impl Renderer {
 			})
 	}
 
-	pub fn register_texture(&mut self, texture: Texture) -> u16 {
+	// creates, and uploads the texture, after setup
+	pub fn register_texture(&mut self, mut texture: Texture) -> u16 {
+		texture.register(self.backend_mut());
 		let index = self.texture_manager.add(texture);
 		if self.texture_manager.len() == 1 {
 			//			self.texture_manager.set_active( index );
@@ -333,6 +388,16 @@ impl Renderer {
 		}
 	*/
 	pub fn setup(&mut self, window: &Window, _system: &mut System) -> anyhow::Result<()> {
+		// so a virtual resolution follows the window
+		self.window_size_source = Some(window.physical_size_handle());
+		if window.is_headless() {
+			// there is no gl context to load from
+			let size = window.size();
+			return self.setup_with_backend(Box::new(SoftwareBackend::new(
+				size.x as usize,
+				size.y as usize,
+			)));
+		}
 		gl::load_with(|s| {
 			let a = window.get_proc_address(s) as *const _;
 			// let a = 0 as *const _; // force nullptr for testing
@@ -340,11 +405,19 @@ impl Renderer {
 			a
 		}); // :TODO: maybe use CFBundleGetFunctionPointerForName directly
 
-		unsafe {
-			let s = gl::GetString(gl::VERSION);
-			let s = String::from_utf8(std::ffi::CStr::from_ptr(s as *const _).to_bytes().to_vec())?;
-			println!("GL Version: {}", s);
+		println!("GL Version: {}", GlBackend::version()?);
+
+		self.setup_with_backend(Box::new(GlBackend::new()))
+	}
+
+	// e.g. a SoftwareBackend for rendering without a window
+	pub fn setup_with_backend(&mut self, backend: Box<dyn RendererBackend>) -> anyhow::Result<()> {
+		// textures, and programs can't move between backends
+		if let Some(b) = &self.backend {
+			anyhow::bail!("Renderer is already set up with the {} backend", b.name());
 		}
+		tracing::debug!("Using {} renderer backend", backend.name());
+		self.backend = Some(backend);
 
 		// ensure we have one texture
 		self.register_texture(Texture::create_canvas("[]", 2));
@@ -358,12 +431,37 @@ impl Renderer {
 		Ok(())
 	}
 
+	// panics before setup, there is nothing to render with
+	fn expect_backend(backend: &mut Option<Box<dyn RendererBackend>>) -> &mut dyn RendererBackend {
+		match backend.as_deref_mut() {
+			Some(b) => b,
+			None => panic!("No renderer backend, call Renderer::setup first"),
+		}
+	}
+
+	pub(crate) fn backend_mut(&mut self) -> &mut dyn RendererBackend {
+		Self::expect_backend(&mut self.backend)
+	}
+
 	pub fn teardown(&mut self) {
 		self.command_rx = None;
 		self.command_tx = None;
 	}
 
+	// calls can nest, e.g. an App::render that begins its own frame inside the one Game began for its states,
+	// only the outermost pair begins, and ends the frame
 	pub fn begin_frame(&mut self) {
+		self.frame_depth += 1;
+		if self.frame_depth > 1 {
+			tracing::trace!("Nested begin_frame, depth {}", self.frame_depth);
+			return;
+		}
+		if let Some(window_size) = self.window_size_source.as_ref().map(|s| s.get()) {
+			// zero until the first update
+			if window_size.x > 0.0 && window_size.y > 0.0 {
+				self.set_window_size(&window_size);
+			}
+		}
 		self.vertices.clear();
 		for material in self.material_manager.iter_mut() {
 			material.clear();
@@ -382,17 +480,37 @@ impl Renderer {
 		//		let default_effect_name = self.default_effect_name.clone();
 		//		self.use_effect( &default_effect_name );
 
-		unsafe {
-			let p = &self.viewport_pos;
-			let s = &self.viewport_size;
-			gl::Viewport(p.x as i32, p.y as i32, s.x as i32, s.y as i32);
-			//			gl::Scissor( self.pos.x as i32, self.pos.y as i32, self.size.x as i32, self.size.y as i32 );
-			//			gl::Enable( gl::SCISSOR_TEST );
+		let backend = Self::expect_backend(&mut self.backend);
+		if let Some(vr) = &self.virtual_resolution {
+			if vr.has_bars() {
+				backend.clear(vr.bar_color(), None);
+			}
 		}
+		backend.set_viewport(&self.viewport_pos, &self.viewport_size);
+		self.cleared_render_targets.clear();
+		self.scene_render_target = self.prepare_post_process();
+		self.bind_render_target(None);
 
 		self.color = Color::white();
 
+		if self.open_transforms > 0 {
+			tracing::warn!(
+				"{} push_transform calls without pop_transform last frame",
+				self.open_transforms
+			);
+			self.open_transforms = 0;
+		}
 		self.layer_matrix.clear();
+		self.frame_layer_mvp_matrix.clear();
+		if !self.clip_rect_stack.is_empty() {
+			tracing::warn!(
+				"{} push_clip_rect calls without pop_clip_rect last frame",
+				self.clip_rect_stack.len()
+			);
+			self.clip_rect_stack.clear();
+			self.switch_active_material_if_needed();
+		}
+		self.clip_rects.clear();
 		/*
 		for m in self.layer_matrix.iter_mut() {
 			m.clear();
@@ -401,11 +519,55 @@ impl Renderer {
 	}
 
 	pub fn end_frame(&mut self) {
+		if self.frame_depth > 1 {
+			self.frame_depth -= 1;
+			return;
+		}
+		self.frame_depth = 0;
+		if let Some(name) = self.section_render_target.take() {
+			tracing::warn!("begin_render_target {} without end_render_target", name);
+		}
+		let debug = self.frame % 500 == 0;
+		let (total_vertices, total_materials_with_vertices, total_materials) =
+			self.render_materials();
+		if self.scene_render_target.is_some() {
+			self.render_post_process();
+		}
+		self.scene_render_target = None;
+		self.bind_render_target(None);
+
+		self.backend_mut().flush();
+
+		let screenshots = self.update_queued_screenshots();
+		if !screenshots.is_empty() {
+			// tracing::debug!("Screenshots: {:#?}", screenshots);
+			for s in screenshots {
+				let s = format!("{}-{:06}", s, self.frame);
+				match self.take_screenshot(&s) {
+					Ok(_) => {},
+					Err(e) => {
+						tracing::error!("Failed taking screenshot {}", e);
+					},
+				}
+			}
+		}
+
+		if debug {
+			//			dbg!(&self.material_manager);
+			println!(
+				"Render Stats: {} {} {}",
+				total_vertices, total_materials_with_vertices, total_materials
+			);
+		}
+		self.frame += 1;
+	}
+
+	// renders, and empties all materials, into their layer's render target, or the section's
+	fn render_materials(&mut self) -> (u32, u32, u32) {
 		let mut total_vertices = 0;
 		let mut total_materials = 0;
 		let mut total_materials_with_vertices = 0;
 
-		let debug = self.frame % 500 == 0;
 		// just to avoid ghost
 
 		// moved to Effect
@@ -424,69 +586,68 @@ impl Renderer {
 			material_indices.push(i);
 		}
 
+		// layers with a render target first, so the screen can use them this frame
+		let on_screen = |m: &Material| !self.layer_render_targets.contains_key(&m.layer_id());
 		material_indices.sort_unstable_by(|a, b| {
-			let a = self.material_manager.get(*a).unwrap().key();
-			let b = self.material_manager.get(*b).unwrap().key();
+			let a = self.material_manager.get(*a).unwrap();
+			let b = self.material_manager.get(*b).unwrap();
 
-			a.partial_cmp(&b).unwrap()
+			(on_screen(a), a.key())
+				.partial_cmp(&(on_screen(b), b.key()))
+				.unwrap()
 		});
 
 		for t in self.texture_manager.iter_mut() {
-			t.update();
+			t.update(Self::expect_backend(&mut self.backend));
 		}
 
 		for i in material_indices {
-			let material = self.material_manager.get_mut(i).unwrap();
+			let material = self.material_manager.get(i).unwrap();
+			total_materials += 1;
+			if material.vertex_count() == 0 {
+				continue;
+			}
+			let layer_id = material.layer_id();
+			let material_clip_id = material.clip_id();
+			let render_target = self
+				.section_render_target
+				.clone()
+				.or_else(|| self.layer_render_targets.get(&layer_id).cloned());
+			self.bind_render_target(render_target.as_deref());
+			let scissor = self.clip_rect_in_pixels(material_clip_id);
 
+			let material = self.material_manager.get_mut(i).unwrap();
 			//			println!("SortKey: 0x{:016X}", material.key() );
 			// :TODO: ask material for effect
 			let effect_id = material.effect_id();
-			let e = match self.effects.get_mut(&effect_id) {
+			let e = match self.effects.get(&effect_id) {
 				Some(e) => e,
-				None => match self.effects.get_mut(&self.default_effect_id) {
+				None => match self.effects.get(&self.default_effect_id) {
 					Some(e) => e,
 					None => panic!("No default render Effect"),
 				},
 			};
-			material.set_mvp_matrix(&self.mvp_matrix);
-			let vc = material.render(e);
+			let mvp_matrix = self
+				.frame_layer_mvp_matrix
+				.get(&layer_id)
+				.or_else(|| self.layer_mvp_matrix.get(&layer_id))
+				.unwrap_or(&self.mvp_matrix);
+			material.set_mvp_matrix(mvp_matrix);
+			material.set_scissor(scissor);
+			let vc = material.render(e, Self::expect_backend(&mut self.backend));
+			material.clear();
 			total_vertices += vc;
-			total_materials += 1;
 			if vc > 0 {
 				total_materials_with_vertices += 1;
 			}
-			if debug {
-				//				println!("Rendered {} vertices for material {:?} with effect {:?}", vc, &material, &e );
-			}
-		}
-
-		// glFlush or glFinish
-		unsafe {
-			gl::Flush();
-		}
-
-		let screenshots = self.update_queued_screenshots();
-		if !screenshots.is_empty() {
-			// tracing::debug!("Screenshots: {:#?}", screenshots);
-			for s in screenshots {
-				let s = format!("{}-{:06}", s, self.frame);
-				match self.take_screenshot(&s) {
-					Ok(_) => {},
-					Err(e) => {
-						tracing::error!("Failed taking screenshot {}", e);
-					},
-				}
-			}
+			//				println!("Rendered {} vertices for material {:?} with effect {:?}", vc, &material, &e );
 		}
 
-		if debug {
-			//			dbg!(&self.material_manager);
-			println!(
-				"Render Stats: {} {} {}",
-				total_vertices, total_materials_with_vertices, total_materials
-			);
-		}
-		self.frame += 1;
+		(
+			total_vertices,
+			total_materials_with_vertices,
+			total_materials,
+		)
 	}
 
 	// rendering functions
@@ -494,10 +655,15 @@ impl Renderer {
 	pub fn clear(&mut self, color: &Color) {
 		//		println!("clear with {:?}", &color );
 		// glClearColor and glClear
-		unsafe {
-			gl::ClearColor(color.r, color.g, color.b, color.a);
-			gl::Clear(gl::COLOR_BUFFER_BIT); // :TODO: clear other buffers?
-		}
+		// keep the bars
+		let scissor = if self.bound_render_target.is_none()
+			&& self.virtual_resolution.is_some_and(|vr| vr.has_bars())
+		{
+			Some((&self.viewport_pos, &self.viewport_size))
+		} else {
+			None
+		};
+		Self::expect_backend(&mut self.backend).clear(color, scissor);
 	}
 
 	pub fn aspect_ratio(&self) -> f32 {
@@ -521,15 +687,310 @@ impl Renderer {
 		self.size = *size;
 	}
 
+	// in physical pixels, like WindowUpdateContext::window_size
 	pub fn set_viewport(&mut self, pos: &Vector2, size: &Vector2) {
 		self.viewport_pos = *pos;
 		self.viewport_size = *size;
 	}
 
+	// replaces the manual set_size, set_viewport, and set_mvp_matrix calls, see set_window_size
+	pub fn set_virtual_resolution(&mut self, virtual_resolution: Option<VirtualResolution>) {
+		self.virtual_resolution = virtual_resolution;
+		let window_size = self.window_size;
+		self.window_size = Vector2::zero();
+		self.set_window_size(&window_size);
+	}
+
+	pub fn virtual_resolution(&self) -> Option<&VirtualResolution> {
+		self.virtual_resolution.as_ref()
+	}
+
+	// called by begin_frame when set up with a Window, and by Game before App::update if App::renderer_mut returns one
+	// without a virtual resolution the viewport is left alone
+	pub fn set_window_size(&mut self, window_size: &Vector2) {
+		if self.window_size == *window_size {
+			return;
+		}
+		self.window_size = *window_size;
+		if let Some(vr) = &mut self.virtual_resolution {
+			vr.set_window_size(window_size);
+			self.size = *vr.design_size();
+			self.viewport_pos = *vr.viewport_pos();
+			self.viewport_size = *vr.viewport_size();
+			self.mvp_matrix = vr.mvp_matrix();
+		}
+	}
+
+	// e.g. for WindowUpdateContext::mouse_pos_pixels, origin in the center without a virtual resolution
+	pub fn window_to_design(&self, pos_pixels: &Vector2) -> Vector2 {
+		match &self.virtual_resolution {
+			Some(vr) => vr.window_to_design(pos_pixels),
+			None => pos_pixels.sub(&self.window_size.scaled(0.5)),
+		}
+	}
+
 	pub fn set_mvp_matrix(&mut self, mvp_matrix: &Matrix44) {
 		self.mvp_matrix = *mvp_matrix;
 	}
 
+	// e.g. a scrolling world layer with Camera2D::matrix, and a fixed hud layer
+	pub fn set_mvp_matrix_for_layer(&mut self, layer_id: u8, mvp_matrix: &Matrix44) {
+		self.layer_mvp_matrix.insert(layer_id, *mvp_matrix);
+	}
+
+	// back to the shared mvp matrix
+	pub fn clear_mvp_matrix_for_layer(&mut self, layer_id: u8) {
+		self.layer_mvp_matrix.remove(&layer_id);
+	}
+
+	// e.g. for a full-screen overlay, without touching the layer's own matrix
+	pub fn set_mvp_matrix_for_layer_this_frame(&mut self, layer_id: u8, mvp_matrix: &Matrix44) {
+		self.frame_layer_mvp_matrix.insert(layer_id, *mvp_matrix);
+	}
+
+	pub fn mvp_matrix_for_layer(&self, layer_id: u8) -> &Matrix44 {
+		self.frame_layer_mvp_matrix
+			.get(&layer_id)
+			.or_else(|| self.layer_mvp_matrix.get(&layer_id))
+			.unwrap_or(&self.mvp_matrix)
+	}
+
+	// registering the same name again replaces it, e.g. to follow the window size
+	pub fn register_render_target(
+		&mut self,
+		mut render_target: RenderTarget,
+	) -> anyhow::Result<()> {
+		let name = render_target.name().to_string();
+		let w = render_target.size().x as u32;
+		let h = render_target.size().y as u32;
+		let hwid = match self.find_texture_mut(&name) {
+			Some(t) => {
+				t.set_size(w, h);
+				t.hwid() as u32
+			},
+			None => {
+				let index = self.register_texture(Texture::create_render_target(&name, w, h));
+				self.texture_manager
+					.get(index as usize)
+					.map_or(0, |t| t.hwid() as u32)
+			},
+		};
+		if let Some(old) = self.render_targets.remove(&name) {
+			if self.bound_render_target.as_deref() == Some(name.as_str()) {
+				self.bind_render_target(None);
+			}
+			if let Some(framebuffer) = old.framebuffer() {
+				self.backend_mut().destroy_render_target(framebuffer);
+			}
+		}
+
+		let format = render_target.format();
+		let framebuffer = self
+			.backend_mut()
+			.create_render_target(hwid, w, h, format)
+			.ok_or_else(|| anyhow::anyhow!("Failed creating render target {}", &name))?;
+		render_target.set_framebuffer(Some(framebuffer));
+		self.render_targets.insert(name, render_target);
+		Ok(())
+	}
+
+	pub fn render_target(&self, name: &str) -> Option<&RenderTarget> {
+		self.render_targets.get(name)
+	}
+
+	// everything rendered on the layer goes to the render target, e.g. a minimap, or cached ui
+	pub fn set_render_target_for_layer(&mut self, layer_id: u8, name: &str) {
+		if !self.render_targets.contains_key(name) {
+			tracing::warn!(
+				"Render target {} not found, layer {} renders to the screen",
+				name,
+				layer_id
+			);
+		}
+		self.layer_render_targets.insert(layer_id, name.to_string());
+	}
+
+	// back to the screen
+	pub fn clear_render_target_for_layer(&mut self, layer_id: u8) {
+		self.layer_render_targets.remove(&layer_id);
+	}
+
+	// everything rendered until end_render_target goes to the render target, on all layers
+	// the batches so far are rendered first, so this costs a few extra draw calls
+	pub fn begin_render_target(&mut self, name: &str) {
+		if let Some(active) = &self.section_render_target {
+			tracing::warn!("begin_render_target {} while {} is active", name, active);
+		}
+		if !self.render_targets.contains_key(name) {
+			tracing::warn!("Render target {} not found, rendering to the screen", name);
+		}
+		self.render_materials();
+		self.section_render_target = Some(name.to_string());
+		self.bind_render_target(Some(name));
+	}
+
+	pub fn end_render_target(&mut self) {
+		if self.section_render_target.is_none() {
+			tracing::warn!("end_render_target without begin_render_target");
+			return;
+		}
+		self.render_materials();
+		self.section_render_target = None;
+		self.bind_render_target(None);
+	}
+
+	// unknown names bind the screen, which is offscreen while post processing
+	fn bind_render_target(&mut self, name: Option<&str>) {
+		let name = name.or(self.scene_render_target.as_deref());
+		let render_target = name.and_then(|n| self.render_targets.get(n));
+		let name = render_target.map(|rt| rt.name().to_string());
+		if self.bound_render_target == name {
+			return;
+		}
+		match render_target {
+			Some(rt) => {
+				let clear_color = rt
+					.clear_color()
+					.filter(|_| !self.cleared_render_targets.contains(rt.name()));
+				let backend = Self::expect_backend(&mut self.backend);
+				backend.bind_render_target(rt.framebuffer());
+				backend.set_viewport(&Vector2::zero(), rt.size());
+				if let Some(color) = clear_color {
+					backend.clear(color, None);
+				}
+				self.cleared_render_targets.insert(rt.name().to_string());
+			},
+			None => {
+				let backend = Self::expect_backend(&mut self.backend);
+				backend.bind_render_target(None);
+				backend.set_viewport(&self.viewport_pos, &self.viewport_size);
+			},
+		}
+		self.bound_render_target = name;
+	}
+
+	pub fn post_process(&self) -> &PostProcessChain {
+		&self.post_process
+	}
+
+	// changes are picked up in begin_frame
+	pub fn post_process_mut(&mut self) -> &mut PostProcessChain {
+		&mut self.post_process
+	}
+
+	pub fn set_post_process(&mut self, post_process: PostProcessChain) {
+		self.post_process = post_process;
+	}
+
+	// enabled passes with a registered effect, the others are skipped until it is registered
+	fn runnable_post_process_passes(&self) -> Vec<PostProcessPass> {
+		self.post_process
+			.enabled_passes()
+			.filter(|p| {
+				let found = self.effects.contains_key(&p.effect_id());
+				if !found {
+					tracing::debug!(
+						"Effect {} for post process pass {} not found, skipping it",
+						p.effect_id(),
+						p.name()
+					);
+				}
+				found
+			})
+			.cloned()
+			.collect()
+	}
+
+	// returns the render target for the frame, or None when no pass can run
+	fn prepare_post_process(&mut self) -> Option<String> {
+		if self.runnable_post_process_passes().is_empty() {
+			return None;
+		}
+
+		let format = self.post_process.format();
+		for name in POST_PROCESS_TARGETS {
+			let render_target = RenderTarget::new(name, &self.viewport_size)
+				.with_format(format)
+				.with_clear_color(Some(Color::from_rgba(0.0, 0.0, 0.0, 0.0)));
+			let up_to_date = self.render_targets.get(name).is_some_and(|rt| {
+				rt.size() == render_target.size() && rt.format() == render_target.format()
+			});
+			if !up_to_date {
+				if let Err(e) = self.register_render_target(render_target) {
+					tracing::warn!("Post processing disabled: {}", e);
+					return None;
+				}
+			}
+		}
+		Some(POST_PROCESS_TARGETS[0].to_string())
+	}
+
+	// the frame is in the first target, the last pass renders to the screen
+	fn render_post_process(&mut self) {
+		let passes = self.runnable_post_process_passes();
+		let default_hwid = self.texture_manager.get(0).map_or(0, |t| t.hwid());
+		let v = |x: f32, y: f32| {
+			Vertex::from_pos_with_tex_coords_and_color(
+				&Vector2::new(x, y),
+				&Vector2::new((x + 1.0) * 0.5, (y + 1.0) * 0.5),
+				&Color::white(),
+			)
+		};
+		let vertices = [
+			v(-1.0, 1.0),
+			v(-1.0, -1.0),
+			v(1.0, -1.0),
+			v(1.0, -1.0),
+			v(1.0, 1.0),
+			v(-1.0, 1.0),
+		];
+
+		let mut source = 0;
+		for (i, pass) in passes.iter().enumerate() {
+			let mut textures = vec![default_hwid; MAX_TEXTURE_CHANNELS];
+			for (channel, name) in pass.textures() {
+				let hwid = self.find_texture_mut(name).map(|t| t.hwid());
+				match (textures.get_mut(*channel as usize), hwid) {
+					(Some(t), Some(hwid)) => *t = hwid,
+					_ => tracing::warn!("Texture {} for channel {} not found", name, channel),
+				}
+			}
+			if let Some(hwid) = self
+				.find_texture_mut(POST_PROCESS_TARGETS[source])
+				.map(|t| t.hwid())
+			{
+				textures[0] = hwid;
+			}
+
+			if i + 1 == passes.len() {
+				self.scene_render_target = None;
+				self.bind_render_target(None);
+			} else {
+				source = 1 - source;
+				self.bind_render_target(Some(POST_PROCESS_TARGETS[source]));
+			}
+
+			let Some(effect) = self.effects.get(&pass.effect_id()) else {
+				continue;
+			};
+			let (blend_source, blend_destination) = effect.blend_func();
+			let draw_call = DrawCall {
+				vertices: &vertices,
+				program: effect.program().id(),
+				textures: &textures,
+				mvp_matrix: &Matrix44::identity(),
+				uniforms: pass.uniforms(),
+				cull_face: effect.cull_face(),
+				depth_test: effect.depth_test(),
+				blend_source,
+				blend_destination,
+				textured: effect.textured(),
+				scissor: None,
+			};
+			Self::expect_backend(&mut self.backend).draw(&draw_call);
+		}
+	}
+
 	pub fn set_tex_matrix(&mut self, tex_matrix: &Matrix32) {
 		self.tex_matrix = *tex_matrix;
 	}
@@ -560,7 +1021,8 @@ impl Renderer {
 			.map(|&t| t.hwid())
 			.collect::<Vec<_>>()
 			.to_vec();
-		let key = Material::calculate_key(lid, eid, &tids);
+		let clip_id = self.active_clip_id();
+		let key = Material::calculate_key_with_clip_id(lid, eid, &tids, clip_id);
 		let can_render = {
 			let m = self.material_manager.get_active();
 			m.can_render(key)
@@ -584,7 +1046,8 @@ impl Renderer {
 					let ti = self.active_textures[i].unwrap_or(0);
 					textures.push(self.texture_manager.get(ti as usize).unwrap());
 				}
-				let m = Material::new(self.active_layer_id, &self.get_active_effect(), textures);
+				let m = Material::new(self.active_layer_id, self.get_active_effect(), textures)
+					.with_clip_id(clip_id);
 				let i = self.material_manager.add(m);
 				self.material_manager.set_active(i);
 			}
@@ -692,6 +1155,130 @@ impl Renderer {
 		lm.push_multiply(&s);
 	}
 
+	// applied on the cpu to vertices added to the layer until the matching pop_transform
+	// e.g. Matrix44::translation, Matrix44::rotation_z, or Matrix44::scaling_xy
+	pub fn push_transform(&mut self, layer_id: u8, transform: &Matrix44) {
+		self.layer_matrix
+			.entry(layer_id)
+			.or_default()
+			.push_multiply(transform);
+		self.open_transforms += 1;
+	}
+
+	pub fn pop_transform(&mut self, layer_id: u8) {
+		match self.layer_matrix.get_mut(&layer_id) {
+			Some(lm) if lm.depth() > 0 => {
+				lm.pop();
+				self.open_transforms = self.open_transforms.saturating_sub(1);
+			},
+			_ => tracing::warn!(
+				"pop_transform without push_transform for layer {}",
+				layer_id
+			),
+		}
+	}
+
+	// content added until the matching pop_clip_rect is clipped, pos is the center like for render_quad
+	// in the active layer's coordinates, with its matrices at the time of the push,
+	// nested rectangles are intersected, rotated ones clip to their bounding box
+	pub fn push_clip_rect(&mut self, pos: &Vector2, size: &Vector2) {
+		let lm = self.layer_matrix.entry(self.active_layer_id).or_default();
+		let m = *lm.top();
+		let mvp = self.mvp_matrix_for_layer(self.active_layer_id);
+		let hs = size.scaled(0.5);
+		let mut min = Vector2::new(f32::MAX, f32::MAX);
+		let mut max = Vector2::new(f32::MIN, f32::MIN);
+		for (x, y) in [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)] {
+			let p = m * Vector2::new(pos.x + x * hs.x, pos.y + y * hs.y);
+			// column major, like the gpu sees it
+			let w = mvp[3] * p.x + mvp[7] * p.y + mvp[15];
+			let w = if w == 0.0 { 1.0 } else { w };
+			let ndc = Vector2::new(
+				(mvp[0] * p.x + mvp[4] * p.y + mvp[12]) / w,
+				(mvp[1] * p.x + mvp[5] * p.y + mvp[13]) / w,
+			);
+			min = Vector2::new(min.x.min(ndc.x), min.y.min(ndc.y));
+			max = Vector2::new(max.x.max(ndc.x), max.y.max(ndc.y));
+		}
+		if let Some((pmin, pmax)) = self.active_clip_rect() {
+			min = Vector2::new(min.x.max(pmin.x), min.y.max(pmin.y));
+			max = Vector2::new(max.x.min(pmax.x).max(min.x), max.y.min(pmax.y).max(min.y));
+		}
+
+		// the same rectangle shares batches
+		let clip_id = match self.clip_rects.iter().position(|r| *r == (min, max)) {
+			Some(i) => i as u16 + 1,
+			None if self.clip_rects.len() >= u16::MAX as usize => {
+				// 0 is unclipped, so ids can't wrap around,
+				// render everything using them so far, and keep only the pushed ones
+				tracing::debug!("Out of clip ids, rendering the batches so far");
+				self.render_materials();
+				self.clip_rects = self
+					.clip_rect_stack
+					.iter()
+					.map(|id| self.clip_rects[*id as usize - 1])
+					.collect();
+				for (i, id) in self.clip_rect_stack.iter_mut().enumerate() {
+					*id = i as u16 + 1;
+				}
+				self.clip_rects.push((min, max));
+				self.clip_rects.len() as u16
+			},
+			None => {
+				self.clip_rects.push((min, max));
+				self.clip_rects.len() as u16
+			},
+		};
+		self.clip_rect_stack.push(clip_id);
+		self.switch_active_material_if_needed();
+	}
+
+	pub fn pop_clip_rect(&mut self) {
+		if self.clip_rect_stack.pop().is_none() {
+			tracing::warn!("pop_clip_rect without push_clip_rect");
+			return;
+		}
+		self.switch_active_material_if_needed();
+	}
+
+	fn active_clip_id(&self) -> u16 {
+		self.clip_rect_stack.last().copied().unwrap_or(0)
+	}
+
+	fn active_clip_rect(&self) -> Option<(Vector2, Vector2)> {
+		match self.active_clip_id() {
+			0 => None,
+			id => self.clip_rects.get(id as usize - 1).copied(),
+		}
+	}
+
+	// pos and size in pixels of the bound framebuffer
+	fn clip_rect_in_pixels(&self, clip_id: u16) -> Option<(Vector2, Vector2)> {
+		let (min, max) = self.clip_rects.get((clip_id as usize).checked_sub(1)?)?;
+		let (vp_pos, vp_size) = match self
+			.bound_render_target
+			.as_ref()
+			.and_then(|n| self.render_targets.get(n))
+		{
+			Some(rt) => (Vector2::zero(), *rt.size()),
+			None => (self.viewport_pos, self.viewport_size),
+		};
+		let to_pixels = |p: &Vector2| {
+			Vector2::new(
+				vp_pos.x + (p.x * 0.5 + 0.5) * vp_size.x,
+				vp_pos.y + (p.y * 0.5 + 0.5) * vp_size.y,
+			)
+		};
+		let min = to_pixels(min);
+		let max = to_pixels(max);
+		let pos = Vector2::new(min.x.round(), min.y.round());
+		let size = Vector2::new(
+			(max.x.round() - pos.x).max(0.0),
+			(max.y.round() - pos.y).max(0.0),
+		);
+		Some((pos, size))
+	}
+
 	pub fn add_vertex(&mut self, pos: &Vector2) -> u32 {
 		let lm = self
 			.layer_matrix
@@ -901,6 +1488,11 @@ impl Renderer {
 			self.add_triangle( v[ 2 ], v[ 3 ], v[ 0 ] );
 		}
 	*/
+	// by the index from register_texture
+	pub fn texture(&self, index: u16) -> Option<&Texture> {
+		self.texture_manager.get(index as usize)
+	}
+
 	pub fn find_texture_mut(&mut self, name: &str) -> Option<&mut Texture> {
 		self.texture_manager.find_mut(|t| t.name() == name)
 	}
@@ -1023,67 +1615,49 @@ impl Renderer {
 		tracing::debug!("save_screenshot: {}", filename);
 		let w = self.viewport_size.x as usize;
 		let h = self.viewport_size.y as usize;
-		let len = w * h * 4;
-		let v: Vec<u8> = vec![0; len];
-		let mut buffer = v.into_boxed_slice();
-		unsafe {
-			// :TODO:
-			// gl::ReadBuffer
-			// gl::PixelTransfer
-			// gl::PixelMap
-			gl::ReadPixels(
-				0,
-				0,
-				w as i32,
-				h as i32,
-				gl::RGBA,
-				gl::UNSIGNED_BYTE,
-				buffer.as_mut_ptr() as *mut core::ffi::c_void,
-			);
-		}
+		let buffer = self.read_pixels(w, h);
+		let png_buffer = Self::encode_png(w, h, &buffer)?;
+
+		self.ready_screenshots.push_back(ReadyScreenshot {
+			filename: format!("{}.png", filename),
+			data:     png_buffer,
+		});
+
+		Ok(())
+	}
+	// rgba, top row first, from the bottom left of the current backend
+	pub fn read_pixels(&mut self, w: usize, h: usize) -> Vec<u8> {
+		let buffer = self.backend_mut().read_pixels(w, h);
 
 		// flip buffer upside down
-		// :TODO: we can make this far more efficient
-		let mut buffer_flipped = vec![0; len];
+		let mut buffer_flipped = vec![0; w * h * 4];
 		for y in 0..h {
-			for x in 0..w {
-				let sp = (w * y + x) * 4;
-				let dp = (w * (h - y - 1) + x) * 4;
-				buffer_flipped[dp] = buffer[sp];
-				buffer_flipped[dp + 1] = buffer[sp + 1];
-				buffer_flipped[dp + 2] = buffer[sp + 2];
-				buffer_flipped[dp + 3] = buffer[sp + 3];
-			}
+			let sp = w * y * 4;
+			let dp = w * (h - y - 1) * 4;
+			buffer_flipped[dp..dp + w * 4].copy_from_slice(&buffer[sp..sp + w * 4]);
 		}
+		buffer_flipped
+	}
 
-		let buffer = buffer_flipped;
-
+	// rgba, top row first
+	pub fn encode_png(w: usize, h: usize, rgba: &[u8]) -> anyhow::Result<Vec<u8>> {
 		let mut png_buffer = Vec::new();
 		{
 			let mut encoder = png::Encoder::new(Cursor::new(&mut png_buffer), w as u32, h as u32);
 			encoder.set_color(png::ColorType::Rgba);
 			encoder.set_depth(png::BitDepth::Eight);
 
-			encoder
-				.add_itxt_chunk("Creator".to_string(), "oml-game".to_string())
-				.unwrap();
-			let mut writer = encoder.write_header().unwrap();
-			writer.write_image_data(&buffer).unwrap();
+			encoder.add_itxt_chunk("Creator".to_string(), "oml-game".to_string())?;
+			let mut writer = encoder.write_header()?;
+			writer.write_image_data(rgba)?;
 		}
 
 		if png_buffer[0] != 0x89 {
 			anyhow::bail!("Broken header from PNG");
-		} else {
-			tracing::debug!("OK");
 		}
-
-		self.ready_screenshots.push_back(ReadyScreenshot {
-			filename: format!("{}.png", filename),
-			data:     png_buffer,
-		});
-
-		Ok(())
+		Ok(png_buffer)
 	}
+
 	pub fn queue_screenshot(&mut self, delay: usize, frames: usize, filename: Option<&str>) {
 		let qs = QueuedScreenshot {
 			delay,
@@ -1124,6 +1698,45 @@ impl Renderer {
 	}
 }
 
+// shared by the renderer tests
+#[cfg(test)]
+impl Renderer {
+	// software backend, effect 0 draws the vertex color, one unit per pixel around the center
+	pub(crate) fn software_for_tests(w: usize, h: usize) -> Self {
+		let mut renderer = Renderer::new();
+		renderer
+			.setup_with_backend(Box::new(SoftwareBackend::new(w, h)))
+			.unwrap();
+		renderer.register_effect(Effect::from_source(0, "colored", "", ""));
+		let (w, h) = (w as f32, h as f32);
+		renderer.set_window_size(&Vector2::new(w, h));
+		renderer.set_viewport(&Vector2::zero(), &Vector2::new(w, h));
+		renderer.set_mvp_matrix(&Matrix44::ortho(
+			-0.5 * w,
+			0.5 * w,
+			-0.5 * h,
+			0.5 * h,
+			1.0,
+			-1.0,
+		));
+		renderer
+	}
+
+	// rgba of the bound target, or the window sized screen, from the bottom left
+	pub(crate) fn pixel_for_tests(&mut self, x: usize, y: usize) -> [u8; 4] {
+		let size = self
+			.bound_render_target
+			.as_ref()
+			.and_then(|name| self.render_targets.get(name))
+			.map_or(self.window_size, |rt| *rt.size());
+		let (w, h) = (size.x as usize, size.y as usize);
+		assert!(x < w && y < h, "{} {} outside of {}x{}", x, y, w, h);
+		let buffer = self.backend_mut().read_pixels(w, h);
+		let p = (y * w + x) * 4;
+		[buffer[p], buffer[p + 1], buffer[p + 2], buffer[p + 3]]
+	}
+}
+
 #[derive(Debug)]
 struct Manager<T> {
 	materials:    Vec<T>,
@@ -1244,8 +1857,12 @@ mod animated_texture;
 pub use animated_texture::AnimatedTexture;
 pub use animated_texture::AnimatedTextureConfiguration;
 
+mod backend;
+pub use backend::{DrawCall, RendererBackend};
 mod blend_factor;
 pub use blend_factor::BlendFactor;
+mod camera2d;
+pub use camera2d::Camera2D;
 
 mod debug;
 pub use debug::Debug;
@@ -1259,13 +1876,21 @@ mod effect;
 pub use effect::Effect;
 mod font;
 pub use font::Font;
+mod gl_backend;
+pub use gl_backend::GlBackend;
 mod material;
 pub use material::Material;
 //mod material_builder;
 //	pub use material_builder::MaterialBuilder as MaterialBuilder;
+mod post_process;
+pub use post_process::{PostProcessChain, PostProcessPass};
+mod post_process_shader;
+pub use post_process_shader::PostProcessShader;
 mod program;
 pub use program::Program;
 pub use program::ShaderType;
+mod render_target;
+pub use render_target::{RenderTarget, RenderTargetFormat};
 mod text_layout;
 pub use text_layout::TextLayout;
 mod texture;
@@ -1274,6 +1899,108 @@ mod texture_atlas;
 pub use texture_atlas::TextureAtlas;
 mod uniform;
 pub use uniform::Uniform;
+mod virtual_resolution;
+pub use virtual_resolution::{ScalingPolicy, VirtualResolution};
 
 mod sixteen_segment;
 pub use sixteen_segment::SixteenSegment;
+mod software_backend;
+pub use software_backend::SoftwareBackend;
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn rendered(renderer: &mut Renderer) -> Vec<bool> {
+		(0..64)
+			.map(|i| renderer.pixel_for_tests(i % 8, i / 8)[0] > 0)
+			.collect()
+	}
+
+	// x0..x1, y0..y1 in pixels, y up
+	fn rect(x0: usize, x1: usize, y0: usize, y1: usize) -> Vec<bool> {
+		(0..64)
+			.map(|i| (x0..x1).contains(&(i % 8)) && (y0..y1).contains(&(i / 8)))
+			.collect()
+	}
+
+	#[test]
+	fn clip_rects_nest_and_follow_the_layer() -> anyhow::Result<()> {
+		let mut renderer = Renderer::software_for_tests(8, 8);
+		renderer.set_mvp_matrix_for_layer(1, &Matrix44::ortho(-2.0, 2.0, -2.0, 2.0, 1.0, -1.0));
+		let full = Vector2::new(8.0, 8.0);
+
+		renderer.begin_frame();
+		renderer.clear(&Color::black());
+		renderer.push_clip_rect(&Vector2::new(-2.0, 0.0), &Vector2::new(4.0, 8.0));
+		renderer.render_quad(&Vector2::zero(), &full);
+		renderer.push_clip_rect(&Vector2::zero(), &Vector2::new(8.0, 2.0));
+		renderer.pop_clip_rect();
+		renderer.pop_clip_rect();
+		renderer.end_frame();
+		assert_eq!(rect(0, 4, 0, 8), rendered(&mut renderer));
+
+		// nested ones intersect
+		renderer.begin_frame();
+		renderer.clear(&Color::black());
+		renderer.push_clip_rect(&Vector2::new(-2.0, 0.0), &Vector2::new(4.0, 8.0));
+		renderer.push_clip_rect(&Vector2::zero(), &Vector2::new(8.0, 2.0));
+		renderer.render_quad(&Vector2::zero(), &full);
+		renderer.pop_clip_rect();
+		renderer.pop_clip_rect();
+		renderer.end_frame();
+		assert_eq!(rect(0, 4, 3, 5), rendered(&mut renderer));
+
+		// in the layer's coordinates, with its transforms
+		renderer.begin_frame();
+		renderer.clear(&Color::black());
+		renderer.use_layer(1);
+		renderer.push_transform(1, &Matrix44::translation(&Vector3::new(1.0, 0.0, 0.0)));
+		renderer.push_clip_rect(&Vector2::new(-1.0, 0.0), &Vector2::new(2.0, 2.0));
+		renderer.pop_transform(1);
+		renderer.render_quad(&Vector2::zero(), &full);
+		renderer.pop_clip_rect();
+		// not clipped
+		renderer.use_layer(0);
+		renderer.render_quad(&Vector2::new(3.5, 3.5), &Vector2::new(1.0, 1.0));
+		renderer.end_frame();
+		let mut expected = rect(2, 6, 2, 6);
+		expected[63] = true;
+		assert_eq!(expected, rendered(&mut renderer));
+		Ok(())
+	}
+
+	#[test]
+	fn running_out_of_clip_ids_keeps_clipping() -> anyhow::Result<()> {
+		let mut renderer = Renderer::software_for_tests(8, 8);
+		let red = Color::from_rgba(1.0, 0.0, 0.0, 1.0);
+		let green = Color::from_rgba(0.0, 1.0, 0.0, 1.0);
+
+		renderer.begin_frame();
+		renderer.clear(&Color::black());
+		renderer.push_clip_rect(&Vector2::new(-2.0, 0.0), &Vector2::new(4.0, 8.0));
+		renderer.set_color(&red);
+		renderer.render_quad(&Vector2::zero(), &Vector2::new(8.0, 8.0));
+		// as if many more had been pushed, and popped
+		let unused = (Vector2::zero(), Vector2::zero());
+		renderer.clip_rects.resize(u16::MAX as usize, unused);
+		renderer.push_clip_rect(&Vector2::zero(), &Vector2::new(8.0, 2.0));
+		assert_eq!(2, renderer.clip_rects.len());
+		renderer.set_color(&green);
+		renderer.render_quad(&Vector2::zero(), &Vector2::new(8.0, 8.0));
+		renderer.pop_clip_rect();
+		renderer.pop_clip_rect();
+		renderer.end_frame();
+
+		for (x, y, color) in [
+			(0, 0, [255, 0, 0, 255]),
+			(3, 7, [255, 0, 0, 255]),
+			(0, 3, [0, 255, 0, 255]),
+			(3, 4, [0, 255, 0, 255]),
+			(4, 4, [0, 0, 0, 255]),
+		] {
+			assert_eq!(color, renderer.pixel_for_tests(x, y), "{} {}", x, y);
+		}
+		Ok(())
+	}
+}
@@ -0,0 +1,56 @@
+use std::collections::HashMap;
+
+use crate::math::{Matrix44, Vector2};
+use crate::renderer::{BlendFactor, Color, RenderTargetFormat, ShaderType, Uniform, Vertex};
+
+// everything needed to draw one material
+#[derive(Debug)]
+pub struct DrawCall<'a> {
+	pub vertices:          &'a [Vertex], // triangles
+	pub program:           u32,
+	pub textures:          &'a [u16],    // one per channel
+	pub mvp_matrix:        &'a Matrix44, // column major, like Matrix44::ortho
+	pub uniforms:          &'a HashMap<String, Uniform>,
+	pub cull_face:         bool,
+	pub depth_test:        bool,
+	pub blend_source:      BlendFactor,
+	pub blend_destination: BlendFactor,
+	pub textured:          bool, // see Effect::with_textured
+	pub scissor:           Option<(&'a Vector2, &'a Vector2)>, // pos and size in pixels, like clear
+}
+
+// the gpu facing part of the renderer, see GlBackend and SoftwareBackend
+// owned by the Renderer, textures and programs are created through it when registered
+pub trait RendererBackend: std::fmt::Debug {
+	fn name(&self) -> &str;
+
+	fn create_texture(&mut self) -> u32;
+	// rgba, first row first
+	fn upload_texture(&mut self, hwid: u32, width: u32, height: u32, rgba: &[u8]);
+
+	// allocates the texture, and attaches it to a new framebuffer, returns None when that is not supported
+	fn create_render_target(
+		&mut self,
+		texture: u32,
+		width: u32,
+		height: u32,
+		format: RenderTargetFormat,
+	) -> Option<u32>;
+	fn destroy_render_target(&mut self, framebuffer: u32);
+	// None is the default framebuffer
+	fn bind_render_target(&mut self, framebuffer: Option<u32>);
+
+	// returns None when compiling or linking failed
+	fn create_program(&mut self, shaders: &[(ShaderType, String)]) -> Option<u32>;
+	fn uniform_location(&mut self, program: u32, name: &str) -> Option<i32>;
+
+	// in physical pixels, origin bottom left
+	fn set_viewport(&mut self, pos: &Vector2, size: &Vector2);
+	// the scissor rectangle is pos and size in pixels
+	fn clear(&mut self, color: &Color, scissor: Option<(&Vector2, &Vector2)>);
+	fn draw(&mut self, draw_call: &DrawCall);
+	fn flush(&mut self);
+
+	// rgba, bottom row first, like glReadPixels
+	fn read_pixels(&mut self, width: usize, height: usize) -> Vec<u8>;
+}
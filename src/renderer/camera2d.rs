@@ -0,0 +1,305 @@
+use crate::math::{Matrix44, Rectangle, Vector2};
+
+// screen positions have their origin in the center, y up, in viewport units,
+// e.g. design coordinates from Renderer::window_to_design
+#[derive(Debug, Clone)]
+pub struct Camera2D {
+	pos:           Vector2,
+	zoom:          f32,
+	rotation:      f32, // in radians, counter clockwise
+	viewport_size: Vector2,
+	bounds:        Option<Rectangle>,
+
+	target:           Option<Vector2>,
+	follow_smoothing: f32, // seconds to cover ~63% of the distance, 0 snaps
+
+	trauma:           f32, // 0..1, shake is trauma squared
+	trauma_decay:     f32, // per second
+	max_shake_offset: Vector2,
+	max_shake_angle:  f32,
+	shake_time:       f32,
+	shake_offset:     Vector2,
+	shake_angle:      f32,
+}
+
+impl Camera2D {
+	pub fn new(viewport_size: &Vector2) -> Self {
+		Self {
+			pos:           Vector2::zero(),
+			zoom:          1.0,
+			rotation:      0.0,
+			viewport_size: *viewport_size,
+			bounds:        None,
+
+			target:           None,
+			follow_smoothing: 0.2,
+
+			trauma:           0.0,
+			trauma_decay:     1.0,
+			max_shake_offset: viewport_size.scaled(0.02),
+			max_shake_angle:  0.05,
+			shake_time:       0.0,
+			shake_offset:     Vector2::zero(),
+			shake_angle:      0.0,
+		}
+	}
+
+	// the visible world area is kept inside, and centered when it is bigger
+	pub fn with_bounds(mut self, bounds: Option<Rectangle>) -> Self {
+		self.bounds = bounds;
+		self.clamp_to_bounds();
+		self
+	}
+
+	pub fn with_follow_smoothing(mut self, follow_smoothing: f32) -> Self {
+		self.follow_smoothing = follow_smoothing;
+		self
+	}
+
+	pub fn with_trauma_decay(mut self, trauma_decay: f32) -> Self {
+		self.trauma_decay = trauma_decay;
+		self
+	}
+
+	// at full trauma, in viewport units and radians
+	pub fn with_max_shake(mut self, offset: &Vector2, angle: f32) -> Self {
+		self.max_shake_offset = *offset;
+		self.max_shake_angle = angle;
+		self
+	}
+
+	pub fn pos(&self) -> &Vector2 {
+		&self.pos
+	}
+
+	pub fn set_pos(&mut self, pos: &Vector2) {
+		self.pos = *pos;
+		self.clamp_to_bounds();
+	}
+
+	pub fn zoom(&self) -> f32 {
+		self.zoom
+	}
+
+	// bigger values show less of the world
+	pub fn set_zoom(&mut self, zoom: f32) {
+		self.zoom = zoom.max(0.0001);
+		self.clamp_to_bounds();
+	}
+
+	pub fn rotation(&self) -> f32 {
+		self.rotation
+	}
+
+	pub fn set_rotation(&mut self, rotation: f32) {
+		self.rotation = rotation;
+	}
+
+	pub fn viewport_size(&self) -> &Vector2 {
+		&self.viewport_size
+	}
+
+	pub fn set_viewport_size(&mut self, viewport_size: &Vector2) {
+		self.viewport_size = *viewport_size;
+		self.clamp_to_bounds();
+	}
+
+	pub fn set_bounds(&mut self, bounds: Option<Rectangle>) {
+		self.bounds = bounds;
+		self.clamp_to_bounds();
+	}
+
+	// the world area covered, ignoring rotation and shake
+	pub fn visible_size(&self) -> Vector2 {
+		self.viewport_size.scaled(1.0 / self.zoom)
+	}
+
+	// followed smoothly in update, None stops following
+	pub fn set_target(&mut self, target: Option<Vector2>) {
+		self.target = target;
+	}
+
+	pub fn trauma(&self) -> f32 {
+		self.trauma
+	}
+
+	// e.g. 0.3 for a hit, 1.0 for an explosion
+	pub fn add_trauma(&mut self, amount: f32) {
+		self.trauma = (self.trauma + amount).clamp(0.0, 1.0);
+	}
+
+	pub fn update(&mut self, time_step: f64) {
+		let time_step = time_step as f32;
+		if let Some(target) = self.target {
+			let t = if self.follow_smoothing > 0.0 {
+				1.0 - (-time_step / self.follow_smoothing).exp()
+			} else {
+				1.0
+			};
+			self.pos = self.pos.add(&target.sub(&self.pos).scaled(t));
+			self.clamp_to_bounds();
+		}
+
+		self.trauma = (self.trauma - self.trauma_decay * time_step).max(0.0);
+		self.shake_time += time_step;
+		let shake = self.trauma * self.trauma;
+		if shake > 0.0 {
+			// smooth, and deterministic for replays
+			self.shake_offset = Vector2::new(
+				self.max_shake_offset.x * shake * Self::noise(self.shake_time, 0.0),
+				self.max_shake_offset.y * shake * Self::noise(self.shake_time, 1.7),
+			);
+			self.shake_angle = self.max_shake_angle * shake * Self::noise(self.shake_time, 3.1);
+		} else {
+			self.shake_offset = Vector2::zero();
+			self.shake_angle = 0.0;
+		}
+	}
+
+	fn noise(t: f32, seed: f32) -> f32 {
+		((t * 23.0 + seed).sin() + (t * 37.3 + seed * 2.3).sin() * 0.5) / 1.5
+	}
+
+	fn clamp_to_bounds(&mut self) {
+		let Some(bounds) = &self.bounds else {
+			return;
+		};
+		let hs = self.visible_size().scaled(0.5);
+		let axis = |p: f32, min: f32, max: f32, half: f32| {
+			if max - min < 2.0 * half {
+				(min + max) * 0.5
+			} else {
+				p.clamp(min + half, max - half)
+			}
+		};
+		self.pos = Vector2::new(
+			axis(self.pos.x, bounds.left(), bounds.right(), hs.x),
+			axis(self.pos.y, bounds.bottom(), bounds.top(), hs.y),
+		);
+	}
+
+	fn view_pos(&self) -> Vector2 {
+		let (s, c) = self.rotation.sin_cos();
+		let o = &self.shake_offset;
+		// the shake is in screen space
+		self.pos
+			.add(&Vector2::new(o.x * c - o.y * s, o.x * s + o.y * c).scaled(1.0 / self.zoom))
+	}
+
+	fn view_rotation(&self) -> f32 {
+		self.rotation + self.shake_angle
+	}
+
+	// for Renderer::set_mvp_matrix, column major like Matrix44::ortho
+	pub fn matrix(&self) -> Matrix44 {
+		let hs = self.visible_size().scaled(0.5);
+		let projection = Matrix44::ortho(-hs.x, hs.x, -hs.y, hs.y, 1.0, -1.0);
+
+		// rotate by -rotation around the camera position
+		let (s, c) = self.view_rotation().sin_cos();
+		let p = self.view_pos();
+		let tx = -(c * p.x + s * p.y);
+		let ty = -(-s * p.x + c * p.y);
+		#[rustfmt::skip]
+		let view = Matrix44::new(
+			c,   -s,  0.0, 0.0,
+			s,   c,   0.0, 0.0,
+			0.0, 0.0, 1.0, 0.0,
+			tx,  ty,  0.0, 1.0,
+		);
+		view.multiply(&projection)
+	}
+
+	pub fn screen_to_world(&self, pos: &Vector2) -> Vector2 {
+		let (s, c) = self.view_rotation().sin_cos();
+		let p = pos.scaled(1.0 / self.zoom);
+		self.view_pos()
+			.add(&Vector2::new(c * p.x - s * p.y, s * p.x + c * p.y))
+	}
+
+	pub fn world_to_screen(&self, pos: &Vector2) -> Vector2 {
+		let (s, c) = self.view_rotation().sin_cos();
+		let p = pos.sub(&self.view_pos());
+		Vector2::new(c * p.x + s * p.y, -s * p.x + c * p.y).scaled(self.zoom)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn assert_near(a: &Vector2, b: &Vector2) {
+		assert!(a.sub(b).length() < 0.001, "{:?} != {:?}", a, b);
+	}
+
+	// what the gpu does with the column major matrix
+	fn to_clip(m: &Matrix44, p: &Vector2) -> Vector2 {
+		Vector2::new(
+			m[0] * p.x + m[4] * p.y + m[12],
+			m[1] * p.x + m[5] * p.y + m[13],
+		)
+	}
+
+	#[test]
+	fn matrix_and_conversions_agree() {
+		let mut camera = Camera2D::new(&Vector2::new(200.0, 100.0));
+		camera.set_pos(&Vector2::new(50.0, -20.0));
+		camera.set_zoom(2.0);
+		camera.set_rotation(0.5);
+
+		let world = Vector2::new(70.0, -5.0);
+		let screen = camera.world_to_screen(&world);
+		assert_near(&world, &camera.screen_to_world(&screen));
+		// screen is -half..half of the viewport, clip space -1..1
+		let clip = to_clip(&camera.matrix(), &world);
+		assert_near(&Vector2::new(screen.x / 100.0, screen.y / 50.0), &clip);
+
+		assert_near(&Vector2::zero(), &to_clip(&camera.matrix(), camera.pos()));
+		assert_near(camera.pos(), &camera.screen_to_world(&Vector2::zero()));
+	}
+
+	#[test]
+	fn bounds_keep_the_view_inside() {
+		let bounds = Rectangle::default()
+			.with_bottom_left(&Vector2::new(0.0, 0.0))
+			.with_size(&Vector2::new(1000.0, 80.0));
+		let mut camera = Camera2D::new(&Vector2::new(200.0, 100.0)).with_bounds(Some(bounds));
+
+		camera.set_pos(&Vector2::new(-500.0, 0.0));
+		// too low to fit vertically, so it is centered
+		assert_eq!(Vector2::new(100.0, 40.0), *camera.pos());
+		camera.set_pos(&Vector2::new(2000.0, 0.0));
+		assert_eq!(Vector2::new(900.0, 40.0), *camera.pos());
+		camera.set_zoom(2.0);
+		camera.set_pos(&Vector2::new(2000.0, 0.0));
+		assert_eq!(Vector2::new(950.0, 25.0), *camera.pos());
+	}
+
+	#[test]
+	fn follow_is_smooth_and_shake_decays() {
+		let mut camera = Camera2D::new(&Vector2::new(200.0, 100.0))
+			.with_follow_smoothing(0.1)
+			.with_trauma_decay(2.0);
+		camera.set_target(Some(Vector2::new(100.0, 0.0)));
+		camera.update(0.1);
+		assert!((camera.pos().x - 63.2).abs() < 0.1);
+		for _ in 0..20 {
+			camera.update(0.1);
+		}
+		assert_near(&Vector2::new(100.0, 0.0), camera.pos());
+
+		camera.add_trauma(1.0);
+		camera.update(0.1);
+		assert!(
+			camera
+				.screen_to_world(&Vector2::zero())
+				.sub(camera.pos())
+				.length() > 0.0
+		);
+		for _ in 0..5 {
+			camera.update(0.1);
+		}
+		assert_eq!(0.0, camera.trauma());
+		assert_eq!(*camera.pos(), camera.screen_to_world(&Vector2::zero()));
+	}
+}
@@ -1,8 +1,8 @@
 use crate::renderer::{
-	gl,
 	//	Debug,
 	BlendFactor,
 	Program,
+	RendererBackend,
 	ShaderType,
 };
 use crate::system::System;
@@ -14,8 +14,9 @@ pub struct Effect {
 	program: Program,
 	cull_face: bool,
 	depth_test: bool,
-	blend_source_factor: gl::types::GLenum,
-	blend_destination_factor: gl::types::GLenum,
+	blend_source_factor: BlendFactor,
+	blend_destination_factor: BlendFactor,
+	textured: bool,
 }
 
 /*
@@ -39,17 +40,21 @@ impl Effect {
 		vertex_shader_name: &str,
 		fragment_shader_name: &str,
 	) -> Self {
-		let mut program = Program::new();
-
 		let mut vsf = system.default_filesystem_mut().open(vertex_shader_name);
 		let vs = vsf.read_as_string();
 
 		let mut fsf = system.default_filesystem_mut().open(fragment_shader_name);
 		let fs = fsf.read_as_string();
 
-		program.add_shader(ShaderType::Vertex, &vs);
-		program.add_shader(ShaderType::Fragment, &fs);
-		program.link();
+		Effect::from_source(id, name, &vs, &fs)
+	}
+
+	// e.g. for shaders that are not in a filesystem, compiled by Renderer::register_effect
+	pub fn from_source(id: u16, name: &str, vertex_shader: &str, fragment_shader: &str) -> Self {
+		let mut program = Program::new();
+
+		program.add_shader(ShaderType::Vertex, vertex_shader);
+		program.add_shader(ShaderType::Fragment, fragment_shader);
 
 		Self {
 			id,
@@ -57,32 +62,18 @@ impl Effect {
 			program,
 			cull_face: true,
 			depth_test: false,
-			blend_source_factor: gl::SRC_ALPHA,
-			blend_destination_factor: gl::ONE_MINUS_SRC_ALPHA,
+			blend_source_factor: BlendFactor::SrcAlpha,
+			blend_destination_factor: BlendFactor::OneMinusSrcAlpha,
+			textured: false,
 		}
 	}
 
-	pub fn id(&self) -> u16 {
-		self.id
+	pub(crate) fn link(&mut self, backend: &mut dyn RendererBackend) {
+		self.program.link(backend);
 	}
 
-	pub fn r#use(&mut self) {
-		unsafe {
-			if self.cull_face {
-				gl::Enable(gl::CULL_FACE);
-			} else {
-				gl::Disable(gl::CULL_FACE);
-			}
-			if self.depth_test {
-				gl::Enable(gl::DEPTH_TEST);
-			} else {
-				gl::Disable(gl::DEPTH_TEST);
-			}
-
-			gl::BlendFunc(self.blend_source_factor, self.blend_destination_factor);
-		}
-
-		self.program.r#use();
+	pub fn id(&self) -> u16 {
+		self.id
 	}
 
 	pub fn name(&self) -> &str {
@@ -93,6 +84,23 @@ impl Effect {
 		&self.program
 	}
 
+	pub fn cull_face(&self) -> bool {
+		self.cull_face
+	}
+
+	pub fn depth_test(&self) -> bool {
+		self.depth_test
+	}
+
+	pub fn textured(&self) -> bool {
+		self.textured
+	}
+
+	// source and destination
+	pub fn blend_func(&self) -> (BlendFactor, BlendFactor) {
+		(self.blend_source_factor, self.blend_destination_factor)
+	}
+
 	pub fn with_cull_face(mut self, cull_face: bool) -> Self {
 		self.cull_face = cull_face;
 		self
@@ -109,13 +117,20 @@ impl Effect {
 		source_factor: BlendFactor,
 		destination_factor: BlendFactor,
 	) -> Self {
-		self.blend_source_factor = source_factor.into();
-		self.blend_destination_factor = destination_factor.into();
+		self.blend_source_factor = source_factor;
+		self.blend_destination_factor = destination_factor;
+		self
+	}
+
+	// only used by the SoftwareBackend, which doesn't run shaders,
+	// textured effects draw texture0 multiplied by the vertex color, others just the vertex color
+	pub fn with_textured(mut self, textured: bool) -> Self {
+		self.textured = textured;
 		self
 	}
 
 	pub fn set_blend_func(&mut self, source_factor: BlendFactor, destination_factor: BlendFactor) {
-		self.blend_source_factor = source_factor.into();
-		self.blend_destination_factor = destination_factor.into();
+		self.blend_source_factor = source_factor;
+		self.blend_destination_factor = destination_factor;
 	}
 }
@@ -0,0 +1,401 @@
+use std::collections::HashMap;
+
+use crate::math::Vector2;
+use crate::renderer::{
+	gl, Color, Debug, DrawCall, RenderTargetFormat, RendererBackend, ShaderType, Uniform, Vertex,
+};
+
+fn gl_check_error(line: u32) {
+	unsafe {
+		let e = gl::GetError();
+		match e {
+			gl::NO_ERROR => {},
+			e => {
+				tracing::error!("gl error: {} in line {}", e, line);
+			},
+		}
+	}
+}
+
+// needs the gl functions loaded, see Renderer::setup
+#[derive(Debug, Default)]
+pub struct GlBackend {
+	buffer:   gl::types::GLuint,
+	vao:      gl::types::GLuint,
+	uniforms: HashMap<u32, HashMap<String, Option<i32>>>, // locations per program, None if not found
+}
+
+impl GlBackend {
+	pub fn new() -> Self {
+		Self {
+			buffer:   0xffffffff,
+			vao:      0xffffffff,
+			uniforms: HashMap::new(),
+		}
+	}
+
+	pub fn version() -> anyhow::Result<String> {
+		unsafe {
+			let s = gl::GetString(gl::VERSION);
+			if s.is_null() {
+				anyhow::bail!("No GL version, is the context current?");
+			}
+			let s = String::from_utf8(std::ffi::CStr::from_ptr(s as *const _).to_bytes().to_vec())?;
+			Ok(s)
+		}
+	}
+
+	fn compile_shader(shader_type: ShaderType, source: &str) -> gl::types::GLuint {
+		let gl_shader_type = match shader_type {
+			ShaderType::Vertex => gl::VERTEX_SHADER,
+			ShaderType::Fragment => gl::FRAGMENT_SHADER,
+			//			_ => todo!("{:?}", &shader_type),
+		};
+
+		unsafe {
+			let id = gl::CreateShader(gl_shader_type);
+			gl::ShaderSource(
+				id,
+				1,
+				[source.as_ptr() as *const _].as_ptr() as *const _,
+				//std::ptr::null(),
+				[source.len()].as_ptr() as *const _,
+			);
+			gl::CompileShader(id);
+
+			let mut log_length = 0;
+			gl::GetShaderiv(id, gl::INFO_LOG_LENGTH, &mut log_length);
+			if log_length > 0 {
+				println!(
+					"Warning: LogLength {} for shader {:?}\n{}",
+					&log_length, &shader_type, &source
+				);
+
+				let mut buf: Vec<u8> = Vec::with_capacity(log_length as usize);
+				gl::GetShaderInfoLog(id, log_length, &mut log_length, buf.as_mut_ptr() as *mut _);
+				buf.set_len(log_length as usize);
+				let s = String::from_utf8(buf).unwrap();
+				println!("Error Log: {}", &s);
+			}
+			Debug::check_gl_error(std::file!(), std::line!());
+			id
+		}
+	}
+}
+
+impl RendererBackend for GlBackend {
+	fn name(&self) -> &str {
+		"gl"
+	}
+
+	fn create_texture(&mut self) -> u32 {
+		let mut hwid = 0xffff;
+		unsafe {
+			gl::GenTextures(1, &mut hwid);
+			gl::BindTexture(gl::TEXTURE_2D, hwid);
+
+			gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
+			gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+		}
+		hwid
+	}
+
+	fn upload_texture(&mut self, hwid: u32, width: u32, height: u32, rgba: &[u8]) {
+		unsafe {
+			gl::BindTexture(gl::TEXTURE_2D, hwid);
+			gl::TexImage2D(
+				gl::TEXTURE_2D,
+				0, // mimap
+				gl::RGBA8 as i32,
+				width as i32,
+				height as i32,
+				0, // border
+				gl::RGBA,
+				gl::UNSIGNED_BYTE,
+				rgba.as_ptr() as *const _,
+			);
+
+			Debug::check_gl_error(std::file!(), std::line!());
+
+			//				gl::GenerateMipmap( gl::TEXTURE_2D );
+		}
+	}
+
+	fn create_render_target(
+		&mut self,
+		texture: u32,
+		width: u32,
+		height: u32,
+		format: RenderTargetFormat,
+	) -> Option<u32> {
+		let (internal_format, pixel_type) = match format {
+			RenderTargetFormat::Rgba8 => (gl::RGBA8, gl::UNSIGNED_BYTE),
+			RenderTargetFormat::Rgba16F => (gl::RGBA16F, gl::HALF_FLOAT),
+		};
+		unsafe {
+			gl::BindTexture(gl::TEXTURE_2D, texture);
+			gl::TexImage2D(
+				gl::TEXTURE_2D,
+				0,
+				internal_format as i32,
+				width as i32,
+				height as i32,
+				0,
+				gl::RGBA,
+				pixel_type,
+				std::ptr::null(),
+			);
+			gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+			gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+
+			let mut framebuffer = 0;
+			gl::GenFramebuffers(1, &mut framebuffer);
+			gl::BindFramebuffer(gl::FRAMEBUFFER, framebuffer);
+			gl::FramebufferTexture2D(
+				gl::FRAMEBUFFER,
+				gl::COLOR_ATTACHMENT0,
+				gl::TEXTURE_2D,
+				texture,
+				0,
+			);
+			let status = gl::CheckFramebufferStatus(gl::FRAMEBUFFER);
+			gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+			Debug::check_gl_error(std::file!(), std::line!());
+
+			if status != gl::FRAMEBUFFER_COMPLETE {
+				tracing::warn!("Framebuffer incomplete: 0x{:04x}", status);
+				gl::DeleteFramebuffers(1, &framebuffer);
+				return None;
+			}
+			Some(framebuffer)
+		}
+	}
+
+	fn destroy_render_target(&mut self, framebuffer: u32) {
+		unsafe {
+			gl::DeleteFramebuffers(1, &framebuffer);
+		}
+	}
+
+	fn bind_render_target(&mut self, framebuffer: Option<u32>) {
+		unsafe {
+			gl::BindFramebuffer(gl::FRAMEBUFFER, framebuffer.unwrap_or(0));
+		}
+	}
+
+	fn create_program(&mut self, shaders: &[(ShaderType, String)]) -> Option<u32> {
+		let shader_ids = shaders
+			.iter()
+			.map(|(t, s)| Self::compile_shader(*t, s))
+			.collect::<Vec<_>>();
+		let id = unsafe {
+			let id = gl::CreateProgram();
+			for s_id in shader_ids.iter() {
+				gl::AttachShader(id, *s_id);
+			}
+			gl::LinkProgram(id);
+
+			let mut status = 0;
+			gl::GetProgramiv(id, gl::LINK_STATUS, &mut status);
+			if status != 1 {
+				println!("Warning: Failed linking shaders into program");
+				for (_k, s) in shaders.iter() {
+					println!("{}", &s);
+				}
+				return None;
+			}
+			id
+		};
+		Debug::check_gl_error(std::file!(), std::line!());
+
+		self.uniforms.insert(id, HashMap::new());
+
+		Some(id)
+	}
+
+	// cached, this is called for every uniform of every draw call
+	fn uniform_location(&mut self, program: u32, name: &str) -> Option<i32> {
+		// names may, or may not be nul terminated
+		let name = name.trim_end_matches('\0');
+		let uniforms = self.uniforms.entry(program).or_default();
+		if let Some(l) = uniforms.get(name) {
+			return *l;
+		}
+		let l = std::ffi::CString::new(name).ok().and_then(|n| {
+			let l = unsafe { gl::GetUniformLocation(program, n.as_ptr()) };
+			if l != -1 {
+				Some(l)
+			} else {
+				None
+			}
+		});
+		if l.is_none() {
+			// e.g. optimised away by the compiler
+			tracing::debug!("Uniform {} not found in program {}", name, program);
+		}
+		uniforms.insert(name.to_string(), l);
+		l
+	}
+
+	fn set_viewport(&mut self, pos: &Vector2, size: &Vector2) {
+		unsafe {
+			gl::Viewport(pos.x as i32, pos.y as i32, size.x as i32, size.y as i32);
+		}
+	}
+
+	fn clear(&mut self, color: &Color, scissor: Option<(&Vector2, &Vector2)>) {
+		unsafe {
+			if let Some((p, s)) = scissor {
+				gl::Scissor(p.x as i32, p.y as i32, s.x as i32, s.y as i32);
+				gl::Enable(gl::SCISSOR_TEST);
+			}
+			gl::ClearColor(color.r, color.g, color.b, color.a);
+			gl::Clear(gl::COLOR_BUFFER_BIT); // :TODO: clear other buffers?
+			if scissor.is_some() {
+				gl::Disable(gl::SCISSOR_TEST);
+			}
+		}
+	}
+
+	fn draw(&mut self, draw_call: &DrawCall) {
+		let vertex_count = draw_call.vertices.len();
+		gl_check_error(line!());
+		unsafe {
+			if self.vao == 0xffffffff {
+				gl::GenVertexArrays(1, &mut self.vao);
+				gl::GenBuffers(1, &mut self.buffer);
+			}
+
+			gl::Enable(gl::BLEND);
+			if draw_call.cull_face {
+				gl::Enable(gl::CULL_FACE);
+			} else {
+				gl::Disable(gl::CULL_FACE);
+			}
+			if draw_call.depth_test {
+				gl::Enable(gl::DEPTH_TEST);
+			} else {
+				gl::Disable(gl::DEPTH_TEST);
+			}
+			gl::BlendFunc(
+				draw_call.blend_source.into(),
+				draw_call.blend_destination.into(),
+			);
+			match draw_call.scissor {
+				Some((p, s)) => {
+					gl::Scissor(p.x as i32, p.y as i32, s.x as i32, s.y as i32);
+					gl::Enable(gl::SCISSOR_TEST);
+				},
+				None => gl::Disable(gl::SCISSOR_TEST),
+			}
+			gl::UseProgram(draw_call.program);
+
+			gl::BindVertexArray(self.vao);
+			gl::BindBuffer(gl::ARRAY_BUFFER, self.buffer);
+
+			let vertex_size = core::mem::size_of::<Vertex>() as isize;
+			// :TODO: we might want to reuse this
+			gl::BufferData(
+				gl::ARRAY_BUFFER,
+				vertex_size * vertex_count as isize,
+				draw_call.vertices.as_ptr() as *const core::ffi::c_void,
+				gl::STATIC_DRAW, //maybe STREAM?
+			);
+
+			let attrib_pos_index = 0;
+			let attrib_tex_coords_index = 1;
+			let attrib_color_index = 2;
+
+			gl::EnableVertexAttribArray(attrib_pos_index);
+			gl::VertexAttribPointer(
+				attrib_pos_index,
+				3,
+				gl::FLOAT,
+				gl::FALSE,
+				vertex_size as i32,
+				std::ptr::null(),
+			);
+
+			// :TODO: only enable when needed
+			gl::EnableVertexAttribArray(attrib_tex_coords_index);
+			gl::VertexAttribPointer(
+				attrib_tex_coords_index,
+				2,
+				gl::FLOAT,
+				gl::FALSE,
+				vertex_size as i32,
+				(3 * 4) as *const _,
+			);
+
+			// :TODO: only enable when needed
+			gl::EnableVertexAttribArray(attrib_color_index);
+			gl::VertexAttribPointer(
+				attrib_color_index,
+				4,
+				gl::FLOAT,
+				gl::FALSE,
+				vertex_size as i32,
+				((3 + 2) * 4) as *const _,
+			);
+
+			for (n, v) in draw_call.uniforms.iter() {
+				if let Some(l) = self.uniform_location(draw_call.program, n) {
+					match v {
+						Uniform::F32(v) => {
+							gl::Uniform1f(l, *v);
+						},
+						Uniform::MATRIX44(mtx44) => {
+							gl::UniformMatrix4fv(l, 1, 0, mtx44.as_ptr() as *const _);
+						},
+					}
+				}
+			}
+			// always use channel 0 for texture0, and channel 1 for texture1
+			for (channel, n) in ["texture0", "texture1"].iter().enumerate() {
+				if let Some(l) = self.uniform_location(draw_call.program, n) {
+					gl::Uniform1i(l, channel as i32);
+				}
+			}
+			if let Some(l) = self.uniform_location(draw_call.program, "modelViewProjectionMatrix") {
+				gl::UniformMatrix4fv(l, 1, 0, draw_call.mvp_matrix.as_ptr() as *const _);
+			}
+
+			for (i, hwid) in draw_call.textures.iter().enumerate() {
+				gl::ActiveTexture(gl::TEXTURE0 + i as u32);
+				gl::BindTexture(gl::TEXTURE_2D, *hwid as u32);
+			}
+			// :TODO: decide if we really cleanup in case somebody else expects defaults
+			gl::ActiveTexture(gl::TEXTURE0);
+
+			gl::DrawArrays(gl::TRIANGLES, 0, vertex_count as i32);
+		}
+		gl_check_error(line!());
+	}
+
+	fn flush(&mut self) {
+		// glFlush or glFinish
+		unsafe {
+			gl::Flush();
+		}
+	}
+
+	fn read_pixels(&mut self, width: usize, height: usize) -> Vec<u8> {
+		let mut buffer = vec![0; width * height * 4];
+		unsafe {
+			// :TODO:
+			// gl::ReadBuffer
+			// gl::PixelTransfer
+			// gl::PixelMap
+			gl::ReadPixels(
+				0,
+				0,
+				width as i32,
+				height as i32,
+				gl::RGBA,
+				gl::UNSIGNED_BYTE,
+				buffer.as_mut_ptr() as *mut core::ffi::c_void,
+			);
+		}
+		buffer
+	}
+}
@@ -2,29 +2,18 @@ use std::collections::HashMap;
 
 use derivative::Derivative;
 
-use crate::math::Matrix44;
+use crate::math::{Matrix44, Vector2};
 use crate::renderer::{
-	gl,
 	//	Debug,
+	DrawCall,
 	Effect,
 	//	Program,
+	RendererBackend,
 	//	ShaderType,
 	Texture,
 	Uniform,
 	Vertex,
 };
-
-fn gl_check_error(line: u32) {
-	unsafe {
-		let e = gl::GetError();
-		match e {
-			gl::NO_ERROR => {},
-			e => {
-				tracing::error!("gl error: {} in line {}", e, line);
-			},
-		}
-	}
-}
 //#[derive(Debug)]
 #[derive(Derivative)]
 #[derivative(Debug)]
@@ -32,12 +21,11 @@ fn gl_check_error(line: u32) {
 pub struct Material {
 	#[derivative(Debug = "ignore")]
 	vertices: Vec<Vertex>,
-	buffer:   gl::types::GLuint,
-	vao:      gl::types::GLuint,
 
 	layer_id:      u8,
 	effect_id:     u16,
 	texture_hwids: Vec<u16>,
+	clip_id:       u16, // 0 is unclipped, see Renderer::push_clip_rect
 
 	effect_name:  String,
 	texture_name: String,
@@ -47,6 +35,7 @@ pub struct Material {
 	uniforms: HashMap<String, Uniform>,
 
 	mvp_matrix: Matrix44,
+	scissor:    Option<(Vector2, Vector2)>,
 }
 
 impl Material {
@@ -58,14 +47,13 @@ impl Material {
 			.map(|&t| t.name().to_owned())
 			.collect::<Vec<_>>()
 			.join(" ");
-		let mut s = Self {
+		Self {
 			vertices: Vec::new(),
-			buffer:   0xffffffff,
-			vao:      0xffffffff,
 
 			layer_id:      layer_id,
 			effect_id:     effect.id(),
 			texture_hwids: texture_hwids,
+			clip_id:       0,
 
 			effect_name:  effect.name().to_string(),
 			texture_name: texture_name,
@@ -75,17 +63,31 @@ impl Material {
 			uniforms: HashMap::new(),
 
 			mvp_matrix: Matrix44::identity(),
-		};
-
-		unsafe {
-			gl::GenVertexArrays(1, &mut s.vao);
-			gl::GenBuffers(1, &mut s.buffer);
+			scissor:    None,
 		}
+	}
 
-		s
+	pub fn with_clip_id(mut self, clip_id: u16) -> Self {
+		self.clip_id = clip_id;
+		self.key = Material::calculate_key_with_clip_id(
+			self.layer_id,
+			self.effect_id,
+			&self.texture_hwids,
+			clip_id,
+		);
+		self
 	}
 
 	pub fn calculate_key(layer_id: u8, effect_id: u16, texture_hwids: &Vec<u16>) -> u128 {
+		Material::calculate_key_with_clip_id(layer_id, effect_id, texture_hwids, 0)
+	}
+
+	pub fn calculate_key_with_clip_id(
+		layer_id: u8,
+		effect_id: u16,
+		texture_hwids: &[u16],
+		clip_id: u16,
+	) -> u128 {
 		// old fiiish:
 		// 00##llll pppppppp rrrrtttt tttttttt
 
@@ -93,6 +95,8 @@ impl Material {
 		// .. .. .. .. .. .. .. .. .. .. .. .. .l rr tt tt
 		// with multi texture support
 		// .. .. .. .. .. .. .l rr t3 t3 t2 t2 t1 t1 tt tt
+		// with clip rects, sorted by layer first
+		// .. .. .. .. .l cc cc rr t3 t3 t2 t2 t1 t1 tt tt
 
 		if texture_hwids.len() > 4 {
 			panic!("Too many texture channels. {}", texture_hwids.len());
@@ -115,7 +119,9 @@ impl Material {
 			shift += 16;
 		}
 
-		r | ((effect_id as u128 & 0xff) << (shift)) | ((layer_id as u128 & 0x000f) << (8 + shift))
+		r | ((effect_id as u128 & 0xff) << (shift))
+			| ((clip_id as u128 & 0xffff) << (8 + shift))
+			| ((layer_id as u128 & 0x000f) << (24 + shift))
 	}
 
 	pub fn can_render(&self, key: u128) -> bool {
@@ -134,6 +140,10 @@ impl Material {
 	pub fn effect_id(&self) -> u16 {
 		self.effect_id
 	}
+
+	pub fn clip_id(&self) -> u16 {
+		self.clip_id
+	}
 	/*
 		pub fn texture_hwid( &self ) -> u16 {
 			self.texture_hwid
@@ -151,6 +161,10 @@ impl Material {
 		self.vertices.clear();
 	}
 
+	pub fn vertex_count(&self) -> usize {
+		self.vertices.len()
+	}
+
 	pub fn add_vertex(&mut self, vertex: &Vertex) {
 		self.vertices.push(*vertex);
 	}
@@ -163,131 +177,34 @@ impl Material {
 		self.mvp_matrix = *mvp_matrix;
 	}
 
-	pub fn render(&mut self, effect: &mut Effect) -> u32 {
+	// pos and size in pixels of the bound framebuffer
+	pub fn set_scissor(&mut self, scissor: Option<(Vector2, Vector2)>) {
+		self.scissor = scissor;
+	}
+
+	pub fn render(&mut self, effect: &Effect, backend: &mut dyn RendererBackend) -> u32 {
 		let vertex_count = self.vertices.len();
 		if vertex_count == 0 {
 			return 0;
 		}
 		// tracing::debug!("Rendering Material with {} vertices", vertex_count);
 
-		gl_check_error(line!());
-		let vertex_count = unsafe {
-			gl::Enable(gl::BLEND);
-
-			// moved to Effect
-			// gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
-
-			// premultiplied alpha
-			//gl::BlendFunc(gl::ONE, gl::ONE_MINUS_SRC_ALPHA);
-
-			//			gl::Disable( gl::BLEND );
-			//			gl::BlendFunc( gl::ONE, gl::ONE );
-
-			gl::BindVertexArray(self.vao);
-			gl::BindBuffer(gl::ARRAY_BUFFER, self.buffer);
-
-			//			let vertex_size = ( core::mem::size_of<gl::type::GLFloat>() * 3 ) as i32;
-			let vertex_size = (core::mem::size_of::<f32>() * (3 + 2 + 4)) as isize; // :HACK:
-																		   // :TODO: we might want to reuse this
-			gl::BufferData(
-				gl::ARRAY_BUFFER,
-				vertex_size * vertex_count as isize,
-				self.vertices.as_ptr() as *const core::ffi::c_void,
-				gl::STATIC_DRAW, //maybe STREAM?
-			);
-
-			let attrib_pos_index = 0;
-			let attrib_tex_coords_index = 1;
-			let attrib_color_index = 2;
-
-			gl::EnableVertexAttribArray(attrib_pos_index);
-			gl::VertexAttribPointer(
-				attrib_pos_index,
-				3,
-				gl::FLOAT,
-				gl::FALSE,
-				vertex_size as i32,
-				0 as *const _,
-			);
-
-			// :TODO: only enable when needed
-			gl::EnableVertexAttribArray(attrib_tex_coords_index);
-			gl::VertexAttribPointer(
-				attrib_tex_coords_index,
-				2,
-				gl::FLOAT,
-				gl::FALSE,
-				vertex_size as i32,
-				(3 * 4) as *const _,
-			);
-
-			// :TODO: only enable when needed
-			gl::EnableVertexAttribArray(attrib_color_index);
-			gl::VertexAttribPointer(
-				attrib_color_index,
-				4,
-				gl::FLOAT,
-				gl::FALSE,
-				vertex_size as i32,
-				((3 + 2) * 4) as *const _,
-			);
-
-			effect.r#use();
-			// :HACK:
-			for (n, v) in self.uniforms.iter() {
-				match effect.program().lookup_uniform(&n) {
-					Some(l) => {
-						//						println!("Location for {} is {}", &n, l );
-						match v {
-							Uniform::F32(v) => {
-								gl::Uniform1f(l, *v);
-							},
-							Uniform::MATRIX44(mtx44) => {
-								gl::UniformMatrix4fv(l, 1, 0, mtx44.as_ptr() as *const _);
-							},
-							//							_ => todo!("Uniform type not supported. {:?}", v ),
-						}
-					},
-					None => {
-						println!("Warning: Location of {} not found {:?}", &n, &effect);
-					},
-				}
-			}
-			for (n, l) in effect.program().uniforms_iter() {
-				//				println!("{} -> {}", &n, &l );
-				match n.as_str() {
-					"texture0\0" => {
-						gl::Uniform1i(*l, 0); // always use channel 0 for texture0
-					},
-					"texture1\0" => {
-						gl::Uniform1i(*l, 1); // always use channel 1 for texture1
-					},
-					"modelViewProjectionMatrix\0" => {
-						gl::UniformMatrix4fv(*l, 1, 0, self.mvp_matrix.as_ptr() as *const _);
-					},
-					_ => {
-						todo!("handle uniform {:?}", &n);
-					},
-				}
-			}
-			//			gl::Uniform1i( 0, 0 );
-
-			for (i, hwid) in self.texture_hwids.iter().enumerate() {
-				gl::ActiveTexture(gl::TEXTURE0 + i as u32);
-				gl::BindTexture(gl::TEXTURE_2D, *hwid as u32);
-			}
-			// :TODO: decide if we really cleanup in case somebody else expects defaults
-			gl::ActiveTexture(gl::TEXTURE0);
-
-			//			dbg!(&self.vertices);
-			//			gl::PolygonMode( gl::FRONT_AND_BACK, gl::LINE );
-			gl::DrawArrays(gl::TRIANGLES, 0, vertex_count as i32);
-			//			println!("Rendering {} vertices", vertex_count);
-			vertex_count as u32
+		let (blend_source, blend_destination) = effect.blend_func();
+		let draw_call = DrawCall {
+			vertices: &self.vertices,
+			program: effect.program().id(),
+			textures: &self.texture_hwids,
+			mvp_matrix: &self.mvp_matrix,
+			uniforms: &self.uniforms,
+			cull_face: effect.cull_face(),
+			depth_test: effect.depth_test(),
+			blend_source,
+			blend_destination,
+			textured: effect.textured(),
+			scissor: self.scissor.as_ref().map(|(p, s)| (p, s)),
 		};
-		gl_check_error(line!());
-		//		dbg!(&self);
-		vertex_count
+		backend.draw(&draw_call);
+		vertex_count as u32
 	}
 }
 /*
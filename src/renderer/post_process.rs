@@ -0,0 +1,301 @@
+use std::collections::HashMap;
+
+use crate::renderer::{RenderTargetFormat, Uniform};
+
+// one full-screen Effect, texture0 is the frame so far, see PostProcessShader for built in ones
+#[derive(Debug, Clone)]
+pub struct PostProcessPass {
+	name:      String,
+	effect_id: u16,
+	enabled:   bool,
+	uniforms:  HashMap<String, Uniform>,
+	textures:  Vec<(u8, String)>, // channel, texture name
+}
+
+impl PostProcessPass {
+	pub fn new(name: &str, effect_id: u16) -> Self {
+		Self {
+			name: name.to_string(),
+			effect_id,
+			enabled: true,
+			uniforms: HashMap::new(),
+			textures: Vec::new(),
+		}
+	}
+
+	pub fn with_uniform(mut self, name: &str, value: Uniform) -> Self {
+		self.set_uniform(name, value);
+		self
+	}
+
+	// e.g. a lut in channel 1, channel 0 is always the frame
+	pub fn with_texture(mut self, channel: u8, name: &str) -> Self {
+		self.textures.retain(|(c, _)| *c != channel);
+		self.textures.push((channel, name.to_string()));
+		self
+	}
+
+	pub fn with_enabled(mut self, enabled: bool) -> Self {
+		self.enabled = enabled;
+		self
+	}
+
+	pub fn name(&self) -> &str {
+		&self.name
+	}
+
+	pub fn effect_id(&self) -> u16 {
+		self.effect_id
+	}
+
+	pub fn enabled(&self) -> bool {
+		self.enabled
+	}
+
+	pub fn set_enabled(&mut self, enabled: bool) {
+		self.enabled = enabled;
+	}
+
+	pub fn set_uniform(&mut self, name: &str, value: Uniform) {
+		self.uniforms.insert(name.to_string(), value);
+	}
+
+	pub fn uniform(&self, name: &str) -> Option<&Uniform> {
+		self.uniforms.get(name)
+	}
+
+	pub fn uniforms(&self) -> &HashMap<String, Uniform> {
+		&self.uniforms
+	}
+
+	pub fn textures(&self) -> &[(u8, String)] {
+		&self.textures
+	}
+}
+
+// full-screen passes run in order at the end of Renderer::end_frame
+// while any pass is enabled the frame is rendered offscreen first
+#[derive(Debug, Default, Clone)]
+pub struct PostProcessChain {
+	passes: Vec<PostProcessPass>,
+	format: RenderTargetFormat,
+}
+
+impl PostProcessChain {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	// of the offscreen frames, e.g. Rgba16F for bloom
+	pub fn with_format(mut self, format: RenderTargetFormat) -> Self {
+		self.format = format;
+		self
+	}
+
+	pub fn format(&self) -> RenderTargetFormat {
+		self.format
+	}
+
+	pub fn set_format(&mut self, format: RenderTargetFormat) {
+		self.format = format;
+	}
+
+	// passes with the same name are replaced in place
+	pub fn add_pass(&mut self, pass: PostProcessPass) {
+		match self.passes.iter_mut().find(|p| p.name == pass.name) {
+			Some(p) => *p = pass,
+			None => self.passes.push(pass),
+		}
+	}
+
+	pub fn insert_pass(&mut self, index: usize, pass: PostProcessPass) {
+		self.remove_pass(&pass.name);
+		let index = index.min(self.passes.len());
+		self.passes.insert(index, pass);
+	}
+
+	pub fn remove_pass(&mut self, name: &str) -> Option<PostProcessPass> {
+		let index = self.index_of(name)?;
+		Some(self.passes.remove(index))
+	}
+
+	// returns false for unknown passes
+	pub fn move_pass(&mut self, name: &str, index: usize) -> bool {
+		match self.remove_pass(name) {
+			Some(pass) => {
+				self.insert_pass(index, pass);
+				true
+			},
+			None => false,
+		}
+	}
+
+	// returns false for unknown passes
+	pub fn set_enabled(&mut self, name: &str, enabled: bool) -> bool {
+		match self.pass_mut(name) {
+			Some(p) => {
+				p.set_enabled(enabled);
+				true
+			},
+			None => false,
+		}
+	}
+
+	pub fn index_of(&self, name: &str) -> Option<usize> {
+		self.passes.iter().position(|p| p.name == name)
+	}
+
+	pub fn pass(&self, name: &str) -> Option<&PostProcessPass> {
+		self.passes.iter().find(|p| p.name == name)
+	}
+
+	pub fn pass_mut(&mut self, name: &str) -> Option<&mut PostProcessPass> {
+		self.passes.iter_mut().find(|p| p.name == name)
+	}
+
+	pub fn passes(&self) -> &[PostProcessPass] {
+		&self.passes
+	}
+
+	pub fn enabled_passes(&self) -> impl Iterator<Item = &PostProcessPass> {
+		self.passes.iter().filter(|p| p.enabled)
+	}
+
+	pub fn is_active(&self) -> bool {
+		self.passes.iter().any(|p| p.enabled)
+	}
+
+	pub fn clear(&mut self) {
+		self.passes.clear();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::math::Vector2;
+	use crate::renderer::{BlendFactor, Color, Effect, PostProcessShader, Renderer};
+
+	fn names(chain: &PostProcessChain) -> Vec<&str> {
+		chain.enabled_passes().map(|p| p.name()).collect()
+	}
+
+	#[test]
+	fn passes_can_be_reordered_and_toggled() {
+		let mut chain = PostProcessChain::new();
+		assert!(!chain.is_active());
+		chain.add_pass(PostProcessShader::Bloom.pass(10));
+		chain.add_pass(PostProcessShader::Vignette.pass(11));
+		chain.add_pass(PostProcessShader::Pixelate.pass(12).with_enabled(false));
+		assert!(chain.is_active());
+		assert_eq!(vec!["bloom", "vignette"], names(&chain));
+
+		assert!(chain.set_enabled("pixelate", true));
+		assert!(chain.move_pass("pixelate", 0));
+		assert_eq!(vec!["pixelate", "bloom", "vignette"], names(&chain));
+		chain.insert_pass(1, PostProcessPass::new("vignette", 13));
+		assert_eq!(vec!["pixelate", "vignette", "bloom"], names(&chain));
+		assert_eq!(13, chain.pass("vignette").unwrap().effect_id());
+
+		assert!(chain.set_enabled("bloom", false));
+		assert!(!chain.set_enabled("scanlines", false));
+		assert_eq!(vec!["pixelate", "vignette"], names(&chain));
+
+		let pixelate = chain.pass_mut("pixelate").unwrap();
+		pixelate.set_uniform("pixel_size", Uniform::F32(8.0));
+		assert!(matches!(
+			pixelate.uniform("pixel_size"),
+			Some(Uniform::F32(v)) if *v == 8.0
+		));
+	}
+
+	fn render(renderer: &mut Renderer) -> Vec<[u8; 4]> {
+		renderer.begin_frame();
+		renderer.clear(&Color::black());
+		renderer.set_color(&Color::from_rgba(1.0, 0.0, 0.0, 1.0));
+		renderer.render_quad(&Vector2::new(-1.0, 0.0), &Vector2::new(2.0, 4.0));
+		renderer.end_frame();
+		vec![
+			renderer.pixel_for_tests(0, 0),
+			renderer.pixel_for_tests(3, 3),
+		]
+	}
+
+	#[test]
+	fn passes_run_in_order_after_the_frame() -> anyhow::Result<()> {
+		let mut renderer = Renderer::software_for_tests(4, 4);
+		// the software backend doesn't run shaders, passes without a texture ignore the frame
+		let pass_effect = |id, textured, blend_source| {
+			Effect::from_source(id, "pass", "", "")
+				.with_textured(textured)
+				.with_cull_face(false)
+				.with_blend_func(blend_source, BlendFactor::Zero)
+		};
+		// copies the frame
+		renderer.register_effect(pass_effect(1, true, BlendFactor::One));
+		// fills with white
+		renderer.register_effect(pass_effect(2, false, BlendFactor::One));
+		// opaque pixels become transparent black
+		renderer.register_effect(pass_effect(3, true, BlendFactor::OneMinusSrcAlpha));
+
+		let red = [255, 0, 0, 255];
+		let black = [0, 0, 0, 255];
+		let white = [255, 255, 255, 255];
+		let transparent = [0, 0, 0, 0];
+		assert_eq!(vec![red, black], render(&mut renderer));
+
+		renderer
+			.post_process_mut()
+			.add_pass(PostProcessPass::new("copy", 1));
+		assert_eq!(vec![red, black], render(&mut renderer));
+
+		// white, then fade
+		renderer
+			.post_process_mut()
+			.add_pass(PostProcessPass::new("fade", 3));
+		renderer
+			.post_process_mut()
+			.insert_pass(0, PostProcessPass::new("white", 2));
+		assert_eq!(vec![transparent, transparent], render(&mut renderer));
+		// fade, then white
+		renderer.post_process_mut().move_pass("white", 2);
+		assert_eq!(vec![white, white], render(&mut renderer));
+		renderer.post_process_mut().set_enabled("white", false);
+		assert_eq!(vec![transparent, transparent], render(&mut renderer));
+		renderer.post_process_mut().set_enabled("fade", false);
+		assert_eq!(vec![red, black], render(&mut renderer));
+
+		// passes without an effect are skipped, but stay enabled
+		renderer
+			.post_process_mut()
+			.add_pass(PostProcessPass::new("late", 7));
+		assert_eq!(vec![red, black], render(&mut renderer));
+		assert!(renderer.post_process().pass("late").unwrap().enabled());
+		renderer.register_effect(pass_effect(7, false, BlendFactor::One));
+		assert_eq!(vec![white, white], render(&mut renderer));
+		Ok(())
+	}
+
+	// the software backend doesn't run them, so at least check their defaults are declared
+	#[test]
+	fn built_in_shaders_declare_their_uniforms() {
+		for shader in [
+			PostProcessShader::Bloom,
+			PostProcessShader::ColorGrading,
+			PostProcessShader::Vignette,
+			PostProcessShader::CrtScanlines,
+			PostProcessShader::Pixelate,
+		] {
+			let fs = shader.fragment_shader();
+			assert!(
+				fs.contains("uniform sampler2D texture0;"),
+				"{}",
+				shader.name()
+			);
+			for (name, _) in shader.default_uniforms() {
+				let declaration = format!("uniform float {};", name);
+				assert!(fs.contains(&declaration), "{} {}", shader.name(), name);
+			}
+		}
+	}
+}
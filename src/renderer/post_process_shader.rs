@@ -0,0 +1,187 @@
+use crate::renderer::{BlendFactor, Effect, PostProcessPass, Uniform};
+
+// built in full-screen shaders for PostProcessChain, texture0 is the frame so far
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PostProcessShader {
+	// single pass, bright parts are blurred and added
+	Bloom,
+	// texture1 is a strip lut, lut_size slices of lut_size x lut_size, blue selects the slice
+	ColorGrading,
+	Vignette,
+	CrtScanlines,
+	Pixelate,
+}
+
+const VERTEX_SHADER: &str = r#"#version 330 core
+layout(location = 0) in vec3 pos;
+layout(location = 1) in vec2 texCoords;
+uniform mat4 modelViewProjectionMatrix;
+out vec2 uv;
+
+void main() {
+	uv = texCoords;
+	gl_Position = modelViewProjectionMatrix * vec4(pos, 1.0);
+}
+"#;
+
+const BLOOM_FRAGMENT_SHADER: &str = r#"#version 330 core
+in vec2 uv;
+out vec4 fragColor;
+uniform sampler2D texture0;
+uniform float threshold;
+uniform float intensity;
+uniform float radius;
+
+void main() {
+	vec2 texel = radius / vec2(textureSize(texture0, 0));
+	vec4 c = texture(texture0, uv);
+	vec3 bloom = vec3(0.0);
+	float total = 0.0;
+	for (int x = -3; x <= 3; x++) {
+		for (int y = -3; y <= 3; y++) {
+			vec2 o = vec2(x, y);
+			float w = exp(-dot(o, o) / 8.0);
+			vec3 s = texture(texture0, uv + o * texel).rgb;
+			bloom += max(s - vec3(threshold), vec3(0.0)) * w;
+			total += w;
+		}
+	}
+	fragColor = vec4(c.rgb + bloom / total * intensity, c.a);
+}
+"#;
+
+const COLOR_GRADING_FRAGMENT_SHADER: &str = r#"#version 330 core
+in vec2 uv;
+out vec4 fragColor;
+uniform sampler2D texture0;
+uniform sampler2D texture1;
+uniform float lut_size;
+uniform float intensity;
+
+void main() {
+	vec4 c = texture(texture0, uv);
+	vec3 s = clamp(c.rgb, 0.0, 1.0) * (lut_size - 1.0);
+	float b0 = floor(s.b);
+	float b1 = min(b0 + 1.0, lut_size - 1.0);
+	vec2 rg = (s.rg + 0.5) / vec2(lut_size * lut_size, lut_size);
+	vec3 g0 = texture(texture1, vec2(rg.x + b0 / lut_size, rg.y)).rgb;
+	vec3 g1 = texture(texture1, vec2(rg.x + b1 / lut_size, rg.y)).rgb;
+	vec3 graded = mix(g0, g1, s.b - b0);
+	fragColor = vec4(mix(c.rgb, graded, intensity), c.a);
+}
+"#;
+
+const VIGNETTE_FRAGMENT_SHADER: &str = r#"#version 330 core
+in vec2 uv;
+out vec4 fragColor;
+uniform sampler2D texture0;
+uniform float intensity;
+uniform float radius;
+uniform float softness;
+
+void main() {
+	vec4 c = texture(texture0, uv);
+	float d = distance(uv, vec2(0.5)) * 1.41421356;
+	float v = smoothstep(radius, radius - softness, d);
+	fragColor = vec4(c.rgb * mix(1.0, v, intensity), c.a);
+}
+"#;
+
+const CRT_SCANLINES_FRAGMENT_SHADER: &str = r#"#version 330 core
+in vec2 uv;
+out vec4 fragColor;
+uniform sampler2D texture0;
+uniform float intensity;
+uniform float line_height;
+uniform float curvature;
+
+void main() {
+	vec2 cc = uv * 2.0 - 1.0;
+	cc *= 1.0 + curvature * dot(cc.yx, cc.yx);
+	vec2 p = cc * 0.5 + 0.5;
+	if (p.x < 0.0 || p.x > 1.0 || p.y < 0.0 || p.y > 1.0) {
+		fragColor = vec4(0.0, 0.0, 0.0, 1.0);
+		return;
+	}
+	vec2 size = vec2(textureSize(texture0, 0));
+	float line = mod(floor(p.y * size.y / line_height), 2.0);
+	vec4 c = texture(texture0, p);
+	fragColor = vec4(c.rgb * (1.0 - intensity * line), c.a);
+}
+"#;
+
+const PIXELATE_FRAGMENT_SHADER: &str = r#"#version 330 core
+in vec2 uv;
+out vec4 fragColor;
+uniform sampler2D texture0;
+uniform float pixel_size;
+
+void main() {
+	vec2 size = vec2(textureSize(texture0, 0));
+	vec2 p = (floor(uv * size / pixel_size) + 0.5) * pixel_size / size;
+	fragColor = texture(texture0, p);
+}
+"#;
+
+impl PostProcessShader {
+	pub fn name(&self) -> &'static str {
+		match self {
+			PostProcessShader::Bloom => "bloom",
+			PostProcessShader::ColorGrading => "color_grading",
+			PostProcessShader::Vignette => "vignette",
+			PostProcessShader::CrtScanlines => "crt_scanlines",
+			PostProcessShader::Pixelate => "pixelate",
+		}
+	}
+
+	pub fn vertex_shader(&self) -> &'static str {
+		VERTEX_SHADER
+	}
+
+	pub fn fragment_shader(&self) -> &'static str {
+		match self {
+			PostProcessShader::Bloom => BLOOM_FRAGMENT_SHADER,
+			PostProcessShader::ColorGrading => COLOR_GRADING_FRAGMENT_SHADER,
+			PostProcessShader::Vignette => VIGNETTE_FRAGMENT_SHADER,
+			PostProcessShader::CrtScanlines => CRT_SCANLINES_FRAGMENT_SHADER,
+			PostProcessShader::Pixelate => PIXELATE_FRAGMENT_SHADER,
+		}
+	}
+
+	pub fn default_uniforms(&self) -> &'static [(&'static str, f32)] {
+		match self {
+			PostProcessShader::Bloom => &[("threshold", 0.7), ("intensity", 0.6), ("radius", 2.0)],
+			PostProcessShader::ColorGrading => &[("lut_size", 16.0), ("intensity", 1.0)],
+			PostProcessShader::Vignette => {
+				&[("intensity", 0.5), ("radius", 0.9), ("softness", 0.5)]
+			},
+			PostProcessShader::CrtScanlines => &[
+				("intensity", 0.25),
+				("line_height", 2.0),
+				("curvature", 0.0),
+			],
+			PostProcessShader::Pixelate => &[("pixel_size", 4.0)],
+		}
+	}
+
+	// for Renderer::register_effect, passes overwrite the target, so there is no blending
+	pub fn effect(&self, effect_id: u16) -> Effect {
+		Effect::from_source(
+			effect_id,
+			self.name(),
+			self.vertex_shader(),
+			self.fragment_shader(),
+		)
+		.with_textured(true)
+		.with_cull_face(false)
+		.with_blend_func(BlendFactor::One, BlendFactor::Zero)
+	}
+
+	// named like the shader, with the default uniforms, the effect has to be registered separately
+	pub fn pass(&self, effect_id: u16) -> PostProcessPass {
+		self.default_uniforms().iter().fold(
+			PostProcessPass::new(self.name(), effect_id),
+			|pass, (name, value)| pass.with_uniform(name, Uniform::F32(*value)),
+		)
+	}
+}
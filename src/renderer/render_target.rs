@@ -0,0 +1,126 @@
+use crate::math::Vector2;
+use crate::renderer::Color;
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum RenderTargetFormat {
+	#[default]
+	Rgba8,
+	// e.g. for bloom, the software backend stores it as Rgba8
+	Rgba16F,
+}
+
+// an offscreen framebuffer, see Renderer::register_render_target
+// its colour attachment is registered as a texture with the same name, e.g. for use_texture_in_channel
+#[derive(Debug, Clone)]
+pub struct RenderTarget {
+	name:        String,
+	size:        Vector2, // in pixels
+	format:      RenderTargetFormat,
+	clear_color: Option<Color>,
+	framebuffer: Option<u32>,
+}
+
+impl RenderTarget {
+	pub fn new(name: &str, size: &Vector2) -> Self {
+		Self {
+			name:        name.to_string(),
+			size:        Vector2::new(size.x.floor().max(1.0), size.y.floor().max(1.0)),
+			format:      RenderTargetFormat::default(),
+			clear_color: None,
+			framebuffer: None,
+		}
+	}
+
+	pub fn with_format(mut self, format: RenderTargetFormat) -> Self {
+		self.format = format;
+		self
+	}
+
+	// cleared the first time it is rendered to each frame, None keeps the old content, e.g. for caching
+	pub fn with_clear_color(mut self, clear_color: Option<Color>) -> Self {
+		self.clear_color = clear_color;
+		self
+	}
+
+	pub fn name(&self) -> &str {
+		&self.name
+	}
+
+	pub fn size(&self) -> &Vector2 {
+		&self.size
+	}
+
+	pub fn format(&self) -> RenderTargetFormat {
+		self.format
+	}
+
+	pub fn clear_color(&self) -> Option<&Color> {
+		self.clear_color.as_ref()
+	}
+
+	// None until registered
+	pub fn framebuffer(&self) -> Option<u32> {
+		self.framebuffer
+	}
+
+	pub(crate) fn set_framebuffer(&mut self, framebuffer: Option<u32>) {
+		self.framebuffer = framebuffer;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::math::Matrix44;
+	use crate::renderer::{Effect, Renderer};
+
+	#[test]
+	fn layers_render_into_targets_used_as_textures() -> anyhow::Result<()> {
+		let mut renderer = Renderer::software_for_tests(4, 4);
+		renderer.register_effect(Effect::from_source(1, "textured", "", "").with_textured(true));
+
+		let green = Color::from_rgba(0.0, 1.0, 0.0, 1.0);
+		renderer.register_render_target(
+			RenderTarget::new("minimap", &Vector2::new(2.0, 2.0)).with_clear_color(Some(green)),
+		)?;
+		renderer.set_render_target_for_layer(1, "minimap");
+		renderer.set_mvp_matrix_for_layer(1, &Matrix44::ortho(-1.0, 1.0, -1.0, 1.0, 1.0, -1.0));
+
+		renderer.begin_frame();
+		renderer.clear(&Color::black());
+		// the screen layer comes first, but is rendered after the target
+		renderer.use_layer(0);
+		renderer.use_effect(1);
+		renderer.use_texture("minimap");
+		renderer.render_textured_quad(&Vector2::zero(), &Vector2::new(4.0, 4.0));
+		// left half of the minimap
+		renderer.use_layer(1);
+		renderer.use_effect(0);
+		renderer.set_color(&Color::from_rgba(1.0, 0.0, 0.0, 1.0));
+		renderer.render_quad(&Vector2::new(-0.5, 0.0), &Vector2::new(1.0, 2.0));
+		renderer.end_frame();
+
+		assert_eq!([255, 0, 0, 255], renderer.pixel_for_tests(0, 0));
+		assert_eq!([255, 0, 0, 255], renderer.pixel_for_tests(1, 3));
+		assert_eq!([0, 255, 0, 255], renderer.pixel_for_tests(2, 0));
+		assert_eq!([0, 255, 0, 255], renderer.pixel_for_tests(3, 3));
+
+		// sections render every layer into the target
+		renderer.clear_render_target_for_layer(1);
+		renderer.begin_frame();
+		renderer.clear(&Color::black());
+		renderer.begin_render_target("minimap");
+		renderer.use_layer(1);
+		renderer.use_effect(0);
+		renderer.set_color(&Color::white());
+		renderer.render_quad(&Vector2::new(-0.5, 0.0), &Vector2::new(1.0, 2.0));
+		renderer.end_render_target();
+		renderer.end_frame();
+
+		assert_eq!([0, 0, 0, 255], renderer.pixel_for_tests(0, 0));
+		renderer.bind_render_target(Some("minimap"));
+		assert_eq!([255, 255, 255, 255], renderer.pixel_for_tests(0, 0));
+		assert_eq!([0, 255, 0, 255], renderer.pixel_for_tests(1, 0));
+		Ok(())
+	}
+}
@@ -0,0 +1,520 @@
+use std::collections::HashMap;
+
+use crate::math::Vector2;
+use crate::renderer::{
+	BlendFactor, Color, DrawCall, RenderTargetFormat, RendererBackend, ShaderType,
+};
+
+#[derive(Debug, Default)]
+struct SoftwareTexture {
+	width:  u32,
+	height: u32,
+	rgba:   Vec<u8>,
+}
+
+// renders on the cpu into an rgba buffer, e.g. for tests, and screenshots without a gpu
+// shaders and uniforms are ignored, textured effects draw texture0 multiplied by the vertex color,
+// all others just the vertex color, see Effect::with_textured, depth testing is ignored
+#[derive(Debug)]
+pub struct SoftwareBackend {
+	width:         usize,
+	height:        usize,
+	pixels:        Vec<[f32; 4]>, // bottom row first
+	viewport_pos:  Vector2,
+	viewport_size: Vector2,
+	textures:      HashMap<u32, SoftwareTexture>,
+	next_texture:  u32,
+	next_program:  u32,
+
+	render_targets:      HashMap<u32, u32>, // framebuffer to texture
+	next_render_target:  u32,
+	bound_render_target: Option<u32>,
+	default_pixels:      Vec<[f32; 4]>, // while a render target is bound
+	default_size:        (usize, usize),
+}
+
+impl SoftwareBackend {
+	pub fn new(width: usize, height: usize) -> Self {
+		Self {
+			width,
+			height,
+			pixels: vec![[0.0; 4]; width * height],
+			viewport_pos: Vector2::zero(),
+			viewport_size: Vector2::new(width as f32, height as f32),
+			textures: HashMap::new(),
+			next_texture: 1,
+			next_program: 1,
+
+			render_targets: HashMap::new(),
+			next_render_target: 1,
+			bound_render_target: None,
+			default_pixels: Vec::new(),
+			default_size: (width, height),
+		}
+	}
+
+	pub fn width(&self) -> usize {
+		self.width
+	}
+
+	pub fn height(&self) -> usize {
+		self.height
+	}
+
+	fn sample(&self, hwid: Option<&u16>, uv: &[f32; 2]) -> [f32; 4] {
+		let t = match hwid.and_then(|id| self.textures.get(&(*id as u32))) {
+			Some(t) if t.width > 0 && t.height > 0 => t,
+			_ => return [1.0; 4],
+		};
+		// nearest, and repeat like the gl defaults
+		let x = (uv[0] * t.width as f32).floor() as i64;
+		let y = (uv[1] * t.height as f32).floor() as i64;
+		let x = x.rem_euclid(t.width as i64) as usize;
+		let y = y.rem_euclid(t.height as i64) as usize;
+		let p = (y * t.width as usize + x) * 4;
+		match t.rgba.get(p..p + 4) {
+			Some(c) => [
+				c[0] as f32 / 255.0,
+				c[1] as f32 / 255.0,
+				c[2] as f32 / 255.0,
+				c[3] as f32 / 255.0,
+			],
+			None => [1.0; 4],
+		}
+	}
+
+	fn blend_factor(factor: BlendFactor, src: &[f32; 4], dst: &[f32; 4]) -> [f32; 4] {
+		match factor {
+			BlendFactor::Zero => [0.0; 4],
+			BlendFactor::One => [1.0; 4],
+			BlendFactor::DstColor => *dst,
+			BlendFactor::OneMinusDstColor => dst.map(|d| 1.0 - d),
+			BlendFactor::SrcAlpha => [src[3]; 4],
+			BlendFactor::OneMinusSrcAlpha => [1.0 - src[3]; 4],
+			BlendFactor::DstAlpha => [dst[3]; 4],
+			BlendFactor::OneMinusDstAlpha => [1.0 - dst[3]; 4],
+			BlendFactor::SrcAlphaSaturate => {
+				let f = src[3].min(1.0 - dst[3]);
+				[f, f, f, 1.0]
+			},
+			// like the gl backend
+			_ => [1.0; 4],
+		}
+	}
+
+	// rgba8 like a gl framebuffer
+	fn quantize(c: f32) -> f32 {
+		(c.clamp(0.0, 1.0) * 255.0).round() / 255.0
+	}
+
+	fn draw_triangle(&mut self, draw_call: &DrawCall, screen: &[[f32; 2]; 3], index: usize) {
+		let [a, b, c] = screen;
+		let area = (b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0]);
+		if area == 0.0 || (draw_call.cull_face && area < 0.0) {
+			// counter clockwise is front facing
+			return;
+		}
+		// rasterize counter clockwise, remembering which vertex is which
+		let (b, c, ib, ic, area) = if area < 0.0 {
+			(c, b, 2, 1, -area)
+		} else {
+			(b, c, 1, 2, area)
+		};
+
+		// the viewport and the scissor both limit drawing, like in gl
+		let (clip_pos, clip_size) = match draw_call.scissor {
+			Some((p, s)) => (*p, *s),
+			None => (
+				Vector2::zero(),
+				Vector2::new(self.width as f32, self.height as f32),
+			),
+		};
+		let min_x = (a[0].min(b[0]).min(c[0]).floor())
+			.max(self.viewport_pos.x)
+			.max(clip_pos.x)
+			.max(0.0) as usize;
+		let min_y = (a[1].min(b[1]).min(c[1]).floor())
+			.max(self.viewport_pos.y)
+			.max(clip_pos.y)
+			.max(0.0) as usize;
+		let max_x = (a[0].max(b[0]).max(c[0]).ceil())
+			.min(self.viewport_pos.x + self.viewport_size.x)
+			.min(clip_pos.x + clip_size.x)
+			.min(self.width as f32);
+		let max_y = (a[1].max(b[1]).max(c[1]).ceil())
+			.min(self.viewport_pos.y + self.viewport_size.y)
+			.min(clip_pos.y + clip_size.y)
+			.min(self.height as f32);
+		if max_x <= 0.0 || max_y <= 0.0 {
+			return;
+		}
+		let (max_x, max_y) = (max_x as usize, max_y as usize);
+
+		let vertices = &draw_call.vertices[index..index + 3];
+		let edge = |p: &[f32; 2], q: &[f32; 2], x: f32, y: f32| {
+			(q[0] - p[0]) * (y - p[1]) - (q[1] - p[1]) * (x - p[0])
+		};
+		// top left fill rule, pixels on a shared edge are drawn only once
+		let owns = |p: &[f32; 2], q: &[f32; 2], w: f32| {
+			w > 0.0 || (w == 0.0 && (q[1] < p[1] || (q[1] == p[1] && q[0] < p[0])))
+		};
+		for y in min_y..max_y {
+			for x in min_x..max_x {
+				// pixel centers, like gl
+				let px = x as f32 + 0.5;
+				let py = y as f32 + 0.5;
+				let w0 = edge(b, c, px, py) / area;
+				let w1 = edge(c, a, px, py) / area;
+				let w2 = edge(a, b, px, py) / area;
+				if !owns(b, c, w0) || !owns(c, a, w1) || !owns(a, b, w2) {
+					continue;
+				}
+				let mut w = [w0, 0.0, 0.0];
+				w[ib] = w1;
+				w[ic] = w2;
+				let lerp = |f: &dyn Fn(usize) -> f32| w[0] * f(0) + w[1] * f(1) + w[2] * f(2);
+				let uv = [
+					lerp(&|i| vertices[i].tex_coords[0]),
+					lerp(&|i| vertices[i].tex_coords[1]),
+				];
+				let color = [0, 1, 2, 3].map(|ci| lerp(&|i| vertices[i].color[ci]));
+				let texel = if draw_call.textured {
+					self.sample(draw_call.textures.first(), &uv)
+				} else {
+					[1.0; 4]
+				};
+				let src = [0, 1, 2, 3].map(|ci| color[ci] * texel[ci]);
+
+				let p = y * self.width + x;
+				let dst = self.pixels[p];
+				let sf = Self::blend_factor(draw_call.blend_source, &src, &dst);
+				let df = Self::blend_factor(draw_call.blend_destination, &src, &dst);
+				self.pixels[p] =
+					[0, 1, 2, 3].map(|ci| Self::quantize(src[ci] * sf[ci] + dst[ci] * df[ci]));
+			}
+		}
+	}
+}
+
+impl RendererBackend for SoftwareBackend {
+	fn name(&self) -> &str {
+		"software"
+	}
+
+	fn create_texture(&mut self) -> u32 {
+		let hwid = self.next_texture;
+		self.next_texture += 1;
+		self.textures.insert(hwid, SoftwareTexture::default());
+		hwid
+	}
+
+	fn upload_texture(&mut self, hwid: u32, width: u32, height: u32, rgba: &[u8]) {
+		self.textures.insert(
+			hwid,
+			SoftwareTexture {
+				width,
+				height,
+				rgba: rgba.to_vec(),
+			},
+		);
+	}
+
+	fn create_render_target(
+		&mut self,
+		texture: u32,
+		width: u32,
+		height: u32,
+		_format: RenderTargetFormat,
+	) -> Option<u32> {
+		self.upload_texture(
+			texture,
+			width,
+			height,
+			&vec![0; width as usize * height as usize * 4],
+		);
+		let framebuffer = self.next_render_target;
+		self.next_render_target += 1;
+		self.render_targets.insert(framebuffer, texture);
+		Some(framebuffer)
+	}
+
+	fn destroy_render_target(&mut self, framebuffer: u32) {
+		if self.bound_render_target == Some(framebuffer) {
+			self.bind_render_target(None);
+		}
+		self.render_targets.remove(&framebuffer);
+	}
+
+	// the texture is only updated when the target is unbound
+	fn bind_render_target(&mut self, framebuffer: Option<u32>) {
+		let framebuffer = framebuffer.filter(|f| self.render_targets.contains_key(f));
+		if self.bound_render_target == framebuffer {
+			return;
+		}
+		let pixels = std::mem::take(&mut self.pixels);
+		match self
+			.bound_render_target
+			.and_then(|f| self.render_targets.get(&f))
+		{
+			Some(texture) => {
+				if let Some(t) = self.textures.get_mut(texture) {
+					t.rgba = pixels
+						.iter()
+						.flat_map(|c| c.map(|c| (c * 255.0).round() as u8))
+						.collect();
+				}
+			},
+			None => self.default_pixels = pixels,
+		}
+		match framebuffer
+			.and_then(|f| self.render_targets.get(&f))
+			.and_then(|t| self.textures.get(t))
+		{
+			Some(t) => {
+				self.width = t.width as usize;
+				self.height = t.height as usize;
+				self.pixels = t
+					.rgba
+					.chunks_exact(4)
+					.map(|c| [0, 1, 2, 3].map(|i| c[i] as f32 / 255.0))
+					.collect();
+			},
+			None => {
+				(self.width, self.height) = self.default_size;
+				self.pixels = std::mem::take(&mut self.default_pixels);
+			},
+		}
+		self.bound_render_target = framebuffer;
+	}
+
+	fn create_program(&mut self, _shaders: &[(ShaderType, String)]) -> Option<u32> {
+		let id = self.next_program;
+		self.next_program += 1;
+		Some(id)
+	}
+
+	fn uniform_location(&mut self, _program: u32, _name: &str) -> Option<i32> {
+		None
+	}
+
+	fn set_viewport(&mut self, pos: &Vector2, size: &Vector2) {
+		self.viewport_pos = *pos;
+		self.viewport_size = *size;
+	}
+
+	fn clear(&mut self, color: &Color, scissor: Option<(&Vector2, &Vector2)>) {
+		let (pos, size) = match scissor {
+			Some((p, s)) => (*p, *s),
+			None => (
+				Vector2::zero(),
+				Vector2::new(self.width as f32, self.height as f32),
+			),
+		};
+		let c = [color.r, color.g, color.b, color.a].map(Self::quantize);
+		let x0 = pos.x.max(0.0) as usize;
+		let y0 = pos.y.max(0.0) as usize;
+		let x1 = ((pos.x + size.x).max(0.0) as usize).min(self.width);
+		let y1 = ((pos.y + size.y).max(0.0) as usize).min(self.height);
+		for y in y0..y1 {
+			for x in x0..x1 {
+				self.pixels[y * self.width + x] = c;
+			}
+		}
+	}
+
+	fn draw(&mut self, draw_call: &DrawCall) {
+		let m = draw_call.mvp_matrix;
+		let vp = self.viewport_pos;
+		let vs = self.viewport_size;
+		for index in (0..draw_call.vertices.len() / 3).map(|i| i * 3) {
+			let screen = [0, 1, 2].map(|i| {
+				let p = &draw_call.vertices[index + i].pos;
+				// column major, like the gpu sees it
+				let x = m[0] * p[0] + m[4] * p[1] + m[8] * p[2] + m[12];
+				let y = m[1] * p[0] + m[5] * p[1] + m[9] * p[2] + m[13];
+				let w = m[3] * p[0] + m[7] * p[1] + m[11] * p[2] + m[15];
+				let w = if w == 0.0 { 1.0 } else { w };
+				[
+					vp.x + (x / w * 0.5 + 0.5) * vs.x,
+					vp.y + (y / w * 0.5 + 0.5) * vs.y,
+				]
+			});
+			self.draw_triangle(draw_call, &screen, index);
+		}
+	}
+
+	fn flush(&mut self) {}
+
+	fn read_pixels(&mut self, width: usize, height: usize) -> Vec<u8> {
+		let mut buffer = vec![0; width * height * 4];
+		for y in 0..height.min(self.height) {
+			for x in 0..width.min(self.width) {
+				let c = self.pixels[y * self.width + x];
+				let p = (y * width + x) * 4;
+				for ci in 0..4 {
+					buffer[p + ci] = (c[ci] * 255.0).round() as u8;
+				}
+			}
+		}
+		buffer
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::math::Matrix44;
+	use crate::renderer::Vertex;
+
+	fn pixel(backend: &mut SoftwareBackend, x: usize, y: usize) -> [u8; 4] {
+		let w = backend.width();
+		let h = backend.height();
+		let p = (y * w + x) * 4;
+		let buffer = backend.read_pixels(w, h);
+		[buffer[p], buffer[p + 1], buffer[p + 2], buffer[p + 3]]
+	}
+
+	fn quad(color: &Color) -> Vec<Vertex> {
+		let v = |x: f32, y: f32| {
+			Vertex::from_pos_with_tex_coords_and_color(
+				&Vector2::new(x, y),
+				&Vector2::new((x + 1.0) * 0.5, (1.0 - y) * 0.5),
+				color,
+			)
+		};
+		// left half of the screen, counter clockwise
+		vec![
+			v(-1.0, 1.0),
+			v(-1.0, -1.0),
+			v(0.0, -1.0),
+			v(0.0, -1.0),
+			v(0.0, 1.0),
+			v(-1.0, 1.0),
+		]
+	}
+
+	fn draw(
+		backend: &mut SoftwareBackend,
+		vertices: &[Vertex],
+		textures: &[u16],
+		blend: (BlendFactor, BlendFactor),
+	) {
+		let uniforms = HashMap::new();
+		backend.draw(&DrawCall {
+			vertices,
+			program: 1,
+			textures,
+			mvp_matrix: &Matrix44::identity(),
+			uniforms: &uniforms,
+			cull_face: true,
+			depth_test: false,
+			blend_source: blend.0,
+			blend_destination: blend.1,
+			textured: !textures.is_empty(),
+			scissor: None,
+		});
+	}
+
+	#[test]
+	fn triangles_are_filled_and_blended() {
+		let mut backend = SoftwareBackend::new(8, 4);
+		backend.clear(&Color::from_rgba(0.0, 0.0, 1.0, 1.0), None);
+
+		let red = Color::from_rgba(1.0, 0.0, 0.0, 0.5);
+		draw(
+			&mut backend,
+			&quad(&red),
+			&[],
+			(BlendFactor::SrcAlpha, BlendFactor::OneMinusSrcAlpha),
+		);
+		assert_eq!([128, 0, 128, 191], pixel(&mut backend, 0, 0));
+		assert_eq!([128, 0, 128, 191], pixel(&mut backend, 3, 3));
+		assert_eq!([0, 0, 255, 255], pixel(&mut backend, 4, 0));
+
+		// back faces are culled
+		let mut back = quad(&Color::white());
+		back.reverse();
+		draw(
+			&mut backend,
+			&back,
+			&[],
+			(BlendFactor::One, BlendFactor::Zero),
+		);
+		assert_eq!([128, 0, 128, 191], pixel(&mut backend, 0, 0));
+
+		// only inside the scissor
+		backend.clear(
+			&Color::black(),
+			Some((&Vector2::new(1.0, 1.0), &Vector2::new(1.0, 1.0))),
+		);
+		assert_eq!([0, 0, 0, 255], pixel(&mut backend, 1, 1));
+		assert_eq!([128, 0, 128, 191], pixel(&mut backend, 2, 1));
+	}
+
+	#[test]
+	fn render_targets_become_textures() {
+		let mut backend = SoftwareBackend::new(4, 4);
+		let texture = backend.create_texture();
+		let framebuffer = backend
+			.create_render_target(texture, 2, 2, RenderTargetFormat::Rgba8)
+			.unwrap();
+
+		backend.bind_render_target(Some(framebuffer));
+		assert_eq!(2, backend.width());
+		backend.clear(&Color::from_rgba(0.0, 1.0, 0.0, 1.0), None);
+		backend.bind_render_target(None);
+		assert_eq!(4, backend.width());
+		assert_eq!([0, 0, 0, 0], pixel(&mut backend, 0, 0));
+
+		// full screen, with the target as texture
+		let mut vertices = quad(&Color::white());
+		vertices.extend(quad(&Color::white()).iter().map(|v| {
+			let mut v = *v;
+			v.pos[0] += 1.0;
+			v
+		}));
+		draw(
+			&mut backend,
+			&vertices,
+			&[texture as u16],
+			(BlendFactor::One, BlendFactor::Zero),
+		);
+		assert_eq!([0, 255, 0, 255], pixel(&mut backend, 0, 0));
+		assert_eq!([0, 255, 0, 255], pixel(&mut backend, 3, 3));
+	}
+
+	#[test]
+	fn textures_are_sampled_nearest() {
+		let mut backend = SoftwareBackend::new(4, 2);
+		let hwid = backend.create_texture();
+		// 2x1, red and green
+		backend.upload_texture(hwid, 2, 1, &[255, 0, 0, 255, 0, 255, 0, 255]);
+
+		// full screen
+		let v = |x: f32, y: f32, u: f32| {
+			Vertex::from_pos_with_tex_coords_and_color(
+				&Vector2::new(x, y),
+				&Vector2::new(u, 0.0),
+				&Color::white(),
+			)
+		};
+		let vertices = vec![
+			v(-1.0, 1.0, 0.0),
+			v(-1.0, -1.0, 0.0),
+			v(1.0, -1.0, 1.0),
+			v(1.0, -1.0, 1.0),
+			v(1.0, 1.0, 1.0),
+			v(-1.0, 1.0, 0.0),
+		];
+		draw(
+			&mut backend,
+			&vertices,
+			&[hwid as u16],
+			(BlendFactor::One, BlendFactor::Zero),
+		);
+		assert_eq!([255, 0, 0, 255], pixel(&mut backend, 0, 0));
+		assert_eq!([255, 0, 0, 255], pixel(&mut backend, 1, 1));
+		assert_eq!([0, 255, 0, 255], pixel(&mut backend, 2, 0));
+		assert_eq!([0, 255, 0, 255], pixel(&mut backend, 3, 1));
+	}
+}
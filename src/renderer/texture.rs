@@ -2,17 +2,19 @@ use derivative::Derivative;
 
 use crate::math::Matrix32;
 use crate::math::Vector2;
-use crate::renderer::{gl, Debug};
+use crate::renderer::RendererBackend;
 use crate::system::System;
 
 #[derive(Derivative)]
 #[derivative(Debug, Default)]
 pub struct Texture {
 	name:                String,
-	hwid:                gl::types::GLuint,
+	hwid:                Option<u32>, // None until registered, see Renderer::register_texture
 	width:               u32,
 	height:              u32,
 	canvas:              Option<Vec<u32>>,
+	#[derivative(Debug = "ignore")]
+	pixels:              Option<Vec<u8>>, // loaded, but not uploaded yet
 	mtx:                 Matrix32,
 	needs_canvas_update: bool,
 }
@@ -29,14 +31,13 @@ impl Texture {
 	pub fn create_canvas(name: &str, size: u32) -> Self {
 		let mut t = Texture::new(name);
 		t.make_canvas(size);
-		t.update_canvas();
 		t
 	}
 
 	pub fn create_from_atlas(name: &str, mtx: &Matrix32, atlas: &Texture) -> Self {
 		Self {
 			name: name.to_string(),
-			hwid: atlas.hwid() as u32,
+			hwid: atlas.hwid,
 			width: 0,  // :TODO:
 			height: 0, // :TODO:
 			canvas: None,
@@ -45,19 +46,22 @@ impl Texture {
 		}
 	}
 
-	pub fn new(name: &str) -> Self {
-		let mut hwid = 0xffff;
-		unsafe {
-			gl::GenTextures(1, &mut hwid);
-			gl::BindTexture(gl::TEXTURE_2D, hwid);
+	// the storage is allocated by RendererBackend::create_render_target after registering
+	pub fn create_render_target(name: &str, width: u32, height: u32) -> Self {
+		let mut t = Texture::new(name);
+		t.set_size(width, height);
+		t
+	}
 
-			gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
-			gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
-		}
+	pub(crate) fn set_size(&mut self, width: u32, height: u32) {
+		self.width = width;
+		self.height = height;
+	}
 
+	pub fn new(name: &str) -> Self {
 		Self {
 			name: name.to_string(),
-			hwid: hwid,
+			hwid: None,
 			width: 0,
 			height: 0,
 			canvas: None,
@@ -71,7 +75,20 @@ impl Texture {
 	}
 
 	pub fn hwid(&self) -> u16 {
-		self.hwid as u16
+		self.hwid.unwrap_or(0) as u16
+	}
+
+	// creates the backend texture, atlas entries share the atlas' one
+	pub(crate) fn register(&mut self, backend: &mut dyn RendererBackend) {
+		if self.hwid.is_none() {
+			self.hwid = Some(backend.create_texture());
+		}
+		if let Some(pixels) = self.pixels.take() {
+			if let Some(hwid) = self.hwid {
+				backend.upload_texture(hwid, self.width, self.height, &pixels);
+			}
+		}
+		self.update_canvas(backend);
 	}
 
 	pub fn mtx(&self) -> &Matrix32 {
@@ -82,14 +99,6 @@ impl Texture {
 		self.mtx = *mtx;
 	}
 
-	pub fn bind(&self) {
-		// :TODO: support texture channels
-		unsafe {
-			gl::ActiveTexture(gl::TEXTURE0);
-			gl::BindTexture(gl::TEXTURE_2D, self.hwid);
-		}
-	}
-
 	pub fn clear(&mut self) {
 		if let Some(c) = &mut self.canvas {
 			c.fill(0);
@@ -106,39 +115,21 @@ impl Texture {
 
 	// Note: This is called in the time critical part of the render path
 	// Do not do too much work, or _block_
-	pub fn update(&mut self) {
+	pub fn update(&mut self, backend: &mut dyn RendererBackend) {
 		if self.needs_canvas_update {
 			self.needs_canvas_update = false;
-			self.update_canvas();
+			self.update_canvas(backend);
 		}
 	}
 
 	pub fn queue_canvas_update(&mut self) {
 		self.needs_canvas_update = true;
 	}
-	pub fn update_canvas(&mut self) {
-		if let Some(c) = &self.canvas {
-			unsafe {
-				gl::BindTexture(gl::TEXTURE_2D, self.hwid);
-				gl::TexImage2D(
-					gl::TEXTURE_2D,
-					0, // mimap
-					gl::RGBA8 as i32,
-					self.width as i32,
-					self.height as i32,
-					0, // border
-					gl::RGBA,
-					gl::UNSIGNED_BYTE,
-					//std::ptr::null(),
-					// dummy_buf.as_ptr() as *const _,
-					//i.into_raw().as_ptr() as *const _,
-					c.as_ptr() as *const _,
-				);
-
-				Debug::check_gl_error(std::file!(), std::line!());
-
-				//				gl::GenerateMipmap( gl::TEXTURE_2D );
-			}
+	pub fn update_canvas(&mut self, backend: &mut dyn RendererBackend) {
+		if let (Some(c), Some(hwid)) = (&self.canvas, self.hwid) {
+			// texels are 0xaabbggrr
+			let rgba = c.iter().flat_map(|t| t.to_le_bytes()).collect::<Vec<_>>();
+			backend.upload_texture(hwid, self.width, self.height, &rgba);
 		}
 	}
 	fn make_canvas(&mut self, size: u32) {
@@ -185,26 +176,7 @@ impl Texture {
 						self.width = w;
 						self.height = h;
 
-						unsafe {
-							gl::BindTexture(gl::TEXTURE_2D, self.hwid);
-							gl::TexImage2D(
-								gl::TEXTURE_2D,
-								0, // mimap
-								gl::RGBA8 as i32,
-								w as i32,
-								h as i32,
-								0, // border
-								gl::RGBA,
-								gl::UNSIGNED_BYTE,
-								//std::ptr::null(),
-								// dummy_buf.as_ptr() as *const _,
-								i.into_raw().as_ptr() as *const _,
-							);
-
-							Debug::check_gl_error(std::file!(), std::line!());
-
-							//							gl::GenerateMipmap( gl::TEXTURE_2D );
-						}
+						self.pixels = Some(i.into_raw());
 
 						return true;
 					},
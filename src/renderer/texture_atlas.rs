@@ -96,7 +96,8 @@ impl TextureAtlas {
 		let mut total_textures_registered = 0;
 		for name in to_load.iter() {
 			let name_atlas = format!("{}.atlas", &name);
-			let t = Texture::create(system, &name);
+			// first, so the entries get its hwid
+			let index = renderer.register_texture(Texture::create(system, name));
 
 			let mut ta = TextureAtlas::new();
 			ta.load(system, &name_atlas);
@@ -107,13 +108,14 @@ impl TextureAtlas {
 				let first_dot = name_wo_ext.find(".").unwrap_or(name_wo_ext.len());
 				name_wo_ext.truncate(first_dot);
 
-				let te = Texture::create_from_atlas(&name_wo_ext, &e.mtx, &t);
+				let te = match renderer.texture(index) {
+					Some(t) => Texture::create_from_atlas(&name_wo_ext, &e.mtx, t),
+					None => continue,
+				};
 				//				println!("Registering atlas (sub) texture '{}' with renderer {:?}", &name_wo_ext, &te);
 				renderer.register_texture(te);
 				total_textures_registered += 1;
 			}
-
-			renderer.register_texture(t);
 		}
 		total_textures_registered
 	}
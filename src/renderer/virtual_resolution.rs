@@ -0,0 +1,215 @@
+use crate::math::{Matrix44, Vector2};
+use crate::renderer::Color;
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum ScalingPolicy {
+	// everything visible, with black bars on the sides (pillarbox) or top and bottom (letterbox)
+	#[default]
+	Fit,
+	// no bars, parts of the design are cropped
+	Fill,
+	// whole number scales only, e.g. for pixel art, falls back to Fit when the window is too small
+	IntegerScale,
+}
+
+// a fixed design resolution mapped onto the window, see Renderer::set_virtual_resolution
+// design coordinates have their origin in the center, y up
+// follows the window the Renderer was set up with, see Renderer::set_window_size
+#[derive(Debug, Copy, Clone)]
+pub struct VirtualResolution {
+	design_size: Vector2,
+	policy:      ScalingPolicy,
+	bar_color:   Color,
+
+	window_size:   Vector2, // in physical pixels
+	scale:         f32,
+	viewport_pos:  Vector2,
+	viewport_size: Vector2,
+}
+
+impl VirtualResolution {
+	pub fn new(design_size: &Vector2) -> Self {
+		let mut vr = Self {
+			design_size:   *design_size,
+			policy:        ScalingPolicy::default(),
+			bar_color:     Color::black(),
+			window_size:   *design_size,
+			scale:         1.0,
+			viewport_pos:  Vector2::zero(),
+			viewport_size: *design_size,
+		};
+		vr.recalculate();
+		vr
+	}
+
+	pub fn with_policy(mut self, policy: ScalingPolicy) -> Self {
+		self.policy = policy;
+		self.recalculate();
+		self
+	}
+
+	pub fn with_bar_color(mut self, bar_color: &Color) -> Self {
+		self.bar_color = *bar_color;
+		self
+	}
+
+	pub fn design_size(&self) -> &Vector2 {
+		&self.design_size
+	}
+
+	pub fn policy(&self) -> ScalingPolicy {
+		self.policy
+	}
+
+	pub fn bar_color(&self) -> &Color {
+		&self.bar_color
+	}
+
+	// design pixels to window pixels
+	pub fn scale(&self) -> f32 {
+		self.scale
+	}
+
+	// in window pixels, the offset is negative when cropping
+	pub fn viewport_pos(&self) -> &Vector2 {
+		&self.viewport_pos
+	}
+
+	pub fn viewport_size(&self) -> &Vector2 {
+		&self.viewport_size
+	}
+
+	// true when parts of the window are not covered by the design
+	pub fn has_bars(&self) -> bool {
+		self.viewport_pos.x > 0.0 || self.viewport_pos.y > 0.0
+	}
+
+	// returns true when the viewport changed
+	pub fn set_window_size(&mut self, window_size: &Vector2) -> bool {
+		if self.window_size == *window_size {
+			return false;
+		}
+		self.window_size = *window_size;
+		self.recalculate();
+		true
+	}
+
+	fn recalculate(&mut self) {
+		let w = &self.window_size;
+		let d = &self.design_size;
+		if w.x <= 0.0 || w.y <= 0.0 || d.x <= 0.0 || d.y <= 0.0 {
+			return;
+		}
+		let fit = (w.x / d.x).min(w.y / d.y);
+		self.scale = match self.policy {
+			ScalingPolicy::Fit => fit,
+			ScalingPolicy::Fill => (w.x / d.x).max(w.y / d.y),
+			ScalingPolicy::IntegerScale => {
+				if fit >= 1.0 {
+					fit.floor()
+				} else {
+					fit
+				}
+			},
+		};
+		let size = d.scaled(self.scale);
+		self.viewport_size = Vector2::new(size.x.round(), size.y.round());
+		self.viewport_pos = Vector2::new(
+			((w.x - self.viewport_size.x) * 0.5).round(),
+			((w.y - self.viewport_size.y) * 0.5).round(),
+		);
+	}
+
+	pub fn mvp_matrix(&self) -> Matrix44 {
+		let hs = self.design_size.scaled(0.5);
+		Matrix44::ortho(-hs.x, hs.x, -hs.y, hs.y, 1.0, -1.0)
+	}
+
+	// e.g. from WindowUpdateContext::mouse_pos_pixels, origin bottom left
+	pub fn window_to_design(&self, pos_pixels: &Vector2) -> Vector2 {
+		pos_pixels
+			.sub(&self.viewport_pos)
+			.scaled(1.0 / self.scale)
+			.sub(&self.design_size.scaled(0.5))
+	}
+
+	pub fn design_to_window(&self, pos: &Vector2) -> Vector2 {
+		pos.add(&self.design_size.scaled(0.5))
+			.scaled(self.scale)
+			.add(&self.viewport_pos)
+	}
+
+	// false for positions on the bars, or cropped away
+	pub fn is_inside_design(&self, pos: &Vector2) -> bool {
+		let hs = self.design_size.scaled(0.5);
+		pos.x >= -hs.x && pos.x <= hs.x && pos.y >= -hs.y && pos.y <= hs.y
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn fit_adds_bars_on_the_short_side() {
+		let mut vr = VirtualResolution::new(&Vector2::new(1920.0, 1080.0));
+
+		// 4:3 gets letterboxed
+		assert!(vr.set_window_size(&Vector2::new(1024.0, 768.0)));
+		assert_eq!(Vector2::new(1024.0, 576.0), *vr.viewport_size());
+		assert_eq!(Vector2::new(0.0, 96.0), *vr.viewport_pos());
+		assert!(vr.has_bars());
+
+		// ultra wide gets pillarboxed
+		vr.set_window_size(&Vector2::new(3440.0, 1440.0));
+		assert_eq!(Vector2::new(2560.0, 1440.0), *vr.viewport_size());
+		assert_eq!(Vector2::new(440.0, 0.0), *vr.viewport_pos());
+		assert!(!vr.set_window_size(&Vector2::new(3440.0, 1440.0)));
+	}
+
+	#[test]
+	fn fill_crops_and_integer_scale_keeps_whole_pixels() {
+		let mut vr =
+			VirtualResolution::new(&Vector2::new(320.0, 180.0)).with_policy(ScalingPolicy::Fill);
+		vr.set_window_size(&Vector2::new(1024.0, 768.0));
+		assert_eq!(Vector2::new(1365.0, 768.0), *vr.viewport_size());
+		assert_eq!(Vector2::new(-171.0, 0.0), *vr.viewport_pos());
+		assert!(!vr.has_bars());
+
+		let mut vr = vr.with_policy(ScalingPolicy::IntegerScale);
+		vr.set_window_size(&Vector2::new(1000.0, 700.0));
+		assert_eq!(3.0, vr.scale());
+		assert_eq!(Vector2::new(960.0, 540.0), *vr.viewport_size());
+		assert_eq!(Vector2::new(20.0, 80.0), *vr.viewport_pos());
+
+		vr.set_window_size(&Vector2::new(160.0, 90.0));
+		assert_eq!(0.5, vr.scale());
+	}
+
+	#[test]
+	fn mouse_positions_map_to_design_coordinates() {
+		let mut vr = VirtualResolution::new(&Vector2::new(1920.0, 1080.0));
+		vr.set_window_size(&Vector2::new(960.0, 740.0));
+
+		// center, and the corners of the viewport
+		assert_eq!(
+			Vector2::zero(),
+			vr.window_to_design(&Vector2::new(480.0, 370.0))
+		);
+		assert_eq!(
+			Vector2::new(-960.0, -540.0),
+			vr.window_to_design(&Vector2::new(0.0, 100.0))
+		);
+		assert_eq!(
+			Vector2::new(960.0, 540.0),
+			vr.window_to_design(&Vector2::new(960.0, 640.0))
+		);
+		assert_eq!(
+			Vector2::new(960.0, 640.0),
+			vr.design_to_window(&Vector2::new(960.0, 540.0))
+		);
+
+		// on the bottom bar
+		assert!(!vr.is_inside_design(&vr.window_to_design(&Vector2::new(480.0, 50.0))));
+	}
+}
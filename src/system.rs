@@ -72,6 +72,12 @@ impl System {
 		dir
 	}
 
+	// the per-user config directory, e.g. ~/.config/name, created when saving into it
+	pub fn get_config_dir(name: &str) -> Option<String> {
+		let config_dir = dirs_next::config_dir()?;
+		Some(config_dir.join(name).to_string_lossy().to_string())
+	}
+
 	pub fn get_resource_path(name: &str) -> Option<String> {
 		let exe_dir = std::env::current_exe().unwrap();
 		let exe_path = Path::new(&exe_dir).parent().unwrap();
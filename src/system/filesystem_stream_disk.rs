@@ -111,11 +111,12 @@ impl FilesystemStream for FilesystemStreamDisk {
 			Some(f) => {
 				let mut buf = [0];
 				match f.read(&mut buf) {
-					Ok(_) => {
+					Ok(1) => {
 						self.pos += 1;
 						buf[0]
 					},
-					Err(_) => 0,
+					// Ok(0) at the end of the file
+					_ => 0,
 				}
 			},
 			None => 0,
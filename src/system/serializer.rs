@@ -32,11 +32,33 @@ impl Serializer {
 		self.any_error
 	}
 
+	// when reading, false (and an error) if count items of at least min_size bytes can't be in the rest of the file,
+	// e.g. to check counts before allocating for them
+	pub fn check_count(&mut self, count: usize, min_size: usize) -> bool {
+		match self.mode {
+			SerializerMode::Read => {
+				let remaining = self.file.size().saturating_sub(self.file.pos());
+				if count.saturating_mul(min_size.max(1)) > remaining {
+					self.any_error = true;
+					return false;
+				}
+				true
+			},
+			SerializerMode::Write => true,
+		}
+	}
+
 	pub fn serialize_u8(&mut self, data: &mut u8) {
 		self.byte_count += 1;
 		match self.mode {
 			SerializerMode::Read => {
-				*data = self.file.read_u8();
+				// reading past the end is an error, not zeros
+				if self.file.eof() {
+					self.any_error = true;
+					*data = 0;
+				} else {
+					*data = self.file.read_u8();
+				}
 			},
 			SerializerMode::Write => {
 				self.file.write_u8(*data);
@@ -68,6 +90,46 @@ impl Serializer {
 		*data = ((a as u32) << 24) | ((b as u32) << 16) | ((c as u32) << 8) | (d as u32);
 	}
 
+	pub fn serialize_u64(&mut self, data: &mut u64) {
+		let mut h = (*data >> 32) as u32;
+		let mut l = (*data & 0xffff_ffff) as u32;
+
+		self.serialize_u32(&mut h);
+		self.serialize_u32(&mut l);
+
+		*data = ((h as u64) << 32) | (l as u64);
+	}
+
+	pub fn serialize_f32(&mut self, data: &mut f32) {
+		let mut bits = data.to_bits();
+		self.serialize_u32(&mut bits);
+		*data = f32::from_bits(bits);
+	}
+
+	pub fn serialize_f64(&mut self, data: &mut f64) {
+		let mut bits = data.to_bits();
+		self.serialize_u64(&mut bits);
+		*data = f64::from_bits(bits);
+	}
+
+	// utf-8, prefixed with the length in bytes
+	pub fn serialize_string(&mut self, data: &mut String) {
+		let mut bytes = data.as_bytes().to_vec();
+		let mut len = bytes.len() as u32;
+		self.serialize_u32(&mut len);
+		if !self.check_count(len as usize, 1) {
+			return;
+		}
+		bytes.resize(len as usize, 0);
+		for b in bytes.iter_mut() {
+			self.serialize_u8(b);
+		}
+		match String::from_utf8(bytes) {
+			Ok(s) => *data = s,
+			Err(_) => self.any_error = true,
+		}
+	}
+
 	pub fn serialize_bool(&mut self, value: &mut bool) {
 		let mut v: u8 = if *value { 1 } else { 0 };
 
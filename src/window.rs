@@ -5,5 +5,29 @@ pub use window::WindowUserData;
 pub mod window_update_context;
 pub use window::WindowUpdateContext;
 pub use window_update_context::ModifierKey;
+pub mod fixed_timestep;
+pub use fixed_timestep::FixedTimestep;
+pub mod frame_pacing;
+pub use frame_pacing::{FrameLimiter, FramePacing};
+pub mod focus_policy;
+pub use focus_policy::FocusPolicy;
+pub mod key_code;
+pub use key_code::KeyCode;
+pub mod text_input;
+pub use text_input::TextInputEvent;
+pub mod input_recording;
+pub use input_recording::{InputFrame, InputRecorder, InputRecorderState, InputRecording};
+pub mod touch;
+pub use touch::{Touch, TouchPhase};
+pub mod gamepad;
+pub use gamepad::{
+	DeadZone, Gamepad, GamepadAxis, GamepadButton, GamepadState, Gamepads, ScriptedGamepad,
+};
+pub mod gestures;
+pub use gestures::{GestureConfig, GestureEvent, GestureRecognizer};
+pub mod action_map;
+pub use action_map::{ActionMap, InputAction, InputBinding};
+pub mod window_mode;
+pub use window_mode::{MonitorInfo, WindowMode};
 pub mod window_layout;
-pub use window_layout::WindowLayout;
+pub use window_layout::{WindowLayout, WindowLayoutSaver};
@@ -0,0 +1,461 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use tracing::*;
+
+use crate::system::filesystem::Filesystem;
+use crate::system::System;
+use crate::window::{GamepadAxis, GamepadButton, KeyCode, WindowUpdateContext};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub enum InputBinding {
+	Key(KeyCode),
+	MouseButton(u8),                  // index into WindowUpdateContext::mouse_buttons
+	GamepadButton(u8, GamepadButton), // gamepad index, button
+	// one direction of a stick, or a trigger, counts as down past half way
+	GamepadAxis {
+		pad:      u8,
+		axis:     GamepadAxis,
+		positive: bool,
+	},
+}
+
+impl InputBinding {
+	const AXIS_DOWN_THRESHOLD: f32 = 0.5;
+
+	// 0.0 .. 1.0, analog for gamepad axes
+	pub fn value(&self, wuc: &WindowUpdateContext) -> f32 {
+		match self {
+			InputBinding::GamepadAxis {
+				pad,
+				axis,
+				positive,
+			} => {
+				let v = wuc.gamepad_axis(*pad as usize, *axis);
+				let v = if *positive { v } else { -v };
+				v.clamp(0.0, 1.0)
+			},
+			_ => {
+				if self.is_down(wuc) {
+					1.0
+				} else {
+					0.0
+				}
+			},
+		}
+	}
+
+	pub fn is_down(&self, wuc: &WindowUpdateContext) -> bool {
+		match self {
+			InputBinding::GamepadButton(pad, button) => {
+				wuc.is_gamepad_button_down(*pad as usize, *button)
+			},
+			InputBinding::GamepadAxis { .. } => self.value(wuc) > Self::AXIS_DOWN_THRESHOLD,
+			InputBinding::Key(key) => wuc.is_key_down(*key),
+			InputBinding::MouseButton(b) => {
+				wuc.mouse_buttons.get(*b as usize).copied().unwrap_or(false)
+			},
+		}
+	}
+
+	pub fn was_down(&self, wuc: &WindowUpdateContext) -> bool {
+		let (pressed, released) = match self {
+			InputBinding::GamepadButton(pad, button) => (
+				wuc.was_gamepad_button_pressed(*pad as usize, *button),
+				wuc.was_gamepad_button_released(*pad as usize, *button),
+			),
+			InputBinding::GamepadAxis {
+				pad,
+				axis,
+				positive,
+			} => {
+				let v = wuc.previous_gamepad_axis(*pad as usize, *axis);
+				let v = if *positive { v } else { -v };
+				return v > Self::AXIS_DOWN_THRESHOLD;
+			},
+			InputBinding::Key(key) => (wuc.was_key_pressed(*key), wuc.was_key_released(*key)),
+			InputBinding::MouseButton(b) => {
+				let b = *b as usize;
+				if b >= wuc.mouse_buttons.len() {
+					return false;
+				}
+				(
+					wuc.was_mouse_button_pressed(b),
+					wuc.was_mouse_button_released(b),
+				)
+			},
+		};
+		(self.is_down(wuc) && !pressed) || released
+	}
+
+	// the first binding pressed this frame, e.g. for a "press a key" rebinding menu
+	pub fn first_pressed(wuc: &WindowUpdateContext) -> Option<Self> {
+		for key in KeyCode::ALL.iter() {
+			if *key != KeyCode::Unknown && wuc.was_key_pressed(*key) {
+				return Some(InputBinding::Key(*key));
+			}
+		}
+		for b in 0..wuc.mouse_buttons.len() {
+			if wuc.was_mouse_button_pressed(b) {
+				return Some(InputBinding::MouseButton(b as u8));
+			}
+		}
+		for pad in 0..wuc.gamepad_count() {
+			for button in GamepadButton::ALL.iter() {
+				if wuc.was_gamepad_button_pressed(pad, *button) {
+					return Some(InputBinding::GamepadButton(pad as u8, *button));
+				}
+			}
+		}
+		None
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub enum InputAction {
+	Button {
+		bindings: Vec<InputBinding>,
+	},
+	// -1.0 .. 1.0, from a negative and a positive set of bindings, e.g. A and D
+	Axis {
+		negative: Vec<InputBinding>,
+		positive: Vec<InputBinding>,
+	},
+}
+
+impl InputAction {
+	fn any(bindings: &[InputBinding], f: impl Fn(&InputBinding) -> bool) -> bool {
+		bindings.iter().any(f)
+	}
+
+	fn is_down(&self, wuc: &WindowUpdateContext) -> bool {
+		match self {
+			InputAction::Button { bindings } => Self::any(bindings, |b| b.is_down(wuc)),
+			InputAction::Axis { negative, positive } => {
+				Self::any(negative, |b| b.is_down(wuc)) || Self::any(positive, |b| b.is_down(wuc))
+			},
+		}
+	}
+
+	fn was_down(&self, wuc: &WindowUpdateContext) -> bool {
+		match self {
+			InputAction::Button { bindings } => Self::any(bindings, |b| b.was_down(wuc)),
+			InputAction::Axis { negative, positive } => {
+				Self::any(negative, |b| b.was_down(wuc)) || Self::any(positive, |b| b.was_down(wuc))
+			},
+		}
+	}
+
+	fn max_value(bindings: &[InputBinding], wuc: &WindowUpdateContext) -> f32 {
+		bindings.iter().map(|b| b.value(wuc)).fold(0.0, f32::max)
+	}
+
+	fn axis(&self, wuc: &WindowUpdateContext) -> f32 {
+		match self {
+			InputAction::Button { bindings } => Self::max_value(bindings, wuc),
+			InputAction::Axis { negative, positive } => {
+				Self::max_value(positive, wuc) - Self::max_value(negative, wuc)
+			},
+		}
+	}
+}
+
+// named actions, e.g. "jump" bound to Space, W, and the left mouse button
+#[derive(Debug, Default, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ActionMap {
+	actions: BTreeMap<String, InputAction>,
+}
+
+impl ActionMap {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn actions(&self) -> &BTreeMap<String, InputAction> {
+		&self.actions
+	}
+
+	pub fn action(&self, name: &str) -> Option<&InputAction> {
+		self.actions.get(name)
+	}
+
+	// adds to existing bindings, turns an axis into a button
+	pub fn bind(&mut self, name: &str, binding: InputBinding) {
+		match self.actions.get_mut(name) {
+			Some(InputAction::Button { bindings }) => {
+				if !bindings.contains(&binding) {
+					bindings.push(binding);
+				}
+			},
+			_ => {
+				self.actions.insert(
+					name.to_string(),
+					InputAction::Button {
+						bindings: vec![binding],
+					},
+				);
+			},
+		}
+	}
+
+	pub fn bind_axis(&mut self, name: &str, negative: InputBinding, positive: InputBinding) {
+		match self.actions.get_mut(name) {
+			Some(InputAction::Axis {
+				negative: n,
+				positive: p,
+			}) => {
+				if !n.contains(&negative) {
+					n.push(negative);
+				}
+				if !p.contains(&positive) {
+					p.push(positive);
+				}
+			},
+			_ => {
+				self.actions.insert(
+					name.to_string(),
+					InputAction::Axis {
+						negative: vec![negative],
+						positive: vec![positive],
+					},
+				);
+			},
+		}
+	}
+
+	// replaces old with new, e.g. from a key rebinding menu, returns false if old wasn't bound
+	pub fn rebind(&mut self, name: &str, old: InputBinding, new: InputBinding) -> bool {
+		let lists: Vec<&mut Vec<InputBinding>> = match self.actions.get_mut(name) {
+			Some(InputAction::Button { bindings }) => vec![bindings],
+			Some(InputAction::Axis { negative, positive }) => vec![negative, positive],
+			None => return false,
+		};
+		for bindings in lists {
+			if let Some(b) = bindings.iter_mut().find(|b| **b == old) {
+				*b = new;
+				return true;
+			}
+		}
+		false
+	}
+
+	pub fn unbind(&mut self, name: &str, binding: InputBinding) {
+		match self.actions.get_mut(name) {
+			Some(InputAction::Button { bindings }) => bindings.retain(|b| *b != binding),
+			Some(InputAction::Axis { negative, positive }) => {
+				negative.retain(|b| *b != binding);
+				positive.retain(|b| *b != binding);
+			},
+			None => {},
+		}
+	}
+
+	pub fn remove_action(&mut self, name: &str) {
+		self.actions.remove(name);
+	}
+
+	pub fn is_action_down(&self, name: &str, wuc: &WindowUpdateContext) -> bool {
+		self.actions
+			.get(name)
+			.map(|a| a.is_down(wuc))
+			.unwrap_or(false)
+	}
+
+	pub fn was_action_pressed(&self, name: &str, wuc: &WindowUpdateContext) -> bool {
+		self.actions
+			.get(name)
+			.map(|a| a.is_down(wuc) && !a.was_down(wuc))
+			.unwrap_or(false)
+	}
+
+	pub fn was_action_released(&self, name: &str, wuc: &WindowUpdateContext) -> bool {
+		self.actions
+			.get(name)
+			.map(|a| !a.is_down(wuc) && a.was_down(wuc))
+			.unwrap_or(false)
+	}
+
+	// -1.0 .. 1.0 for axis actions, 0.0 .. 1.0 for buttons, analog for gamepad axes
+	pub fn axis(&self, name: &str, wuc: &WindowUpdateContext) -> f32 {
+		self.actions.get(name).map(|a| a.axis(wuc)).unwrap_or(0.0)
+	}
+
+	pub fn from_yaml(yaml: &str) -> anyhow::Result<Self> {
+		let map = serde_yaml::from_str(yaml)?;
+		Ok(map)
+	}
+
+	pub fn to_yaml(&self) -> anyhow::Result<String> {
+		let s = serde_yaml::to_string(&self)?;
+		Ok(s)
+	}
+
+	pub fn load_from_filesystem(fs: &mut dyn Filesystem, name: &str) -> anyhow::Result<Self> {
+		let mut f = fs.open(name);
+		if !f.is_valid() {
+			anyhow::bail!("couldn't read from {:?}", f);
+		}
+		let yaml = f.read_as_string();
+		Self::from_yaml(&yaml)
+	}
+
+	pub fn save_to_filesystem(&self, fs: &mut dyn Filesystem, name: &str) -> anyhow::Result<()> {
+		if !fs.writable() {
+			anyhow::bail!("Filesystem {} is not writable", fs.name());
+		}
+		let yaml = self.to_yaml()?;
+		let mut f = fs.create(name, true);
+		if !f.is_valid() {
+			anyhow::bail!("couldn't write to {:?}", f);
+		}
+		for b in yaml.bytes() {
+			f.write_u8(b);
+		}
+		Ok(())
+	}
+
+	// user rebindings from the savegame filesystem win over the defaults shipped in the data
+	pub fn load(system: &mut System, name: &str) -> anyhow::Result<Self> {
+		if system.savegame_filesystem_mut().exists(name) {
+			match Self::load_from_filesystem(system.savegame_filesystem_mut().as_mut(), name) {
+				Ok(map) => return Ok(map),
+				Err(e) => {
+					warn!("Ignoring saved action map {}: {:?}", name, e);
+				},
+			}
+		}
+		Self::load_from_filesystem(system.default_filesystem_mut().as_mut(), name)
+	}
+
+	// saves to the savegame filesystem
+	pub fn save(&self, system: &mut System, name: &str) -> anyhow::Result<()> {
+		self.save_to_filesystem(system.savegame_filesystem_mut().as_mut(), name)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::math::Vector2;
+	use crate::system::filesystem_disk::FilesystemDisk;
+	use crate::window::{DeadZone, Gamepads, ScriptedGamepad};
+
+	fn jump_and_move() -> ActionMap {
+		let mut map = ActionMap::new();
+		map.bind("jump", InputBinding::Key(KeyCode::Space));
+		map.bind("jump", InputBinding::Key(KeyCode::W));
+		map.bind("jump", InputBinding::MouseButton(0));
+		map.bind_axis(
+			"move_x",
+			InputBinding::Key(KeyCode::A),
+			InputBinding::Key(KeyCode::D),
+		);
+		map
+	}
+
+	#[test]
+	fn actions_follow_their_bindings() {
+		let mut map = jump_and_move();
+		let mut wuc = WindowUpdateContext::new();
+
+		wuc.set_key_down(KeyCode::W, true);
+		wuc.set_key_down(KeyCode::D, true);
+		assert!(map.was_action_pressed("jump", &wuc));
+		assert_eq!(1.0, map.axis("move_x", &wuc));
+		wuc.update();
+
+		// another binding of a held action doesn't press it again
+		wuc.set_mouse_button(0, true);
+		wuc.set_key_down(KeyCode::A, true);
+		assert!(map.is_action_down("jump", &wuc));
+		assert!(!map.was_action_pressed("jump", &wuc));
+		assert_eq!(0.0, map.axis("move_x", &wuc));
+		wuc.update();
+
+		wuc.set_key_down(KeyCode::W, false);
+		wuc.set_mouse_button(0, false);
+		assert!(map.was_action_released("jump", &wuc));
+		wuc.update();
+
+		assert!(map.rebind(
+			"jump",
+			InputBinding::Key(KeyCode::Space),
+			InputBinding::Key(KeyCode::Up)
+		));
+		wuc.set_key_down(KeyCode::Space, true);
+		assert!(!map.is_action_down("jump", &wuc));
+		wuc.set_key_down(KeyCode::Up, true);
+		assert!(map.was_action_pressed("jump", &wuc));
+		assert_eq!(
+			Some(InputBinding::Key(KeyCode::Space)),
+			InputBinding::first_pressed(&wuc)
+		);
+	}
+
+	#[test]
+	fn gamepads_drive_actions() {
+		let mut map = ActionMap::new();
+		map.bind("jump", InputBinding::GamepadButton(0, GamepadButton::South));
+		map.bind_axis(
+			"move_x",
+			InputBinding::GamepadAxis {
+				pad:      0,
+				axis:     GamepadAxis::LeftX,
+				positive: false,
+			},
+			InputBinding::GamepadAxis {
+				pad:      0,
+				axis:     GamepadAxis::LeftX,
+				positive: true,
+			},
+		);
+
+		let mut pad = ScriptedGamepad::new("test");
+		pad.set_button(GamepadButton::South, true);
+		pad.set_left_stick(&Vector2::new(-0.25, 0.0));
+		let mut gamepads = Gamepads::new().with_stick_dead_zone(DeadZone::None);
+		gamepads.add(Box::new(pad));
+		let mut wuc = WindowUpdateContext::new();
+
+		gamepads.poll(&mut wuc);
+		assert!(map.was_action_pressed("jump", &wuc));
+		assert_eq!(-0.25, map.axis("move_x", &wuc));
+		assert!(!map.is_action_down("move_x", &wuc));
+	}
+
+	#[test]
+	fn action_map_round_trips_through_savegame_filesystem() -> anyhow::Result<()> {
+		let dir = std::env::temp_dir().join(format!("oml-game-actions-{}", std::process::id()));
+		std::fs::create_dir_all(&dir)?;
+		let mut fs = FilesystemDisk::new(&dir.to_string_lossy());
+		fs.enable_write();
+		let mut system = System::new();
+		system.set_savegame_filesystem(Box::new(fs));
+
+		let map = jump_and_move();
+		map.save(&mut system, "actions.yaml")?;
+		let loaded = ActionMap::load(&mut system, "actions.yaml")?;
+		assert_eq!(map, loaded);
+
+		let yaml = "
+actions:
+  jump: !Button
+    bindings: [ !Key Space, !MouseButton 0 ]
+  move_x: !Axis
+    negative: [ !Key Left ]
+    positive: [ !Key Right ]
+";
+		let map = ActionMap::from_yaml(yaml)?;
+		assert_eq!(2, map.actions().len());
+		assert_eq!(
+			Some(&InputAction::Axis {
+				negative: vec![InputBinding::Key(KeyCode::Left)],
+				positive: vec![InputBinding::Key(KeyCode::Right)],
+			}),
+			map.action("move_x")
+		);
+
+		std::fs::remove_dir_all(&dir)?;
+		Ok(())
+	}
+}
@@ -0,0 +1,115 @@
+#[derive(Debug, Copy, Clone)]
+pub struct FixedTimestep {
+	time_step:      f64,
+	max_frame_time: f64,
+	accumulator:    f64,
+}
+
+impl Default for FixedTimestep {
+	fn default() -> Self {
+		Self {
+			time_step:      1.0 / 120.0,
+			max_frame_time: 0.25,
+			accumulator:    0.0,
+		}
+	}
+}
+
+impl FixedTimestep {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn with_tick_rate(mut self, tick_rate: f64) -> Self {
+		// a negative step would never leave advance, a zero rate would never tick
+		if !(tick_rate.is_finite() && tick_rate > 0.0) {
+			panic!("Tick rate must be positive and finite. Got {}", tick_rate);
+		}
+		self.time_step = 1.0 / tick_rate;
+		self
+	}
+
+	// longer frames are clamped to avoid a spiral of death after a stall
+	pub fn with_max_frame_time(mut self, max_frame_time: f64) -> Self {
+		self.max_frame_time = max_frame_time;
+		self
+	}
+
+	pub fn time_step(&self) -> f64 {
+		self.time_step
+	}
+
+	pub fn tick_rate(&self) -> f64 {
+		1.0 / self.time_step
+	}
+
+	pub fn max_frame_time(&self) -> f64 {
+		self.max_frame_time
+	}
+
+	pub fn reset(&mut self) {
+		self.accumulator = 0.0;
+	}
+
+	// returns the number of fixed updates to run
+	pub fn advance(&mut self, frame_time: f64) -> u32 {
+		let frame_time = frame_time.clamp(0.0, self.max_frame_time);
+		self.accumulator += frame_time;
+
+		let mut ticks = 0;
+		while self.accumulator >= self.time_step {
+			self.accumulator -= self.time_step;
+			ticks += 1;
+		}
+		ticks
+	}
+
+	// 0..1 into the next fixed update, for interpolating between states
+	pub fn alpha(&self) -> f64 {
+		self.accumulator / self.time_step
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn ticks_do_not_depend_on_frame_rate() {
+		let mut slow = FixedTimestep::default().with_tick_rate(100.0);
+		let mut fast = FixedTimestep::default().with_tick_rate(100.0);
+
+		let slow_ticks: u32 = (0..30).map(|_| slow.advance(1.0 / 30.0)).sum();
+		let fast_ticks: u32 = (0..144).map(|_| fast.advance(1.0 / 144.0)).sum();
+
+		// one second of wall time, allow for the rounding of the last partial tick
+		assert!((99..=100).contains(&slow_ticks));
+		assert!((99..=100).contains(&fast_ticks));
+	}
+
+	#[test]
+	fn long_frames_are_clamped() {
+		let mut ft = FixedTimestep::default()
+			.with_tick_rate(10.0)
+			.with_max_frame_time(0.25);
+
+		assert_eq!(2, ft.advance(5.0));
+		assert!((ft.alpha() - 0.5).abs() < 0.0001);
+	}
+
+	#[test]
+	#[should_panic]
+	fn tick_rate_must_be_positive() {
+		let _ = FixedTimestep::default().with_tick_rate(-60.0);
+	}
+
+	#[test]
+	fn alpha_tracks_remainder() {
+		let mut ft = FixedTimestep::default().with_tick_rate(4.0);
+
+		assert_eq!(0, ft.advance(0.125));
+		assert!((ft.alpha() - 0.5).abs() < 0.0001);
+		assert_eq!(1, ft.advance(0.125));
+		assert!(ft.alpha().abs() < 0.0001);
+	}
+}
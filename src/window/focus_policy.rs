@@ -0,0 +1,161 @@
+use crate::window::{FrameLimiter, FramePacing, WindowUpdateContext};
+
+// what happens while the window is in the background, see App::focus_policy
+#[derive(Debug, Copy, Clone)]
+pub struct FocusPolicy {
+	pause_when_unfocused:       bool,
+	pause_when_suspended:       bool, // minimised, occluded, or suspended by the os
+	render_when_suspended:      bool,
+	unfocused_target_fps:       Option<f64>,
+	suspended_target_fps:       Option<f64>,
+	release_keys_on_focus_loss: bool,
+}
+
+// changes nothing, see pause_in_background
+impl Default for FocusPolicy {
+	fn default() -> Self {
+		Self {
+			pause_when_unfocused:       false,
+			pause_when_suspended:       false,
+			render_when_suspended:      true,
+			unfocused_target_fps:       None,
+			suspended_target_fps:       None,
+			release_keys_on_focus_loss: false,
+		}
+	}
+}
+
+impl FocusPolicy {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	// while suspended pauses fixed_update, skips rendering since nothing is visible, and runs at 10 fps,
+	// also releases held keys on focus loss
+	pub fn pause_in_background() -> Self {
+		Self::default()
+			.with_pause_when_suspended(true)
+			.with_render_when_suspended(false)
+			.with_suspended_target_fps(Some(10.0))
+			.with_release_keys_on_focus_loss(true)
+	}
+
+	// like pause_in_background, and also pauses fixed_update and renders at 10 fps while unfocused
+	pub fn auto_pause() -> Self {
+		Self::pause_in_background()
+			.with_pause_when_unfocused(true)
+			.with_unfocused_target_fps(Some(10.0))
+	}
+
+	pub fn with_pause_when_unfocused(mut self, pause: bool) -> Self {
+		self.pause_when_unfocused = pause;
+		self
+	}
+
+	pub fn with_pause_when_suspended(mut self, pause: bool) -> Self {
+		self.pause_when_suspended = pause;
+		self
+	}
+
+	pub fn with_render_when_suspended(mut self, render: bool) -> Self {
+		self.render_when_suspended = render;
+		self
+	}
+
+	// None keeps the normal frame rate
+	pub fn with_unfocused_target_fps(mut self, target_fps: Option<f64>) -> Self {
+		self.unfocused_target_fps = target_fps;
+		self
+	}
+
+	// None keeps the normal frame rate
+	pub fn with_suspended_target_fps(mut self, target_fps: Option<f64>) -> Self {
+		self.suspended_target_fps = target_fps;
+		self
+	}
+
+	pub fn with_release_keys_on_focus_loss(mut self, release: bool) -> Self {
+		self.release_keys_on_focus_loss = release;
+		self
+	}
+
+	pub fn release_keys_on_focus_loss(&self) -> bool {
+		self.release_keys_on_focus_loss
+	}
+
+	// true when fixed_update should not run
+	pub fn is_paused(&self, wuc: &WindowUpdateContext) -> bool {
+		(self.pause_when_suspended && wuc.is_suspended())
+			|| (self.pause_when_unfocused && !wuc.has_focus())
+	}
+
+	pub fn should_render(&self, wuc: &WindowUpdateContext) -> bool {
+		self.render_when_suspended || !wuc.is_suspended()
+	}
+
+	// the frame pacing to use right now, throttled while in the background
+	pub fn frame_pacing(
+		&self,
+		frame_pacing: &FramePacing,
+		wuc: &WindowUpdateContext,
+	) -> FramePacing {
+		let target_fps = if wuc.is_suspended() {
+			self.suspended_target_fps
+		} else if !wuc.has_focus() {
+			self.unfocused_target_fps
+		} else {
+			None
+		};
+		match target_fps {
+			Some(fps)
+				if fps < frame_pacing.target_fps()
+					|| frame_pacing.target_frame_time().is_zero() =>
+			{
+				// spinning in the background would waste power
+				frame_pacing
+					.with_target_fps(fps)
+					.with_limiter(FrameLimiter::Sleep)
+			},
+			_ => *frame_pacing,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn auto_pause_pauses_and_throttles_when_unfocused() {
+		let policy = FocusPolicy::auto_pause();
+		let pacing = FramePacing::default();
+		let mut wuc = WindowUpdateContext::new();
+		assert!(!policy.is_paused(&wuc));
+		assert_eq!(60.0, policy.frame_pacing(&pacing, &wuc).target_fps());
+
+		wuc.set_has_focus(false);
+		assert!(policy.is_paused(&wuc));
+		assert!(policy.should_render(&wuc));
+		assert_eq!(10.0, policy.frame_pacing(&pacing, &wuc).target_fps());
+
+		let policy = FocusPolicy::pause_in_background();
+		assert!(!policy.is_paused(&wuc));
+		wuc.set_minimized(true);
+		assert!(policy.is_paused(&wuc));
+		assert!(!policy.should_render(&wuc));
+		assert_eq!(10.0, policy.frame_pacing(&pacing, &wuc).target_fps());
+	}
+
+	#[test]
+	fn default_changes_nothing() {
+		let policy = FocusPolicy::default();
+		let pacing = FramePacing::default();
+		let mut wuc = WindowUpdateContext::new();
+		wuc.set_has_focus(false);
+		wuc.set_minimized(true);
+		assert!(!policy.is_paused(&wuc));
+		assert!(policy.should_render(&wuc));
+		assert!(!policy.release_keys_on_focus_loss());
+		assert_eq!(60.0, policy.frame_pacing(&pacing, &wuc).target_fps());
+	}
+}
@@ -0,0 +1,156 @@
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FrameLimiter {
+	Sleep,         // let the event loop wait until the next frame is due
+	SleepThenSpin, // wait until shortly before the next frame, then busy wait for better precision
+	Unlimited,     // render as fast as possible, e.g. for benchmarks
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct FramePacing {
+	target_fps: f64,
+	vsync:      bool,
+	limiter:    FrameLimiter,
+	spin_time:  Duration,
+}
+
+impl Default for FramePacing {
+	fn default() -> Self {
+		Self {
+			target_fps: 60.0,
+			vsync:      true,
+			limiter:    FrameLimiter::Sleep,
+			spin_time:  Duration::from_millis(2),
+		}
+	}
+}
+
+impl FramePacing {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	// zero means no target, like FrameLimiter::Unlimited
+	pub fn with_target_fps(mut self, target_fps: f64) -> Self {
+		// a nan or negative rate would panic in target_frame_time, long after it was set
+		if !(target_fps.is_finite() && target_fps >= 0.0) {
+			panic!(
+				"Target fps must be finite, and not negative. Got {}",
+				target_fps
+			);
+		}
+		self.target_fps = target_fps;
+		self
+	}
+
+	// only used when creating the window
+	pub fn with_vsync(mut self, vsync: bool) -> Self {
+		self.vsync = vsync;
+		self
+	}
+
+	pub fn with_limiter(mut self, limiter: FrameLimiter) -> Self {
+		self.limiter = limiter;
+		self
+	}
+
+	// how long before the next frame we stop sleeping, and start spinning
+	pub fn with_spin_time(mut self, spin_time: Duration) -> Self {
+		self.spin_time = spin_time;
+		self
+	}
+
+	pub fn target_fps(&self) -> f64 {
+		self.target_fps
+	}
+
+	pub fn vsync(&self) -> bool {
+		self.vsync
+	}
+
+	pub fn limiter(&self) -> FrameLimiter {
+		self.limiter
+	}
+
+	pub fn target_frame_time(&self) -> Duration {
+		if self.limiter == FrameLimiter::Unlimited || self.target_fps == 0.0 {
+			Duration::ZERO
+		} else {
+			Duration::from_secs_f64(1.0 / self.target_fps)
+		}
+	}
+
+	// when the event loop should wake up for a frame due at next_frame_time
+	pub fn wake_up_time(&self, next_frame_time: Instant) -> Instant {
+		match self.limiter {
+			FrameLimiter::SleepThenSpin => next_frame_time
+				.checked_sub(self.spin_time)
+				.unwrap_or(next_frame_time),
+			_ => next_frame_time,
+		}
+	}
+
+	// returns false if it is too early for the frame, and we should go back to sleep
+	pub fn wait_for_frame(&self, next_frame_time: Instant) -> bool {
+		match self.limiter {
+			FrameLimiter::Unlimited => true,
+			FrameLimiter::Sleep => Instant::now() >= next_frame_time,
+			FrameLimiter::SleepThenSpin => {
+				if Instant::now() < self.wake_up_time(next_frame_time) {
+					return false;
+				}
+				while Instant::now() < next_frame_time {
+					std::hint::spin_loop();
+				}
+				true
+			},
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn target_frame_time_follows_limiter() {
+		let fp = FramePacing::default().with_target_fps(120.0);
+		assert_eq!(Duration::from_secs_f64(1.0 / 120.0), fp.target_frame_time());
+
+		let fp = fp.with_limiter(FrameLimiter::Unlimited);
+		assert_eq!(Duration::ZERO, fp.target_frame_time());
+	}
+
+	#[test]
+	fn zero_target_fps_is_unlimited() {
+		let fp = FramePacing::default().with_target_fps(0.0);
+		assert_eq!(Duration::ZERO, fp.target_frame_time());
+	}
+
+	#[test]
+	#[should_panic]
+	fn target_fps_must_be_finite() {
+		let _ = FramePacing::default().with_target_fps(f64::NAN);
+	}
+
+	#[test]
+	#[should_panic]
+	fn target_fps_must_not_be_negative() {
+		let _ = FramePacing::default().with_target_fps(-60.0);
+	}
+
+	#[test]
+	fn sleep_then_spin_waits_until_due() {
+		let fp = FramePacing::default()
+			.with_limiter(FrameLimiter::SleepThenSpin)
+			.with_spin_time(Duration::from_millis(50));
+
+		let due = Instant::now() + Duration::from_millis(5);
+		assert!(fp.wait_for_frame(due));
+		assert!(Instant::now() >= due);
+
+		let due = Instant::now() + Duration::from_secs(10);
+		assert!(!fp.wait_for_frame(due));
+	}
+}
@@ -0,0 +1,352 @@
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+use crate::math::Vector2;
+use crate::window::WindowUpdateContext;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[repr(u8)]
+pub enum GamepadButton {
+	South, // A on xbox, cross on playstation
+	East,
+	West,
+	North,
+	LeftShoulder,
+	RightShoulder,
+	LeftStick,
+	RightStick,
+	Start,
+	Select,
+	Guide,
+	DPadUp,
+	DPadDown,
+	DPadLeft,
+	DPadRight,
+}
+
+impl GamepadButton {
+	pub const COUNT: usize = GamepadButton::DPadRight as usize + 1;
+	pub const ALL: [GamepadButton; GamepadButton::COUNT] = [
+		GamepadButton::South,
+		GamepadButton::East,
+		GamepadButton::West,
+		GamepadButton::North,
+		GamepadButton::LeftShoulder,
+		GamepadButton::RightShoulder,
+		GamepadButton::LeftStick,
+		GamepadButton::RightStick,
+		GamepadButton::Start,
+		GamepadButton::Select,
+		GamepadButton::Guide,
+		GamepadButton::DPadUp,
+		GamepadButton::DPadDown,
+		GamepadButton::DPadLeft,
+		GamepadButton::DPadRight,
+	];
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum GamepadAxis {
+	LeftX,
+	LeftY,
+	RightX,
+	RightY,
+	LeftTrigger,
+	RightTrigger,
+}
+
+// sticks are -1..1 with y up, triggers 0..1
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct GamepadState {
+	pub connected:     bool,
+	pub buttons:       [bool; GamepadButton::COUNT],
+	pub left_stick:    Vector2,
+	pub right_stick:   Vector2,
+	pub left_trigger:  f32,
+	pub right_trigger: f32,
+}
+
+impl GamepadState {
+	pub fn is_button_down(&self, button: GamepadButton) -> bool {
+		self.buttons[button as usize]
+	}
+
+	pub fn set_button(&mut self, button: GamepadButton, down: bool) {
+		self.buttons[button as usize] = down;
+	}
+
+	pub fn axis(&self, axis: GamepadAxis) -> f32 {
+		match axis {
+			GamepadAxis::LeftX => self.left_stick.x,
+			GamepadAxis::LeftY => self.left_stick.y,
+			GamepadAxis::RightX => self.right_stick.x,
+			GamepadAxis::RightY => self.right_stick.y,
+			GamepadAxis::LeftTrigger => self.left_trigger,
+			GamepadAxis::RightTrigger => self.right_trigger,
+		}
+	}
+
+	pub(crate) fn buttons_bits(&self) -> u32 {
+		self.buttons
+			.iter()
+			.enumerate()
+			.filter(|(_, &d)| d)
+			.fold(0, |bits, (i, _)| bits | (1 << i))
+	}
+
+	pub(crate) fn set_buttons_bits(&mut self, bits: u32) {
+		for (i, b) in self.buttons.iter_mut().enumerate() {
+			*b = bits & (1 << i) != 0;
+		}
+	}
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum DeadZone {
+	None,
+	Axial(f32),        // per axis, keeps small movements on the other axis
+	Radial(f32),       // on the stick's length, values jump from 0 to the dead zone
+	ScaledRadial(f32), // like Radial, but rescaled so values start at 0
+}
+
+impl Default for DeadZone {
+	fn default() -> Self {
+		DeadZone::ScaledRadial(0.15)
+	}
+}
+
+impl DeadZone {
+	pub fn apply(&self, stick: &Vector2) -> Vector2 {
+		let axial = |v: f32, dz: f32| if v.abs() < dz { 0.0 } else { v };
+		match *self {
+			DeadZone::None => *stick,
+			DeadZone::Axial(dz) => Vector2::new(axial(stick.x, dz), axial(stick.y, dz)),
+			DeadZone::Radial(dz) => {
+				if stick.length() < dz {
+					Vector2::zero()
+				} else {
+					*stick
+				}
+			},
+			DeadZone::ScaledRadial(dz) => {
+				let l = stick.length();
+				if l < dz || dz >= 1.0 {
+					Vector2::zero()
+				} else {
+					let scaled = ((l - dz) / (1.0 - dz)).min(1.0);
+					stick.scaled(scaled / l)
+				}
+			},
+		}
+	}
+}
+
+// a source of gamepad state, e.g. a platform backend, or the in-memory ScriptedGamepad
+pub trait Gamepad {
+	fn name(&self) -> &str;
+	// called once per frame
+	fn poll(&mut self, time_step: f64) -> GamepadState;
+}
+
+// an in-memory device, either driven directly, or by a script of frames
+#[derive(Debug, Default)]
+pub struct ScriptedGamepad {
+	name:   String,
+	state:  GamepadState,
+	script: VecDeque<GamepadState>,
+}
+
+impl ScriptedGamepad {
+	pub fn new(name: &str) -> Self {
+		Self {
+			name:   name.to_string(),
+			state:  GamepadState {
+				connected: true,
+				..Default::default()
+			},
+			script: VecDeque::new(),
+		}
+	}
+
+	pub fn state(&self) -> &GamepadState {
+		&self.state
+	}
+
+	pub fn set_connected(&mut self, connected: bool) {
+		self.state.connected = connected;
+	}
+
+	pub fn set_button(&mut self, button: GamepadButton, down: bool) {
+		self.state.set_button(button, down);
+	}
+
+	pub fn set_left_stick(&mut self, stick: &Vector2) {
+		self.state.left_stick = *stick;
+	}
+
+	pub fn set_right_stick(&mut self, stick: &Vector2) {
+		self.state.right_stick = *stick;
+	}
+
+	pub fn set_triggers(&mut self, left: f32, right: f32) {
+		self.state.left_trigger = left;
+		self.state.right_trigger = right;
+	}
+
+	// queued frames are returned by poll, one per frame, the last one stays
+	pub fn push_frame(&mut self, state: GamepadState) {
+		self.script.push_back(state);
+	}
+
+	pub fn is_script_done(&self) -> bool {
+		self.script.is_empty()
+	}
+}
+
+impl Gamepad for ScriptedGamepad {
+	fn name(&self) -> &str {
+		&self.name
+	}
+
+	fn poll(&mut self, _time_step: f64) -> GamepadState {
+		if let Some(state) = self.script.pop_front() {
+			self.state = state;
+		}
+		self.state
+	}
+}
+
+// the connected devices, polled into WindowUpdateContext every frame, see App::gamepads
+pub struct Gamepads {
+	devices:           Vec<Box<dyn Gamepad>>,
+	stick_dead_zone:   DeadZone,
+	trigger_dead_zone: f32,
+}
+
+impl std::fmt::Debug for Gamepads {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		let names = self.devices.iter().map(|d| d.name()).collect::<Vec<_>>();
+		write!(
+			f,
+			"Gamepads: {:?} dead zones: {:?} {}",
+			names, self.stick_dead_zone, self.trigger_dead_zone
+		)
+	}
+}
+
+impl Default for Gamepads {
+	fn default() -> Self {
+		Self {
+			devices:           Vec::new(),
+			stick_dead_zone:   DeadZone::default(),
+			trigger_dead_zone: 0.05,
+		}
+	}
+}
+
+impl Gamepads {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn with_stick_dead_zone(mut self, dead_zone: DeadZone) -> Self {
+		self.stick_dead_zone = dead_zone;
+		self
+	}
+
+	pub fn with_trigger_dead_zone(mut self, dead_zone: f32) -> Self {
+		self.trigger_dead_zone = dead_zone;
+		self
+	}
+
+	// returns the index used in WindowUpdateContext
+	pub fn add(&mut self, device: Box<dyn Gamepad>) -> usize {
+		self.devices.push(device);
+		self.devices.len() - 1
+	}
+
+	pub fn len(&self) -> usize {
+		self.devices.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.devices.is_empty()
+	}
+
+	pub fn device_mut(&mut self, index: usize) -> Option<&mut Box<dyn Gamepad>> {
+		self.devices.get_mut(index)
+	}
+
+	pub fn poll(&mut self, wuc: &mut WindowUpdateContext) {
+		let time_step = wuc.time_step();
+		for (i, device) in self.devices.iter_mut().enumerate() {
+			let mut state = device.poll(time_step);
+			state.left_stick = self.stick_dead_zone.apply(&state.left_stick);
+			state.right_stick = self.stick_dead_zone.apply(&state.right_stick);
+			let trigger = |v: f32| {
+				if v < self.trigger_dead_zone {
+					0.0
+				} else {
+					v
+				}
+			};
+			state.left_trigger = trigger(state.left_trigger);
+			state.right_trigger = trigger(state.right_trigger);
+			wuc.set_gamepad_state(i, state);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn dead_zones_filter_small_stick_movements() {
+		let small = Vector2::new(0.1, 0.05);
+		assert_eq!(Vector2::zero(), DeadZone::Radial(0.2).apply(&small));
+		assert_eq!(Vector2::new(0.1, 0.0), DeadZone::Axial(0.08).apply(&small));
+		let full = Vector2::new(0.0, 1.0);
+		assert_eq!(full, DeadZone::ScaledRadial(0.2).apply(&full));
+		let half = DeadZone::ScaledRadial(0.2).apply(&Vector2::new(0.6, 0.0));
+		assert!((half.x - 0.5).abs() < 0.0001);
+	}
+
+	#[test]
+	fn scripted_gamepad_drives_the_update_context() {
+		let mut pad = ScriptedGamepad::new("test");
+		let mut pressed = GamepadState {
+			connected: true,
+			..Default::default()
+		};
+		pressed.set_button(GamepadButton::South, true);
+		pressed.left_stick = Vector2::new(0.05, -1.0);
+		pad.push_frame(pressed);
+		pad.push_frame(GamepadState {
+			connected: true,
+			..Default::default()
+		});
+
+		let mut gamepads = Gamepads::new().with_stick_dead_zone(DeadZone::Axial(0.1));
+		let index = gamepads.add(Box::new(pad));
+		let mut wuc = WindowUpdateContext::new();
+
+		gamepads.poll(&mut wuc);
+		assert!(wuc.was_gamepad_button_pressed(index, GamepadButton::South));
+		assert_eq!(
+			Vector2::new(0.0, -1.0),
+			wuc.gamepad(index).unwrap().left_stick
+		);
+		wuc.update();
+
+		gamepads.poll(&mut wuc);
+		assert!(wuc.was_gamepad_button_released(index, GamepadButton::South));
+		wuc.update();
+
+		gamepads.poll(&mut wuc);
+		assert!(!wuc.is_gamepad_button_down(index, GamepadButton::South));
+		assert!(!wuc.was_gamepad_button_released(index, GamepadButton::South));
+		assert!(!wuc.is_gamepad_button_down(index + 1, GamepadButton::South));
+	}
+}
@@ -0,0 +1,297 @@
+use crate::math::Vector2;
+use crate::window::WindowUpdateContext;
+
+#[derive(Debug, Copy, Clone)]
+pub struct GestureConfig {
+	double_click_time: f64,
+	long_press_time:   f64,
+	drag_threshold:    f32, // in pixels, also the max distance between the clicks of a double-click
+}
+
+impl Default for GestureConfig {
+	fn default() -> Self {
+		Self {
+			double_click_time: 0.3,
+			long_press_time:   0.5,
+			drag_threshold:    4.0,
+		}
+	}
+}
+
+impl GestureConfig {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn with_double_click_time(mut self, double_click_time: f64) -> Self {
+		self.double_click_time = double_click_time;
+		self
+	}
+
+	pub fn with_long_press_time(mut self, long_press_time: f64) -> Self {
+		self.long_press_time = long_press_time;
+		self
+	}
+
+	pub fn with_drag_threshold(mut self, drag_threshold: f32) -> Self {
+		self.drag_threshold = drag_threshold;
+		self
+	}
+
+	pub fn double_click_time(&self) -> f64 {
+		self.double_click_time
+	}
+
+	pub fn long_press_time(&self) -> f64 {
+		self.long_press_time
+	}
+
+	pub fn drag_threshold(&self) -> f32 {
+		self.drag_threshold
+	}
+}
+
+// positions are in pixels, origin bottom left, like WindowUpdateContext::mouse_pos_pixels
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum GestureEvent {
+	// released without moving past the drag threshold, and before the long press time
+	Click {
+		button: usize,
+		pos:    Vector2,
+	},
+	// sent after the Click of the second click
+	DoubleClick {
+		button: usize,
+		pos:    Vector2,
+	},
+	// sent once while still held, the release will not be a Click
+	LongPress {
+		button: usize,
+		pos:    Vector2,
+	},
+	DragStart {
+		button: usize,
+		start:  Vector2,
+		pos:    Vector2,
+	},
+	DragMove {
+		button: usize,
+		pos:    Vector2,
+		delta:  Vector2,
+	},
+	DragEnd {
+		button: usize,
+		start:  Vector2,
+		pos:    Vector2,
+	},
+}
+
+#[derive(Debug, Default, Copy, Clone)]
+struct ButtonGesture {
+	is_down:      bool,
+	press_time:   f64,
+	press_pos:    Vector2,
+	dragging:     bool,
+	long_pressed: bool,
+	last_click:   Option<(f64, Vector2)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct GestureRecognizer {
+	config:  GestureConfig,
+	time:    f64,
+	buttons: [ButtonGesture; WindowUpdateContext::MOUSE_BUTTON_COUNT],
+}
+
+impl Default for GestureRecognizer {
+	fn default() -> Self {
+		Self::new(GestureConfig::default())
+	}
+}
+
+impl GestureRecognizer {
+	pub fn new(config: GestureConfig) -> Self {
+		Self {
+			config,
+			time: 0.0,
+			buttons: [ButtonGesture::default(); WindowUpdateContext::MOUSE_BUTTON_COUNT],
+		}
+	}
+
+	pub fn config(&self) -> &GestureConfig {
+		&self.config
+	}
+
+	pub fn set_config(&mut self, config: GestureConfig) {
+		self.config = config;
+	}
+
+	pub fn is_dragging(&self, button: usize) -> bool {
+		self.buttons[button].dragging
+	}
+
+	// returns the gestures for this frame, call once per frame
+	pub fn process(&mut self, wuc: &WindowUpdateContext) -> Vec<GestureEvent> {
+		let mut events = Vec::new();
+		self.time += wuc.time_step();
+		let pos = wuc.mouse_pos_pixels;
+		let threshold = self.config.drag_threshold;
+
+		for (button, g) in self.buttons.iter_mut().enumerate() {
+			let down = wuc.is_mouse_button_down(button);
+			if down && !g.is_down {
+				g.is_down = true;
+				g.press_time = self.time;
+				g.press_pos = pos;
+				g.dragging = false;
+				g.long_pressed = false;
+			}
+			if !g.is_down {
+				continue;
+			}
+
+			if !g.dragging && !g.long_pressed && pos.sub(&g.press_pos).length() > threshold {
+				g.dragging = true;
+				events.push(GestureEvent::DragStart {
+					button,
+					start: g.press_pos,
+					pos,
+				});
+			} else if g.dragging {
+				let delta = wuc.mouse_delta_pixels();
+				if delta != Vector2::zero() {
+					events.push(GestureEvent::DragMove { button, pos, delta });
+				}
+			}
+
+			if down
+				&& !g.dragging
+				&& !g.long_pressed
+				&& self.time - g.press_time >= self.config.long_press_time
+			{
+				g.long_pressed = true;
+				g.last_click = None;
+				events.push(GestureEvent::LongPress { button, pos });
+			}
+
+			if !down {
+				g.is_down = false;
+				if g.dragging {
+					g.dragging = false;
+					events.push(GestureEvent::DragEnd {
+						button,
+						start: g.press_pos,
+						pos,
+					});
+				} else if !g.long_pressed {
+					events.push(GestureEvent::Click { button, pos });
+					let is_double = match g.last_click {
+						Some((t, p)) => {
+							self.time - t <= self.config.double_click_time
+								&& pos.sub(&p).length() <= threshold
+						},
+						None => false,
+					};
+					if is_double {
+						// a third click starts over
+						g.last_click = None;
+						events.push(GestureEvent::DoubleClick { button, pos });
+					} else {
+						g.last_click = Some((self.time, pos));
+					}
+				}
+			}
+		}
+
+		events
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn frame(
+		recognizer: &mut GestureRecognizer,
+		wuc: &mut WindowUpdateContext,
+		down: bool,
+		pos: (f32, f32),
+	) -> Vec<GestureEvent> {
+		wuc.set_mouse_pos_pixels(&Vector2::new(pos.0, pos.1));
+		wuc.set_mouse_button(0, down);
+		let events = recognizer.process(wuc);
+		wuc.update();
+		events
+	}
+
+	#[test]
+	fn clicks_double_clicks_and_long_presses() {
+		let mut recognizer = GestureRecognizer::default();
+		let mut wuc = WindowUpdateContext::new();
+		wuc.time_step = 0.1;
+
+		assert!(frame(&mut recognizer, &mut wuc, true, (10.0, 10.0)).is_empty());
+		let events = frame(&mut recognizer, &mut wuc, false, (11.0, 10.0));
+		assert_eq!(
+			vec![GestureEvent::Click {
+				button: 0,
+				pos:    Vector2::new(11.0, 10.0),
+			}],
+			events
+		);
+		frame(&mut recognizer, &mut wuc, true, (11.0, 11.0));
+		let events = frame(&mut recognizer, &mut wuc, false, (11.0, 11.0));
+		assert_eq!(2, events.len());
+		assert!(matches!(
+			events[1],
+			GestureEvent::DoubleClick { button: 0, .. }
+		));
+
+		let mut events = Vec::new();
+		for _ in 0..8 {
+			events.append(&mut frame(&mut recognizer, &mut wuc, true, (50.0, 50.0)));
+		}
+		events.append(&mut frame(&mut recognizer, &mut wuc, false, (50.0, 50.0)));
+		assert_eq!(1, events.len());
+		assert!(matches!(
+			events[0],
+			GestureEvent::LongPress { button: 0, .. }
+		));
+	}
+
+	#[test]
+	fn drags_start_after_the_threshold() {
+		let mut recognizer =
+			GestureRecognizer::new(GestureConfig::default().with_drag_threshold(10.0));
+		let mut wuc = WindowUpdateContext::new();
+		wuc.time_step = 0.01;
+		wuc.set_mouse_inside(true);
+
+		frame(&mut recognizer, &mut wuc, true, (0.0, 0.0));
+		assert!(frame(&mut recognizer, &mut wuc, true, (5.0, 0.0)).is_empty());
+		let events = frame(&mut recognizer, &mut wuc, true, (15.0, 0.0));
+		assert!(matches!(
+			events[0],
+			GestureEvent::DragStart { button: 0, .. }
+		));
+		assert!(recognizer.is_dragging(0));
+		let events = frame(&mut recognizer, &mut wuc, true, (20.0, 0.0));
+		assert_eq!(
+			vec![GestureEvent::DragMove {
+				button: 0,
+				pos:    Vector2::new(20.0, 0.0),
+				delta:  Vector2::new(5.0, 0.0),
+			}],
+			events
+		);
+		let events = frame(&mut recognizer, &mut wuc, false, (20.0, 0.0));
+		assert_eq!(
+			vec![GestureEvent::DragEnd {
+				button: 0,
+				start:  Vector2::zero(),
+				pos:    Vector2::new(20.0, 0.0),
+			}],
+			events
+		);
+	}
+}
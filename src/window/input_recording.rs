@@ -0,0 +1,470 @@
+use crate::math::Vector2;
+use crate::system::{Serializer, System};
+use crate::window::{
+	GamepadState, KeyCode, TextInputEvent, Touch, TouchPhase, WindowUpdateContext,
+};
+
+const MAGIC: u32 = 0x4f4d4952; // OMIR
+const VERSION: u16 = 8;
+// in bytes, to reject counts a corrupt file can't hold before allocating for them
+const MIN_FRAME_SIZE: usize = 8 // time_step
+	+ 7 * 8 // vectors
+	+ WindowUpdateContext::MOUSE_BUTTON_COUNT
+	+ 1 // mouse_inside
+	+ InputFrame::KEY_WORDS * 4
+	+ 1 // window_changed
+	+ 3 * 4 // counts
+	+ 2 // has_focus, is_suspended
+	+ 8; // scale_factor
+const MIN_TEXT_INPUT_EVENT_SIZE: usize = 2;
+const TOUCH_SIZE: usize = 8 + 1 + 3 * 8;
+const GAMEPAD_SIZE: usize = 1 + 4 + 2 * 8 + 2 * 4;
+
+// the input part of one frame's WindowUpdateContext
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct InputFrame {
+	pub(crate) time_step:               f64,
+	pub(crate) mouse_pos:               Vector2,
+	pub(crate) mouse_pos_pixels:        Vector2,
+	pub(crate) mouse_delta_pixels:      Vector2,
+	pub(crate) mouse_wheel_line_delta:  Vector2,
+	pub(crate) mouse_wheel_pixel_delta: Vector2,
+	pub(crate) mouse_buttons:           [bool; WindowUpdateContext::MOUSE_BUTTON_COUNT],
+	pub(crate) mouse_inside:            bool,
+	pub(crate) keys:                    [u32; InputFrame::KEY_WORDS], // bitset of keys down
+	pub(crate) window_size:             Vector2,
+	pub(crate) window_pos:              Vector2,
+	pub(crate) window_changed:          bool,
+	pub(crate) text_input_events:       Vec<TextInputEvent>,
+	pub(crate) touches:                 Vec<Touch>,
+	pub(crate) gamepads:                Vec<GamepadState>,
+	pub(crate) has_focus:               bool,
+	pub(crate) is_suspended:            bool,
+	pub(crate) scale_factor:            f64,
+}
+
+impl InputFrame {
+	pub(crate) const KEY_WORDS: usize = KeyCode::COUNT.div_ceil(32);
+
+	pub fn time_step(&self) -> f64 {
+		self.time_step
+	}
+
+	fn serialize_vector2(serializer: &mut Serializer, v: &mut Vector2) {
+		serializer.serialize_f32(&mut v.x);
+		serializer.serialize_f32(&mut v.y);
+	}
+
+	fn serialize_count(
+		serializer: &mut Serializer,
+		len: usize,
+		min_size: usize,
+		what: &str,
+	) -> anyhow::Result<usize> {
+		let mut count = len as u32;
+		serializer.serialize_u32(&mut count);
+		if !serializer.check_count(count as usize, min_size) {
+			anyhow::bail!("Corrupt input recording, {} {} don't fit", count, what);
+		}
+		Ok(count as usize)
+	}
+
+	fn serialize(&mut self, serializer: &mut Serializer) -> anyhow::Result<()> {
+		serializer.serialize_f64(&mut self.time_step);
+		Self::serialize_vector2(serializer, &mut self.mouse_pos);
+		Self::serialize_vector2(serializer, &mut self.mouse_pos_pixels);
+		Self::serialize_vector2(serializer, &mut self.mouse_delta_pixels);
+		Self::serialize_vector2(serializer, &mut self.mouse_wheel_line_delta);
+		Self::serialize_vector2(serializer, &mut self.mouse_wheel_pixel_delta);
+		for b in self.mouse_buttons.iter_mut() {
+			serializer.serialize_bool(b);
+		}
+		serializer.serialize_bool(&mut self.mouse_inside);
+		for k in self.keys.iter_mut() {
+			serializer.serialize_u32(k);
+		}
+		Self::serialize_vector2(serializer, &mut self.window_size);
+		Self::serialize_vector2(serializer, &mut self.window_pos);
+		serializer.serialize_bool(&mut self.window_changed);
+
+		let count = Self::serialize_count(
+			serializer,
+			self.text_input_events.len(),
+			MIN_TEXT_INPUT_EVENT_SIZE,
+			"text input events",
+		)?;
+		self.text_input_events
+			.resize(count, TextInputEvent::Key(KeyCode::Unknown));
+		for e in self.text_input_events.iter_mut() {
+			Self::serialize_text_input_event(serializer, e);
+		}
+
+		let count = Self::serialize_count(serializer, self.touches.len(), TOUCH_SIZE, "touches")?;
+		self.touches
+			.resize(count, Touch::new(0, &Vector2::zero(), &Vector2::zero()));
+		for t in self.touches.iter_mut() {
+			serializer.serialize_u64(&mut t.id);
+			let mut phase = t.phase as u8;
+			serializer.serialize_u8(&mut phase);
+			t.phase = match phase {
+				0 => TouchPhase::Began,
+				1 => TouchPhase::Moved,
+				2 => TouchPhase::Stationary,
+				3 => TouchPhase::Ended,
+				_ => TouchPhase::Cancelled,
+			};
+			Self::serialize_vector2(serializer, &mut t.pos);
+			Self::serialize_vector2(serializer, &mut t.pos_pixels);
+			Self::serialize_vector2(serializer, &mut t.start_pos_pixels);
+		}
+
+		let count =
+			Self::serialize_count(serializer, self.gamepads.len(), GAMEPAD_SIZE, "gamepads")?;
+		self.gamepads.resize(count, GamepadState::default());
+		for g in self.gamepads.iter_mut() {
+			serializer.serialize_bool(&mut g.connected);
+			let mut buttons = g.buttons_bits();
+			serializer.serialize_u32(&mut buttons);
+			g.set_buttons_bits(buttons);
+			Self::serialize_vector2(serializer, &mut g.left_stick);
+			Self::serialize_vector2(serializer, &mut g.right_stick);
+			serializer.serialize_f32(&mut g.left_trigger);
+			serializer.serialize_f32(&mut g.right_trigger);
+		}
+
+		serializer.serialize_bool(&mut self.has_focus);
+		serializer.serialize_bool(&mut self.is_suspended);
+		serializer.serialize_f64(&mut self.scale_factor);
+		Ok(())
+	}
+
+	fn serialize_text_input_event(serializer: &mut Serializer, event: &mut TextInputEvent) {
+		let mut tag: u8 = match event {
+			TextInputEvent::Character(_) => 0,
+			TextInputEvent::Key(_) => 1,
+			TextInputEvent::Preedit { .. } => 2,
+			TextInputEvent::Commit(_) => 3,
+		};
+		serializer.serialize_u8(&mut tag);
+		match tag {
+			0 => {
+				let mut c = match event {
+					TextInputEvent::Character(c) => *c as u32,
+					_ => 0,
+				};
+				serializer.serialize_u32(&mut c);
+				*event = TextInputEvent::Character(char::from_u32(c).unwrap_or('?'));
+			},
+			1 => {
+				let mut k = match event {
+					TextInputEvent::Key(k) => *k as u8,
+					_ => KeyCode::Unknown as u8,
+				};
+				serializer.serialize_u8(&mut k);
+				let k = KeyCode::from_index(k as usize).unwrap_or(KeyCode::Unknown);
+				*event = TextInputEvent::Key(k);
+			},
+			2 => {
+				let (mut text, cursor) = match event {
+					TextInputEvent::Preedit { text, cursor } => (text.clone(), *cursor),
+					_ => (String::new(), None),
+				};
+				let mut has_cursor = cursor.is_some();
+				let (s, e) = cursor.unwrap_or((0, 0));
+				let (mut s, mut e) = (s as u32, e as u32);
+				serializer.serialize_string(&mut text);
+				serializer.serialize_bool(&mut has_cursor);
+				serializer.serialize_u32(&mut s);
+				serializer.serialize_u32(&mut e);
+				*event = TextInputEvent::Preedit {
+					text,
+					cursor: if has_cursor {
+						Some((s as usize, e as usize))
+					} else {
+						None
+					},
+				};
+			},
+			_ => {
+				let mut text = match event {
+					TextInputEvent::Commit(text) => text.clone(),
+					_ => String::new(),
+				};
+				serializer.serialize_string(&mut text);
+				*event = TextInputEvent::Commit(text);
+			},
+		}
+	}
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct InputRecording {
+	frames: Vec<InputFrame>,
+}
+
+impl InputRecording {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn len(&self) -> usize {
+		self.frames.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.frames.is_empty()
+	}
+
+	pub fn frames(&self) -> &Vec<InputFrame> {
+		&self.frames
+	}
+
+	pub fn record(&mut self, wuc: &WindowUpdateContext) {
+		self.frames.push(wuc.input_frame());
+	}
+
+	// replaces the input in wuc with the recorded frame, returns false when past the end
+	pub fn apply(&self, frame: usize, wuc: &mut WindowUpdateContext) -> bool {
+		match self.frames.get(frame) {
+			Some(f) => {
+				wuc.apply_input_frame(f);
+				true
+			},
+			None => false,
+		}
+	}
+
+	fn serialize(&mut self, serializer: &mut Serializer) -> anyhow::Result<()> {
+		let mut magic = MAGIC;
+		let mut version = VERSION;
+		serializer.serialize_u32(&mut magic);
+		serializer.serialize_u16(&mut version);
+		if magic != MAGIC {
+			anyhow::bail!("Not an input recording, magic {:08x}", magic);
+		}
+		if version != VERSION {
+			anyhow::bail!("Unsupported input recording version {}", version);
+		}
+
+		let count =
+			InputFrame::serialize_count(serializer, self.frames.len(), MIN_FRAME_SIZE, "frames")?;
+		self.frames.resize(count, InputFrame::default());
+		for f in self.frames.iter_mut() {
+			f.serialize(serializer)?;
+			// e.g. a truncated file
+			if serializer.any_error() {
+				anyhow::bail!("Failed serializing input recording");
+			}
+		}
+
+		if serializer.any_error() {
+			anyhow::bail!("Failed serializing input recording");
+		}
+		Ok(())
+	}
+
+	// saves to the savegame filesystem
+	pub fn save(&self, system: &mut System, name: &str) -> anyhow::Result<()> {
+		let fs = system.savegame_filesystem_mut();
+		if !fs.writable() {
+			anyhow::bail!("Filesystem is not writable {:?}", fs);
+		}
+		let f = fs.create(name, true);
+		if !f.is_valid() {
+			anyhow::bail!("couldn't write to {:?}", f);
+		}
+		let mut serializer = Serializer::new(f);
+		let mut recording = self.clone();
+		recording.serialize(&mut serializer)
+	}
+
+	// loads from the savegame filesystem
+	pub fn load(system: &mut System, name: &str) -> anyhow::Result<Self> {
+		let fs = system.savegame_filesystem_mut();
+		let f = fs.open(name);
+		if !f.is_valid() {
+			anyhow::bail!("couldn't read from {:?}", f);
+		}
+		let mut serializer = Serializer::new(f);
+		let mut recording = Self::default();
+		recording.serialize(&mut serializer)?;
+		Ok(recording)
+	}
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum InputRecorderState {
+	Idle,
+	Recording,
+	Playing,
+}
+
+// records live input, or replaces it with a recording, see App::input_recorder
+#[derive(Debug)]
+pub struct InputRecorder {
+	state:     InputRecorderState,
+	recording: InputRecording,
+	play_pos:  usize,
+}
+
+impl Default for InputRecorder {
+	fn default() -> Self {
+		Self {
+			state:     InputRecorderState::Idle,
+			recording: InputRecording::default(),
+			play_pos:  0,
+		}
+	}
+}
+
+impl InputRecorder {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn state(&self) -> InputRecorderState {
+		self.state
+	}
+
+	pub fn is_recording(&self) -> bool {
+		self.state == InputRecorderState::Recording
+	}
+
+	pub fn is_playing(&self) -> bool {
+		self.state == InputRecorderState::Playing
+	}
+
+	pub fn recording(&self) -> &InputRecording {
+		&self.recording
+	}
+
+	pub fn start_recording(&mut self) {
+		self.recording = InputRecording::default();
+		self.state = InputRecorderState::Recording;
+	}
+
+	pub fn start_playback(&mut self, recording: InputRecording) {
+		self.recording = recording;
+		self.play_pos = 0;
+		self.state = InputRecorderState::Playing;
+	}
+
+	pub fn stop(&mut self) {
+		self.state = InputRecorderState::Idle;
+	}
+
+	// called once per frame, before App::update
+	pub fn process(&mut self, wuc: &mut WindowUpdateContext) {
+		match self.state {
+			InputRecorderState::Idle => {},
+			InputRecorderState::Recording => self.recording.record(wuc),
+			InputRecorderState::Playing => {
+				if self.recording.apply(self.play_pos, wuc) {
+					self.play_pos += 1;
+				} else {
+					// back to live input
+					self.state = InputRecorderState::Idle;
+				}
+			},
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::system::filesystem_disk::FilesystemDisk;
+
+	#[test]
+	fn recording_round_trips_through_savegame_filesystem() -> anyhow::Result<()> {
+		let dir = std::env::temp_dir().join(format!("oml-game-input-{}", std::process::id()));
+		std::fs::create_dir_all(&dir)?;
+		let mut fs = FilesystemDisk::new(&dir.to_string_lossy());
+		fs.enable_write();
+		let mut system = System::new();
+		system.set_savegame_filesystem(Box::new(fs));
+
+		let mut recorder = InputRecorder::new();
+		recorder.start_recording();
+		let mut wuc = WindowUpdateContext::new();
+		for i in 0..10 {
+			wuc.time_step = 0.01 * i as f64;
+			wuc.mouse_pos = Vector2::new(0.1 * i as f32, 0.5);
+			wuc.set_key_down(KeyCode::A, i % 2 == 0);
+			wuc.set_key_down(KeyCode::F3, i % 3 == 0);
+			wuc.set_mouse_button(2, i > 5);
+			wuc.add_text_input_event(TextInputEvent::Character('ü'));
+			wuc.add_text_input_event(TextInputEvent::Key(KeyCode::Backspace));
+			wuc.add_text_input_event(TextInputEvent::Commit("日本".to_string()));
+			recorder.process(&mut wuc);
+			wuc.update();
+		}
+		recorder.stop();
+		recorder.recording().save(&mut system, "test.omir")?;
+
+		let loaded = InputRecording::load(&mut system, "test.omir")?;
+		assert_eq!(recorder.recording().frames(), loaded.frames());
+
+		let mut replayed = WindowUpdateContext::new();
+		recorder.start_playback(loaded);
+		for i in 0..10 {
+			recorder.process(&mut replayed);
+			assert_eq!(0.01 * i as f64, replayed.time_step());
+			assert_eq!(i % 2 == 0, replayed.is_key_down(KeyCode::A));
+			assert_eq!(i % 3 == 0, replayed.is_function_key_pressed(3));
+			assert_eq!("ü日本", replayed.text());
+			assert_eq!(3, replayed.text_input_events().len());
+			replayed.update();
+		}
+		recorder.process(&mut replayed);
+		assert!(!recorder.is_playing());
+
+		std::fs::remove_dir_all(&dir)?;
+		Ok(())
+	}
+
+	#[test]
+	fn corrupt_recordings_fail_to_load() -> anyhow::Result<()> {
+		let dir =
+			std::env::temp_dir().join(format!("oml-game-input-corrupt-{}", std::process::id()));
+		std::fs::create_dir_all(&dir)?;
+		let mut fs = FilesystemDisk::new(&dir.to_string_lossy());
+		fs.enable_write();
+		let mut system = System::new();
+		system.set_savegame_filesystem(Box::new(fs));
+
+		let mut recording = InputRecording::new();
+		let wuc = WindowUpdateContext::new();
+		for _ in 0..3 {
+			recording.record(&wuc);
+		}
+		recording.save(&mut system, "good.omir")?;
+		let bytes = std::fs::read(dir.join("good.omir"))?;
+		// magic, version, and frame count
+		assert_eq!(10 + 3 * MIN_FRAME_SIZE, bytes.len());
+		assert_eq!(3, InputRecording::load(&mut system, "good.omir")?.len());
+
+		std::fs::write(dir.join("truncated.omir"), &bytes[..bytes.len() - 5])?;
+		assert!(InputRecording::load(&mut system, "truncated.omir").is_err());
+
+		let mut oversized = bytes.clone();
+		oversized[6..10].copy_from_slice(&u32::MAX.to_be_bytes());
+		std::fs::write(dir.join("oversized.omir"), &oversized)?;
+		let r = InputRecording::load(&mut system, "oversized.omir");
+		assert!(r.unwrap_err().to_string().contains("frames don't fit"));
+
+		// the text input event count of the first frame
+		let mut oversized = bytes;
+		let p = 10 + MIN_FRAME_SIZE - (3 * 4 + 2 + 8);
+		oversized[p..p + 4].copy_from_slice(&u32::MAX.to_be_bytes());
+		std::fs::write(dir.join("oversized.omir"), &oversized)?;
+		let r = InputRecording::load(&mut system, "oversized.omir");
+		assert!(r
+			.unwrap_err()
+			.to_string()
+			.contains("text input events don't fit"));
+
+		std::fs::remove_dir_all(&dir)?;
+		Ok(())
+	}
+}
@@ -0,0 +1,545 @@
+use glutin::event::VirtualKeyCode;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize, Serialize)]
+#[repr(u8)]
+pub enum KeyCode {
+	// digits
+	Key0,
+	Key1,
+	Key2,
+	Key3,
+	Key4,
+	Key5,
+	Key6,
+	Key7,
+	Key8,
+	Key9,
+
+	// letters
+	A,
+	B,
+	C,
+	D,
+	E,
+	F,
+	G,
+	H,
+	I,
+	J,
+	K,
+	L,
+	M,
+	N,
+	O,
+	P,
+	Q,
+	R,
+	S,
+	T,
+	U,
+	V,
+	W,
+	X,
+	Y,
+	Z,
+
+	// function keys
+	F1,
+	F2,
+	F3,
+	F4,
+	F5,
+	F6,
+	F7,
+	F8,
+	F9,
+	F10,
+	F11,
+	F12,
+	F13,
+	F14,
+	F15,
+	F16,
+	F17,
+	F18,
+	F19,
+	F20,
+	F21,
+	F22,
+	F23,
+	F24,
+
+	// editing and navigation
+	Escape,
+	Tab,
+	Space,
+	Enter,
+	Backspace,
+	Delete,
+	Insert,
+	Home,
+	End,
+	PageUp,
+	PageDown,
+	Left,
+	Right,
+	Up,
+	Down,
+
+	// numpad
+	Numpad0,
+	Numpad1,
+	Numpad2,
+	Numpad3,
+	Numpad4,
+	Numpad5,
+	Numpad6,
+	Numpad7,
+	Numpad8,
+	Numpad9,
+	NumpadAdd,
+	NumpadSubtract,
+	NumpadMultiply,
+	NumpadDivide,
+	NumpadDecimal,
+	NumpadComma,
+	NumpadEnter,
+	NumpadEquals,
+	NumLock,
+
+	// modifiers
+	LShift,
+	RShift,
+	LControl,
+	RControl,
+	LAlt,
+	RAlt,
+	LSuper,
+	RSuper,
+
+	// punctuation
+	Minus,
+	Equals,
+	Plus,
+	LBracket,
+	RBracket,
+	Backslash,
+	Semicolon,
+	Colon,
+	Apostrophe,
+	Grave,
+	Comma,
+	Period,
+	Slash,
+	Caret,
+	Asterisk,
+	At,
+	Underline,
+
+	// locks and system
+	CapsLock,
+	ScrollLock,
+	PrintScreen,
+	Pause,
+	Menu,
+
+	// media
+	Mute,
+	VolumeUp,
+	VolumeDown,
+	PlayPause,
+	NextTrack,
+	PrevTrack,
+	MediaStop,
+
+	// never pressed, e.g. for ascii without a key
+	Unknown,
+}
+
+impl KeyCode {
+	pub const COUNT: usize = KeyCode::Unknown as usize + 1;
+
+	pub const ALL: [KeyCode; KeyCode::COUNT] = [
+		KeyCode::Key0,
+		KeyCode::Key1,
+		KeyCode::Key2,
+		KeyCode::Key3,
+		KeyCode::Key4,
+		KeyCode::Key5,
+		KeyCode::Key6,
+		KeyCode::Key7,
+		KeyCode::Key8,
+		KeyCode::Key9,
+		KeyCode::A,
+		KeyCode::B,
+		KeyCode::C,
+		KeyCode::D,
+		KeyCode::E,
+		KeyCode::F,
+		KeyCode::G,
+		KeyCode::H,
+		KeyCode::I,
+		KeyCode::J,
+		KeyCode::K,
+		KeyCode::L,
+		KeyCode::M,
+		KeyCode::N,
+		KeyCode::O,
+		KeyCode::P,
+		KeyCode::Q,
+		KeyCode::R,
+		KeyCode::S,
+		KeyCode::T,
+		KeyCode::U,
+		KeyCode::V,
+		KeyCode::W,
+		KeyCode::X,
+		KeyCode::Y,
+		KeyCode::Z,
+		KeyCode::F1,
+		KeyCode::F2,
+		KeyCode::F3,
+		KeyCode::F4,
+		KeyCode::F5,
+		KeyCode::F6,
+		KeyCode::F7,
+		KeyCode::F8,
+		KeyCode::F9,
+		KeyCode::F10,
+		KeyCode::F11,
+		KeyCode::F12,
+		KeyCode::F13,
+		KeyCode::F14,
+		KeyCode::F15,
+		KeyCode::F16,
+		KeyCode::F17,
+		KeyCode::F18,
+		KeyCode::F19,
+		KeyCode::F20,
+		KeyCode::F21,
+		KeyCode::F22,
+		KeyCode::F23,
+		KeyCode::F24,
+		KeyCode::Escape,
+		KeyCode::Tab,
+		KeyCode::Space,
+		KeyCode::Enter,
+		KeyCode::Backspace,
+		KeyCode::Delete,
+		KeyCode::Insert,
+		KeyCode::Home,
+		KeyCode::End,
+		KeyCode::PageUp,
+		KeyCode::PageDown,
+		KeyCode::Left,
+		KeyCode::Right,
+		KeyCode::Up,
+		KeyCode::Down,
+		KeyCode::Numpad0,
+		KeyCode::Numpad1,
+		KeyCode::Numpad2,
+		KeyCode::Numpad3,
+		KeyCode::Numpad4,
+		KeyCode::Numpad5,
+		KeyCode::Numpad6,
+		KeyCode::Numpad7,
+		KeyCode::Numpad8,
+		KeyCode::Numpad9,
+		KeyCode::NumpadAdd,
+		KeyCode::NumpadSubtract,
+		KeyCode::NumpadMultiply,
+		KeyCode::NumpadDivide,
+		KeyCode::NumpadDecimal,
+		KeyCode::NumpadComma,
+		KeyCode::NumpadEnter,
+		KeyCode::NumpadEquals,
+		KeyCode::NumLock,
+		KeyCode::LShift,
+		KeyCode::RShift,
+		KeyCode::LControl,
+		KeyCode::RControl,
+		KeyCode::LAlt,
+		KeyCode::RAlt,
+		KeyCode::LSuper,
+		KeyCode::RSuper,
+		KeyCode::Minus,
+		KeyCode::Equals,
+		KeyCode::Plus,
+		KeyCode::LBracket,
+		KeyCode::RBracket,
+		KeyCode::Backslash,
+		KeyCode::Semicolon,
+		KeyCode::Colon,
+		KeyCode::Apostrophe,
+		KeyCode::Grave,
+		KeyCode::Comma,
+		KeyCode::Period,
+		KeyCode::Slash,
+		KeyCode::Caret,
+		KeyCode::Asterisk,
+		KeyCode::At,
+		KeyCode::Underline,
+		KeyCode::CapsLock,
+		KeyCode::ScrollLock,
+		KeyCode::PrintScreen,
+		KeyCode::Pause,
+		KeyCode::Menu,
+		KeyCode::Mute,
+		KeyCode::VolumeUp,
+		KeyCode::VolumeDown,
+		KeyCode::PlayPause,
+		KeyCode::NextTrack,
+		KeyCode::PrevTrack,
+		KeyCode::MediaStop,
+		KeyCode::Unknown,
+	];
+
+	pub fn from_index(index: usize) -> Option<Self> {
+		Self::ALL.get(index).copied()
+	}
+
+	fn letter(index: u8) -> Self {
+		Self::ALL[KeyCode::A as usize + index as usize]
+	}
+
+	pub(crate) fn from_virtual_key_code(vkc: VirtualKeyCode) -> Option<Self> {
+		let key = match vkc {
+			VirtualKeyCode::Key0 => KeyCode::Key0,
+			VirtualKeyCode::Key1 => KeyCode::Key1,
+			VirtualKeyCode::Key2 => KeyCode::Key2,
+			VirtualKeyCode::Key3 => KeyCode::Key3,
+			VirtualKeyCode::Key4 => KeyCode::Key4,
+			VirtualKeyCode::Key5 => KeyCode::Key5,
+			VirtualKeyCode::Key6 => KeyCode::Key6,
+			VirtualKeyCode::Key7 => KeyCode::Key7,
+			VirtualKeyCode::Key8 => KeyCode::Key8,
+			VirtualKeyCode::Key9 => KeyCode::Key9,
+			VirtualKeyCode::A => KeyCode::A,
+			VirtualKeyCode::B => KeyCode::B,
+			VirtualKeyCode::C => KeyCode::C,
+			VirtualKeyCode::D => KeyCode::D,
+			VirtualKeyCode::E => KeyCode::E,
+			VirtualKeyCode::F => KeyCode::F,
+			VirtualKeyCode::G => KeyCode::G,
+			VirtualKeyCode::H => KeyCode::H,
+			VirtualKeyCode::I => KeyCode::I,
+			VirtualKeyCode::J => KeyCode::J,
+			VirtualKeyCode::K => KeyCode::K,
+			VirtualKeyCode::L => KeyCode::L,
+			VirtualKeyCode::M => KeyCode::M,
+			VirtualKeyCode::N => KeyCode::N,
+			VirtualKeyCode::O => KeyCode::O,
+			VirtualKeyCode::P => KeyCode::P,
+			VirtualKeyCode::Q => KeyCode::Q,
+			VirtualKeyCode::R => KeyCode::R,
+			VirtualKeyCode::S => KeyCode::S,
+			VirtualKeyCode::T => KeyCode::T,
+			VirtualKeyCode::U => KeyCode::U,
+			VirtualKeyCode::V => KeyCode::V,
+			VirtualKeyCode::W => KeyCode::W,
+			VirtualKeyCode::X => KeyCode::X,
+			VirtualKeyCode::Y => KeyCode::Y,
+			VirtualKeyCode::Z => KeyCode::Z,
+			VirtualKeyCode::F1 => KeyCode::F1,
+			VirtualKeyCode::F2 => KeyCode::F2,
+			VirtualKeyCode::F3 => KeyCode::F3,
+			VirtualKeyCode::F4 => KeyCode::F4,
+			VirtualKeyCode::F5 => KeyCode::F5,
+			VirtualKeyCode::F6 => KeyCode::F6,
+			VirtualKeyCode::F7 => KeyCode::F7,
+			VirtualKeyCode::F8 => KeyCode::F8,
+			VirtualKeyCode::F9 => KeyCode::F9,
+			VirtualKeyCode::F10 => KeyCode::F10,
+			VirtualKeyCode::F11 => KeyCode::F11,
+			VirtualKeyCode::F12 => KeyCode::F12,
+			VirtualKeyCode::F13 => KeyCode::F13,
+			VirtualKeyCode::F14 => KeyCode::F14,
+			VirtualKeyCode::F15 => KeyCode::F15,
+			VirtualKeyCode::F16 => KeyCode::F16,
+			VirtualKeyCode::F17 => KeyCode::F17,
+			VirtualKeyCode::F18 => KeyCode::F18,
+			VirtualKeyCode::F19 => KeyCode::F19,
+			VirtualKeyCode::F20 => KeyCode::F20,
+			VirtualKeyCode::F21 => KeyCode::F21,
+			VirtualKeyCode::F22 => KeyCode::F22,
+			VirtualKeyCode::F23 => KeyCode::F23,
+			VirtualKeyCode::F24 => KeyCode::F24,
+			VirtualKeyCode::Escape => KeyCode::Escape,
+			VirtualKeyCode::Tab => KeyCode::Tab,
+			VirtualKeyCode::Space => KeyCode::Space,
+			VirtualKeyCode::Return => KeyCode::Enter,
+			VirtualKeyCode::Back => KeyCode::Backspace,
+			VirtualKeyCode::Delete => KeyCode::Delete,
+			VirtualKeyCode::Insert => KeyCode::Insert,
+			VirtualKeyCode::Home => KeyCode::Home,
+			VirtualKeyCode::End => KeyCode::End,
+			VirtualKeyCode::PageUp => KeyCode::PageUp,
+			VirtualKeyCode::PageDown => KeyCode::PageDown,
+			VirtualKeyCode::Left => KeyCode::Left,
+			VirtualKeyCode::Right => KeyCode::Right,
+			VirtualKeyCode::Up => KeyCode::Up,
+			VirtualKeyCode::Down => KeyCode::Down,
+			VirtualKeyCode::Numpad0 => KeyCode::Numpad0,
+			VirtualKeyCode::Numpad1 => KeyCode::Numpad1,
+			VirtualKeyCode::Numpad2 => KeyCode::Numpad2,
+			VirtualKeyCode::Numpad3 => KeyCode::Numpad3,
+			VirtualKeyCode::Numpad4 => KeyCode::Numpad4,
+			VirtualKeyCode::Numpad5 => KeyCode::Numpad5,
+			VirtualKeyCode::Numpad6 => KeyCode::Numpad6,
+			VirtualKeyCode::Numpad7 => KeyCode::Numpad7,
+			VirtualKeyCode::Numpad8 => KeyCode::Numpad8,
+			VirtualKeyCode::Numpad9 => KeyCode::Numpad9,
+			VirtualKeyCode::NumpadAdd => KeyCode::NumpadAdd,
+			VirtualKeyCode::NumpadSubtract => KeyCode::NumpadSubtract,
+			VirtualKeyCode::NumpadMultiply => KeyCode::NumpadMultiply,
+			VirtualKeyCode::NumpadDivide => KeyCode::NumpadDivide,
+			VirtualKeyCode::NumpadDecimal => KeyCode::NumpadDecimal,
+			VirtualKeyCode::NumpadComma => KeyCode::NumpadComma,
+			VirtualKeyCode::NumpadEnter => KeyCode::NumpadEnter,
+			VirtualKeyCode::NumpadEquals => KeyCode::NumpadEquals,
+			VirtualKeyCode::Numlock => KeyCode::NumLock,
+			VirtualKeyCode::LShift => KeyCode::LShift,
+			VirtualKeyCode::RShift => KeyCode::RShift,
+			VirtualKeyCode::LControl => KeyCode::LControl,
+			VirtualKeyCode::RControl => KeyCode::RControl,
+			VirtualKeyCode::LAlt => KeyCode::LAlt,
+			VirtualKeyCode::RAlt => KeyCode::RAlt,
+			VirtualKeyCode::LWin => KeyCode::LSuper,
+			VirtualKeyCode::RWin => KeyCode::RSuper,
+			VirtualKeyCode::Minus => KeyCode::Minus,
+			VirtualKeyCode::Equals => KeyCode::Equals,
+			VirtualKeyCode::Plus => KeyCode::Plus,
+			VirtualKeyCode::LBracket => KeyCode::LBracket,
+			VirtualKeyCode::RBracket => KeyCode::RBracket,
+			VirtualKeyCode::Backslash => KeyCode::Backslash,
+			VirtualKeyCode::Semicolon => KeyCode::Semicolon,
+			VirtualKeyCode::Colon => KeyCode::Colon,
+			VirtualKeyCode::Apostrophe => KeyCode::Apostrophe,
+			VirtualKeyCode::Grave => KeyCode::Grave,
+			VirtualKeyCode::Comma => KeyCode::Comma,
+			VirtualKeyCode::Period => KeyCode::Period,
+			VirtualKeyCode::Slash => KeyCode::Slash,
+			VirtualKeyCode::Caret => KeyCode::Caret,
+			VirtualKeyCode::Asterisk => KeyCode::Asterisk,
+			VirtualKeyCode::At => KeyCode::At,
+			VirtualKeyCode::Underline => KeyCode::Underline,
+			VirtualKeyCode::Capital => KeyCode::CapsLock,
+			VirtualKeyCode::Scroll => KeyCode::ScrollLock,
+			VirtualKeyCode::Snapshot => KeyCode::PrintScreen,
+			VirtualKeyCode::Pause => KeyCode::Pause,
+			VirtualKeyCode::Apps => KeyCode::Menu,
+			VirtualKeyCode::Mute => KeyCode::Mute,
+			VirtualKeyCode::VolumeUp => KeyCode::VolumeUp,
+			VirtualKeyCode::VolumeDown => KeyCode::VolumeDown,
+			VirtualKeyCode::PlayPause => KeyCode::PlayPause,
+			VirtualKeyCode::NextTrack => KeyCode::NextTrack,
+			VirtualKeyCode::PrevTrack => KeyCode::PrevTrack,
+			VirtualKeyCode::MediaStop => KeyCode::MediaStop,
+			_ => return None,
+		};
+		Some(key)
+	}
+
+	pub fn is_modifier(&self) -> bool {
+		matches!(
+			self,
+			KeyCode::LShift
+				| KeyCode::RShift
+				| KeyCode::LControl
+				| KeyCode::RControl
+				| KeyCode::LAlt
+				| KeyCode::RAlt
+				| KeyCode::LSuper
+				| KeyCode::RSuper
+		)
+	}
+
+	// 1 based, to match is_function_key_pressed
+	pub fn function_key_number(&self) -> Option<u8> {
+		if *self >= KeyCode::F1 && *self <= KeyCode::F24 {
+			Some((*self as u8) - (KeyCode::F1 as u8) + 1)
+		} else {
+			None
+		}
+	}
+}
+
+// maps the ascii used by the old u8 based key api, letters are case insensitive
+impl From<u8> for KeyCode {
+	fn from(ascii: u8) -> Self {
+		match ascii {
+			b'0' => KeyCode::Key0,
+			b'1' => KeyCode::Key1,
+			b'2' => KeyCode::Key2,
+			b'3' => KeyCode::Key3,
+			b'4' => KeyCode::Key4,
+			b'5' => KeyCode::Key5,
+			b'6' => KeyCode::Key6,
+			b'7' => KeyCode::Key7,
+			b'8' => KeyCode::Key8,
+			b'9' => KeyCode::Key9,
+			b' ' => KeyCode::Space,
+			b'\t' => KeyCode::Tab,
+			b'\r' => KeyCode::Enter,
+			b'\n' => KeyCode::Enter,
+			8 => KeyCode::Backspace,
+			27 => KeyCode::Escape,
+			127 => KeyCode::Delete,
+			b'-' => KeyCode::Minus,
+			b'=' => KeyCode::Equals,
+			b'+' => KeyCode::Plus,
+			b'[' => KeyCode::LBracket,
+			b']' => KeyCode::RBracket,
+			b'\\' => KeyCode::Backslash,
+			b';' => KeyCode::Semicolon,
+			b':' => KeyCode::Colon,
+			b'\'' => KeyCode::Apostrophe,
+			b'`' => KeyCode::Grave,
+			b',' => KeyCode::Comma,
+			b'.' => KeyCode::Period,
+			b'/' => KeyCode::Slash,
+			b'^' => KeyCode::Caret,
+			b'*' => KeyCode::Asterisk,
+			b'@' => KeyCode::At,
+			b'_' => KeyCode::Underline,
+			b'a'..=b'z' => Self::letter(ascii - b'a'),
+			b'A'..=b'Z' => Self::letter(ascii - b'A'),
+			_ => KeyCode::Unknown,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn ascii_maps_to_key_codes() {
+		assert_eq!(KeyCode::A, KeyCode::from(b'a'));
+		assert_eq!(KeyCode::A, KeyCode::from(b'A'));
+		assert_eq!(KeyCode::Z, KeyCode::from(b'z'));
+		assert_eq!(KeyCode::Key7, KeyCode::from(b'7'));
+		assert_eq!(KeyCode::Equals, KeyCode::from(b'='));
+		assert_eq!(KeyCode::Unknown, KeyCode::from(200));
+	}
+
+	#[test]
+	fn all_is_indexed_by_key_code() {
+		for (i, k) in KeyCode::ALL.iter().enumerate() {
+			assert_eq!(i, *k as usize);
+		}
+		assert_eq!(
+			Some(KeyCode::Home),
+			KeyCode::from_index(KeyCode::Home as usize)
+		);
+		assert_eq!(None, KeyCode::from_index(KeyCode::COUNT));
+	}
+
+	#[test]
+	fn function_keys_are_numbered_from_one() {
+		assert_eq!(Some(1), KeyCode::F1.function_key_number());
+		assert_eq!(Some(12), KeyCode::F12.function_key_number());
+		assert_eq!(None, KeyCode::Escape.function_key_number());
+	}
+}
@@ -0,0 +1,89 @@
+use crate::window::KeyCode;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TextInputEvent {
+	Character(char),
+	// editing keys, including key repeats, e.g. Backspace, Delete, cursor keys, Home, End, Enter
+	Key(KeyCode),
+	// IME composition in progress, cursor is a byte range into text
+	Preedit {
+		text:   String,
+		cursor: Option<(usize, usize)>,
+	},
+	// IME composition finished
+	Commit(String),
+}
+
+impl TextInputEvent {
+	pub fn is_editing_key(key: KeyCode) -> bool {
+		matches!(
+			key,
+			KeyCode::Backspace
+				| KeyCode::Delete
+				| KeyCode::Left
+				| KeyCode::Right
+				| KeyCode::Up
+				| KeyCode::Down
+				| KeyCode::Home
+				| KeyCode::End
+				| KeyCode::PageUp
+				| KeyCode::PageDown
+				| KeyCode::Enter
+				| KeyCode::NumpadEnter
+				| KeyCode::Tab
+				| KeyCode::Escape
+		)
+	}
+
+	// control characters arrive as Key events, and macOS sends function keys in the private use area
+	pub fn is_printable(c: char) -> bool {
+		!c.is_control() && !('\u{f700}'..='\u{f8ff}').contains(&c)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::window::WindowUpdateContext;
+
+	#[test]
+	fn text_is_per_frame_and_composition_lasts_until_commit() {
+		let mut wuc = WindowUpdateContext::new();
+		wuc.add_text_input_event(TextInputEvent::Character('a'));
+		wuc.add_text_input_event(TextInputEvent::Character('\u{8}'));
+		wuc.add_text_input_event(TextInputEvent::Key(KeyCode::Backspace));
+		wuc.add_text_input_event(TextInputEvent::Preedit {
+			text:   "ka".to_string(),
+			cursor: Some((2, 2)),
+		});
+		assert_eq!("a", wuc.text());
+		assert_eq!(3, wuc.text_input_events().len());
+		assert_eq!(Some("ka"), wuc.ime_preedit());
+		assert_eq!(Some((2, 2)), wuc.ime_preedit_cursor());
+		wuc.update();
+
+		// the IME only reports changes, so the composition outlives the frame
+		assert_eq!("", wuc.text());
+		assert!(wuc.text_input_events().is_empty());
+		assert_eq!(Some("ka"), wuc.ime_preedit());
+
+		wuc.add_text_input_event(TextInputEvent::Commit("か".to_string()));
+		assert_eq!("か", wuc.text());
+		assert_eq!(None, wuc.ime_preedit());
+		assert_eq!(None, wuc.ime_preedit_cursor());
+		wuc.update();
+		assert_eq!("", wuc.text());
+
+		// an empty preedit, e.g. when the IME gets disabled, cancels the composition
+		wuc.add_text_input_event(TextInputEvent::Preedit {
+			text:   "ka".to_string(),
+			cursor: None,
+		});
+		wuc.add_text_input_event(TextInputEvent::Preedit {
+			text:   String::new(),
+			cursor: None,
+		});
+		assert_eq!(None, wuc.ime_preedit());
+		assert_eq!("", wuc.text());
+	}
+}
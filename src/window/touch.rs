@@ -0,0 +1,51 @@
+use crate::math::Vector2;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TouchPhase {
+	Began,
+	Moved,
+	Stationary, // still down, but didn't move this frame
+	Ended,
+	Cancelled, // e.g. taken over by the system, treat like Ended without acting on it
+}
+
+impl TouchPhase {
+	pub fn is_active(&self) -> bool {
+		!matches!(self, TouchPhase::Ended | TouchPhase::Cancelled)
+	}
+}
+
+// positions are like the mouse's, origin bottom left
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Touch {
+	pub id:               u64, // stable while the finger is down
+	pub phase:            TouchPhase,
+	pub pos:              Vector2, // normalised 0..1
+	pub pos_pixels:       Vector2,
+	pub start_pos_pixels: Vector2,
+}
+
+impl Touch {
+	pub fn new(id: u64, pos_pixels: &Vector2, window_size: &Vector2) -> Self {
+		let mut t = Self {
+			id,
+			phase: TouchPhase::Began,
+			pos: Vector2::zero(),
+			pos_pixels: *pos_pixels,
+			start_pos_pixels: *pos_pixels,
+		};
+		t.set_pos_pixels(pos_pixels, window_size);
+		t
+	}
+
+	pub(crate) fn set_pos_pixels(&mut self, pos_pixels: &Vector2, window_size: &Vector2) {
+		self.pos_pixels = *pos_pixels;
+		if window_size.x > 0.0 && window_size.y > 0.0 {
+			self.pos = Vector2::new(pos_pixels.x / window_size.x, pos_pixels.y / window_size.y);
+		}
+	}
+
+	pub fn is_active(&self) -> bool {
+		self.phase.is_active()
+	}
+}
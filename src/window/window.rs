@@ -1,8 +1,11 @@
 //use chrono::prelude::*;
+use std::cell::Cell;
+use std::rc::Rc;
+
 use glutin::dpi::PhysicalPosition;
-use glutin::event::VirtualKeyCode;
-use glutin::event::{ElementState, Event, KeyboardInput, WindowEvent};
+use glutin::event::{ElementState, Event, Ime, KeyboardInput, WindowEvent};
 use glutin::event_loop::{ControlFlow, EventLoop};
+use glutin::platform::run_return::EventLoopExtRunReturn;
 use glutin::window::WindowBuilder;
 use glutin::ContextBuilder;
 use glutin::{ContextWrapper, PossiblyCurrent};
@@ -10,17 +13,18 @@ use tracing::*;
 
 use crate::math::Vector2;
 pub use crate::window::window_update_context::WindowUpdateContext;
+use crate::window::{
+	FixedTimestep, FocusPolicy, FrameLimiter, FramePacing, GestureConfig, KeyCode, MonitorInfo,
+	TextInputEvent, TouchPhase, WindowLayout, WindowMode,
+};
 use crate::DefaultTelemetry;
 
-const TARGET_FPS: f64 = 60.0;
-const TARGET_FRAME_TIME: f64 = 1000.0 / TARGET_FPS;
-
 #[derive(Default)]
 #[allow(dead_code)]
 pub struct WindowCallbacks {
 	update: Option<Box<dyn FnMut(&mut Box<dyn WindowUserData>, &mut WindowUpdateContext) -> bool>>,
 	fixed_update: Option<Box<dyn FnMut(&mut Box<dyn WindowUserData>, f64)>>,
-	render:       Option<Box<dyn FnMut(&mut Box<dyn WindowUserData>)>>,
+	render:       Option<Box<dyn FnMut(&mut Box<dyn WindowUserData>, f64)>>,
 }
 
 impl<'a> WindowCallbacks {
@@ -39,7 +43,7 @@ impl<'a> WindowCallbacks {
 		self.fixed_update = Some(f);
 		self
 	}
-	pub fn with_render(mut self, f: Box<dyn FnMut(&mut Box<dyn WindowUserData>)>) -> Self {
+	pub fn with_render(mut self, f: Box<dyn FnMut(&mut Box<dyn WindowUserData>, f64)>) -> Self {
 		self.render = Some(f);
 		self
 	}
@@ -51,24 +55,54 @@ pub trait WindowUserData {
 }
 
 pub struct Window {
-	el:               Option<EventLoop<()>>,
+	el: Option<EventLoop<()>>,
 	windowed_context: Option<ContextWrapper<PossiblyCurrent, glutin::window::Window>>,
-	title:            String,
-	pos:              Vector2,
-	size:             Vector2,
+	title: String,
+	pos: Vector2,
+	size: Vector2,
+	fixed_timestep: FixedTimestep,
+	frame_pacing: FramePacing,
+	focus_policy: FocusPolicy,
+	gesture_config: GestureConfig,
+	emulate_mouse_from_touch: bool,
+	mode: WindowMode,
+	headless: bool,
+	ime_allowed: bool,
+	physical_size: Rc<Cell<Vector2>>, // updated by Game every frame, see Renderer::setup
 }
 
 impl Window {
 	pub fn new() -> Self {
 		Self {
-			el:               None,
+			el: None,
 			windowed_context: None,
-			title:            String::new(),
-			pos:              Vector2::new(100.0, 100.0),
-			size:             Vector2::new(1400.0, 700.0),
+			title: String::new(),
+			pos: Vector2::new(100.0, 100.0),
+			size: Vector2::new(1400.0, 700.0),
+			fixed_timestep: FixedTimestep::default(),
+			frame_pacing: FramePacing::default(),
+			focus_policy: FocusPolicy::default(),
+			gesture_config: GestureConfig::default(),
+			emulate_mouse_from_touch: false,
+			mode: WindowMode::default(),
+			headless: false,
+			ime_allowed: false,
+			physical_size: Rc::new(Cell::new(Vector2::zero())),
+		}
+	}
+
+	// a window that never opens, and never gets a GL context, e.g. for automated tests
+	pub fn new_headless() -> Self {
+		Self {
+			headless: true,
+			..Self::new()
 		}
 	}
 
+	pub fn is_headless(&self) -> bool {
+		self.headless
+	}
+
 	// some form of configuration
 	pub fn set_title(&mut self, title: &str) {
 		self.title = title.to_string();
@@ -78,14 +112,112 @@ impl Window {
 		}
 	}
 
+	// in physical pixels on the virtual desktop, like MonitorInfo
 	pub fn set_position(&mut self, pos: &Vector2) {
 		self.pos = *pos;
 	}
 
+	// in logical pixels, so the window looks the same on every dpi
 	pub fn set_size(&mut self, size: &Vector2) {
 		self.size = *size;
 	}
 
+	pub fn pos(&self) -> &Vector2 {
+		&self.pos
+	}
+
+	pub fn size(&self) -> &Vector2 {
+		&self.size
+	}
+
+	// applied right away if the window is already open
+	pub fn set_mode(&mut self, mode: WindowMode) {
+		if let Some(ctx) = &self.windowed_context {
+			mode.apply(ctx.window());
+		}
+		self.mode = mode;
+	}
+
+	pub fn mode(&self) -> &WindowMode {
+		&self.mode
+	}
+
+	// empty until setup
+	pub fn monitors(&self) -> Vec<MonitorInfo> {
+		match &self.windowed_context {
+			Some(ctx) => ctx
+				.window()
+				.available_monitors()
+				.map(|m| MonitorInfo::from_handle(&m))
+				.collect(),
+			None => Vec::new(),
+		}
+	}
+
+	pub fn set_fixed_timestep(&mut self, fixed_timestep: FixedTimestep) {
+		self.fixed_timestep = fixed_timestep;
+	}
+
+	pub fn fixed_timestep(&self) -> &FixedTimestep {
+		&self.fixed_timestep
+	}
+
+	// vsync is only picked up by setup
+	pub fn set_frame_pacing(&mut self, frame_pacing: FramePacing) {
+		self.frame_pacing = frame_pacing;
+	}
+
+	pub fn frame_pacing(&self) -> &FramePacing {
+		&self.frame_pacing
+	}
+
+	pub fn set_focus_policy(&mut self, focus_policy: FocusPolicy) {
+		self.focus_policy = focus_policy;
+	}
+
+	pub fn focus_policy(&self) -> &FocusPolicy {
+		&self.focus_policy
+	}
+
+	pub fn set_gesture_config(&mut self, gesture_config: GestureConfig) {
+		self.gesture_config = gesture_config;
+	}
+
+	pub fn gesture_config(&self) -> &GestureConfig {
+		&self.gesture_config
+	}
+
+	pub fn set_emulate_mouse_from_touch(&mut self, emulate: bool) {
+		self.emulate_mouse_from_touch = emulate;
+	}
+
+	// like WindowUpdateContext::window_size, shared so the Renderer can follow it on its own
+	pub(crate) fn physical_size_handle(&self) -> Rc<Cell<Vector2>> {
+		self.physical_size.clone()
+	}
+
+	// a fresh context with this window's input settings
+	pub fn create_update_context(&self) -> WindowUpdateContext {
+		let mut wuc = WindowUpdateContext::new();
+		wuc.set_gesture_config(self.gesture_config);
+		wuc.set_emulate_mouse_from_touch(self.emulate_mouse_from_touch);
+		wuc.set_initial_scale_factor(self.scale_factor());
+		wuc.window_pos = self.pos;
+		wuc.window_size = wuc.to_physical(&self.size);
+		wuc.set_current_window_mode(self.mode.clone());
+		wuc.window_changed = false;
+		wuc.set_monitors(self.monitors());
+		wuc
+	}
+
+	// enables IME composition events, e.g. for name entry in CJK languages
+	pub fn set_ime_allowed(&mut self, allowed: bool) {
+		self.ime_allowed = allowed;
+		if let Some(ctx) = &self.windowed_context {
+			ctx.window().set_ime_allowed(allowed);
+		}
+	}
+
 	pub fn scale_factor(&self) -> f64 {
 		if let Some(ctx) = &self.windowed_context {
 			ctx.window().scale_factor()
@@ -95,13 +227,46 @@ impl Window {
 	}
 
 	pub fn setup(&mut self) -> anyhow::Result<()> {
+		if self.headless {
+			return Ok(());
+		}
 		let el = EventLoop::new();
+
+		// the saved layout might be from a monitor that is gone now
+		let monitors = el
+			.available_monitors()
+			.map(|m| MonitorInfo::from_handle(&m))
+			.collect::<Vec<_>>();
+		// monitors are in physical pixels, the size is logical
+		let scale_factor = monitors
+			.iter()
+			.find(|m| {
+				self.pos.x >= m.pos.x
+					&& self.pos.y >= m.pos.y
+					&& self.pos.x < m.pos.x + m.size.x
+					&& self.pos.y < m.pos.y + m.size.y
+			})
+			.or(monitors.first())
+			.map(|m| m.scale_factor as f32)
+			.unwrap_or(1.0);
+		let (pos, size) =
+			WindowLayout::clamp_to_monitors(&self.pos, &self.size.scaled(scale_factor), &monitors);
+		let size = size.scaled(1.0 / scale_factor);
+		if pos != self.pos || size != self.size {
+			debug!(
+				"Moved window from {:?} {:?} to {:?} {:?}",
+				self.pos, self.size, pos, size
+			);
+			self.pos = pos;
+			self.size = size;
+		}
+
 		let wb = WindowBuilder::new()
 			//	    			.with_inner_size( glutin::dpi::PhysicalSize{ width: 1920/2, height: 1080/2 } )
 			//	    			.with_inner_size( glutin::dpi::PhysicalSize{ width: 1920/2, height: 512 } )
-			.with_inner_size(glutin::dpi::PhysicalSize {
-				width:  self.size.x as i32,
-				height: self.size.y as i32,
+			.with_inner_size(glutin::dpi::LogicalSize {
+				width:  self.size.x as f64,
+				height: self.size.y as f64,
 			})
 			//	    			.with_inner_size( glutin::dpi::PhysicalSize{ width: 1880, height: 700 } )
 			//	    			.with_inner_size( glutin::dpi::PhysicalSize{ width: 512, height: 512 } )
@@ -112,11 +277,15 @@ impl Window {
 			.with_title(&self.title);
 
 		let windowed_context = ContextBuilder::new()
-			.with_vsync(true) // yes?
+			.with_vsync(self.frame_pacing.vsync())
 			.build_windowed(wb, &el)
 			.unwrap();
 
 		let windowed_context = unsafe { windowed_context.make_current().unwrap() };
+		windowed_context.window().set_ime_allowed(self.ime_allowed);
+		if self.mode.is_fullscreen() {
+			self.mode.apply(windowed_context.window());
+		}
 
 		println!(
 			"Pixel format of the window's GL context: {:?}",
@@ -142,15 +311,18 @@ impl Window {
 
 	fn run_event_loop(
 		mut parent_thread: Option<std::thread::Thread>,
-		el: EventLoop<()>,
+		mut el: EventLoop<()>,
 		windowed_context: ContextWrapper<PossiblyCurrent, glutin::window::Window>,
 		mut userdata: Box<dyn WindowUserData>,
 		mut callbacks: WindowCallbacks,
-	) {
+		mut fixed_timestep: FixedTimestep,
+		frame_pacing: FramePacing,
+		focus_policy: FocusPolicy,
+		mut window_update_context: WindowUpdateContext,
+	) -> Box<dyn WindowUserData> {
 		// let el = window.el.take().unwrap();
 		// let windowed_context = window.windowed_context.take().unwrap();
 		let mut is_done = false;
-		let mut window_update_context = WindowUpdateContext::new();
 
 		//let mut previous_now: DateTime<Utc> = Utc::now();
 		let mut previous_now = std::time::Instant::now();
@@ -161,14 +333,19 @@ impl Window {
 		let mut slowest_frame_ms = 0.0;
 		let mut slow_frame_count = 0;
 
-		el.run(move |event, _, control_flow| {
+		let userdata_ref = &mut userdata;
+		el.run_return(move |event, _, control_flow| {
+			let userdata = &mut *userdata_ref;
 			event_count += 1;
 			let start_time = std::time::Instant::now();
 
-			window_update_context.window_size.x =
-				windowed_context.window().inner_size().width as f32;
-			window_update_context.window_size.y =
-				windowed_context.window().inner_size().height as f32;
+			let inner_size = windowed_context.window().inner_size();
+			let size = Vector2::new(inner_size.width as f32, inner_size.height as f32);
+			// minimising reports a zero size, which is not worth remembering
+			if size != window_update_context.window_size && size.x > 0.0 && size.y > 0.0 {
+				window_update_context.window_size = size;
+				window_update_context.window_changed = true;
+			}
 
 			match windowed_context.window().inner_position() {
 				Ok(PhysicalPosition { x, y }) => {
@@ -207,8 +384,32 @@ impl Window {
 				Event::WindowEvent { event, .. } => match event {
 					WindowEvent::Resized(physical_size) => {
 						dbg!(&physical_size);
+						// some platforms report minimising as a resize to zero
+						window_update_context
+							.set_minimized(physical_size.width == 0 || physical_size.height == 0);
 						windowed_context.resize(physical_size)
 					},
+					WindowEvent::ScaleFactorChanged {
+						scale_factor,
+						new_inner_size,
+					} => {
+						debug!(
+							"ScaleFactorChanged {} new inner size {:?}",
+							scale_factor, new_inner_size
+						);
+						// the suggested size keeps the logical size, window_size follows on the next event
+						window_update_context.set_scale_factor(scale_factor);
+						windowed_context.resize(*new_inner_size);
+					},
+					WindowEvent::Focused(focused) => {
+						window_update_context.set_has_focus(focused);
+						if !focused && focus_policy.release_keys_on_focus_loss() {
+							window_update_context.release_all_keys();
+						}
+					},
+					WindowEvent::Occluded(occluded) => {
+						window_update_context.set_occluded(occluded);
+					},
 					WindowEvent::Moved(physical_size) => {
 						dbg!(&physical_size);
 						//	                	windowed_context.resize(physical_size)
@@ -218,46 +419,81 @@ impl Window {
 						// debug!("MouseWheel delta {:?}", delta );
 						match delta {
 							glutin::event::MouseScrollDelta::LineDelta(x, y) => {
-								window_update_context.mouse_wheel_line_delta.x = x;
-								window_update_context.mouse_wheel_line_delta.y = y;
+								window_update_context
+									.add_mouse_wheel_line_delta(&Vector2::new(x, y));
+							},
+							glutin::event::MouseScrollDelta::PixelDelta(PhysicalPosition {
+								x,
+								y,
+							}) => {
+								window_update_context
+									.add_mouse_wheel_pixel_delta(&Vector2::new(x as f32, y as f32));
 							},
-							_ => {},
 						}
 					},
 					WindowEvent::CursorMoved { position, .. } => {
-						let inner_size = windowed_context.window().inner_size();
-
-						let w = inner_size.width as f64;
-						let h = inner_size.height as f64;
-						let mouse_x = position.x / w;
-						let mouse_y = (h - position.y) / h;
-						let scale = 0.5;
-						window_update_context.mouse_pos.x = mouse_x as f32;
-						window_update_context.mouse_pos.y = mouse_y as f32;
-						/*
-						tracing::debug!(
-							"{:?} {:?} {} {}",
-							&position,
-							&inner_size,
-							&mouse_x,
-							&mouse_y
-						);
-						*/
+						let h = windowed_context.window().inner_size().height as f64;
+						let mouse_x = position.x;
+						let mouse_y = h - position.y;
+						window_update_context
+							.set_mouse_pos_pixels(&Vector2::new(mouse_x as f32, mouse_y as f32));
+					},
+					WindowEvent::Touch(touch) => {
+						let h = windowed_context.window().inner_size().height as f64;
+						let pos =
+							Vector2::new(touch.location.x as f32, (h - touch.location.y) as f32);
+						let phase = match touch.phase {
+							glutin::event::TouchPhase::Started => TouchPhase::Began,
+							glutin::event::TouchPhase::Moved => TouchPhase::Moved,
+							glutin::event::TouchPhase::Ended => TouchPhase::Ended,
+							glutin::event::TouchPhase::Cancelled => TouchPhase::Cancelled,
+						};
+						window_update_context.add_touch_event(touch.id, phase, &pos);
+					},
+					WindowEvent::CursorEntered { .. } => {
+						window_update_context.set_mouse_inside(true);
+					},
+					WindowEvent::CursorLeft { .. } => {
+						window_update_context.set_mouse_inside(false);
 					},
 					WindowEvent::MouseInput { state, button, .. } => {
 						let button_index = match button {
 							glutin::event::MouseButton::Left => 0,
 							glutin::event::MouseButton::Middle => 1,
 							glutin::event::MouseButton::Right => 2,
-							_ => 0,
+							glutin::event::MouseButton::Other(n) => 3 + n as usize,
 						};
-						window_update_context.set_mouse_button(
-							button_index,
-							state == glutin::event::ElementState::Pressed,
-						)
+						if button_index < WindowUpdateContext::MOUSE_BUTTON_COUNT {
+							window_update_context.set_mouse_button(
+								button_index,
+								state == glutin::event::ElementState::Pressed,
+							)
+						} else {
+							debug!("Ignoring mouse button {:?}", button);
+						}
 
 						//	                	dbg!(&state, &button, &window_update_context.mouse_buttons);
 					},
+					WindowEvent::ReceivedCharacter(c) => {
+						window_update_context.add_text_input_event(TextInputEvent::Character(c));
+					},
+					WindowEvent::Ime(ime) => match ime {
+						Ime::Preedit(text, cursor) => {
+							window_update_context
+								.add_text_input_event(TextInputEvent::Preedit { text, cursor });
+						},
+						Ime::Commit(text) => {
+							window_update_context
+								.add_text_input_event(TextInputEvent::Commit(text));
+						},
+						Ime::Disabled => {
+							window_update_context.add_text_input_event(TextInputEvent::Preedit {
+								text:   String::new(),
+								cursor: None,
+							});
+						},
+						Ime::Enabled => {},
+					},
 					WindowEvent::KeyboardInput {
 						input:
 							KeyboardInput {
@@ -266,83 +502,27 @@ impl Window {
 								..
 							},
 						..
-					} => match (virtual_code, state) {
-						(VirtualKeyCode::Escape, state) => {
-							window_update_context.is_escape_pressed =
-								state == ElementState::Pressed;
-							//                			println!("Escape {:?}", &state );
-						},
-						(VirtualKeyCode::Space, state) => {
-							window_update_context.is_space_pressed = state == ElementState::Pressed;
-							//                			println!("Space {:?}", &state );
-						},
-						(vkc, state) if vkc >= VirtualKeyCode::A && vkc <= VirtualKeyCode::Z => {
-							let o = ((vkc as u16) - (VirtualKeyCode::A as u16)) as u8;
-							let o = (o + 'a' as u8) as usize;
-							println!("KeyboardInput A-Z {:?} -> {}", &vkc, &o);
-							window_update_context.is_key_pressed[o] =
-								state == ElementState::Pressed;
-						},
-						_ => {
-							// println!("KeyboardInput {:?}", &virtual_code);
-							if let Some(ascii) = match virtual_code {
-								VirtualKeyCode::Equals => Some(61),
-								VirtualKeyCode::LBracket => Some(91),
-								VirtualKeyCode::Backslash => Some(92),
-								VirtualKeyCode::RBracket => Some(93),
-								VirtualKeyCode::Caret => Some(94),
-								VirtualKeyCode::Slash => Some(47),
-								VirtualKeyCode::Grave => Some(96),
-								_ => None,
-							} {
-								window_update_context.is_key_pressed[ascii] =
-									state == ElementState::Pressed;
-							} else if let Some(fkey) = match virtual_code {
-								VirtualKeyCode::F1 => Some(1),
-								VirtualKeyCode::F2 => Some(2),
-								VirtualKeyCode::F3 => Some(3),
-								VirtualKeyCode::F4 => Some(4),
-								VirtualKeyCode::F5 => Some(5),
-								VirtualKeyCode::F6 => Some(6),
-								VirtualKeyCode::F7 => Some(7),
-								VirtualKeyCode::F8 => Some(8),
-								VirtualKeyCode::F9 => Some(9),
-								VirtualKeyCode::F10 => Some(10),
-								VirtualKeyCode::F11 => Some(11),
-								VirtualKeyCode::F12 => Some(12),
-								_ => None,
-							} {
-								window_update_context.is_function_key_pressed[fkey] =
-									state == ElementState::Pressed;
-							} else {
-								match virtual_code {
-									VirtualKeyCode::LShift | VirtualKeyCode::RShift => {
-										window_update_context.set_modifier_pressed(
-											crate::window::ModifierKey::Shift,
-											state == ElementState::Pressed,
-										);
-									},
-									VirtualKeyCode::LControl | VirtualKeyCode::RControl => {
-										window_update_context.set_modifier_pressed(
-											crate::window::ModifierKey::Ctrl,
-											state == ElementState::Pressed,
-										);
-									},
-									VirtualKeyCode::LAlt | VirtualKeyCode::RAlt => {
-										window_update_context.set_modifier_pressed(
-											crate::window::ModifierKey::Alt,
-											state == ElementState::Pressed,
-										);
-									},
-									vc => {
-										println!("Unmapped KeyboardInput {:?} !", &vc)
-									},
-								};
+					} => match KeyCode::from_virtual_key_code(virtual_code) {
+						Some(key) => {
+							let pressed = state == ElementState::Pressed;
+							if pressed && TextInputEvent::is_editing_key(key) {
+								window_update_context
+									.add_text_input_event(TextInputEvent::Key(key));
 							}
+							window_update_context.set_key_down(key, pressed);
+						},
+						None => {
+							println!("Unmapped KeyboardInput {:?} !", &virtual_code)
 						},
 					},
 					_ => (),
 				},
+				Event::Suspended => {
+					window_update_context.set_os_suspended(true);
+				},
+				Event::Resumed => {
+					window_update_context.set_os_suspended(false);
+				},
 				Event::RedrawRequested(_) => {
 					//	                gl.draw_frame([1.0, 0.5, 0.7, 1.0]);
 					windowed_context.swap_buffers().unwrap();
@@ -351,6 +531,19 @@ impl Window {
 					// :TODO: :HACK: swapped RedrawEventsCleared and MainEventsCleared for testing
 					// debug!("RedrawEventsCleared {}", event_count);
 
+					// throttled while in the background
+					let frame_pacing =
+						focus_policy.frame_pacing(&frame_pacing, &window_update_context);
+					if !is_done
+						&& *control_flow != ControlFlow::Exit
+						&& !frame_pacing.wait_for_frame(next_time)
+					{
+						// woken up early, e.g. by input events
+						*control_flow =
+							ControlFlow::WaitUntil(frame_pacing.wake_up_time(next_time));
+						return;
+					}
+
 					// all evens handled, lets render
 					//let now: DateTime<Utc> = Utc::now();
 					let now = std::time::Instant::now();
@@ -362,7 +555,7 @@ impl Window {
 					window_update_context.time_step = time_step;
 
 					let done = if let Some(ref mut ucb) = callbacks.update {
-						ucb(&mut userdata, &mut window_update_context)
+						ucb(userdata, &mut window_update_context)
 					} else {
 						true
 					};
@@ -376,43 +569,57 @@ impl Window {
 						}
 					}
 
-					if !is_done {
+					if !is_done && !focus_policy.is_paused(&window_update_context) {
+						// time_step might have been replaced, e.g. by an input recording
+						let ticks = fixed_timestep.advance(window_update_context.time_step);
 						if let Some(ref mut fucb) = callbacks.fixed_update {
-							// :TODO: make time step fixed
-							let half_time_step = 0.5 * time_step;
-							fucb(&mut userdata, half_time_step);
-							fucb(&mut userdata, half_time_step);
+							for _ in 0..ticks {
+								fucb(userdata, fixed_timestep.time_step());
+							}
 						}
 					}
 
-					if let Some(ref mut rcb) = callbacks.render {
-						rcb(&mut userdata);
+					let should_render = focus_policy.should_render(&window_update_context);
+					if should_render {
+						if let Some(ref mut rcb) = callbacks.render {
+							rcb(userdata, fixed_timestep.alpha());
+						}
 					}
 
 					window_update_context.update();
-					windowed_context.swap_buffers().unwrap();
+					// after update, so window_changed is seen next frame
+					if let Some(mode) = window_update_context.take_requested_window_mode() {
+						debug!("Switching window mode to {:?}", mode);
+						mode.apply(windowed_context.window());
+						window_update_context.set_current_window_mode(mode);
+					}
+					if should_render {
+						windowed_context.swap_buffers().unwrap();
+					}
 					match *control_flow {
 						glutin::event_loop::ControlFlow::Exit => {},
 						_ => {
 							//	        println!("{:?}", event);
 							//	        *control_flow = ControlFlow::Poll;
+							let target_frame_time =
+								frame_pacing.target_frame_time().as_secs_f64() * 1000.0;
 							let elapsed_time = std::time::Instant::now()
-								.duration_since(start_time)
-								.as_millis() as f64;
-							let wait_millis = match TARGET_FRAME_TIME >= elapsed_time {
+								.duration_since(now)
+								.as_secs_f64() * 1000.0;
+							let wait_millis = match target_frame_time >= elapsed_time {
 								true => {
 									/*
 									tracing::debug!(
 										"Fast frame {} > {} (ms)",
 										elapsed_time,
-										TARGET_FRAME_TIME
+										target_frame_time
 									);
 									*/
 									DefaultTelemetry::trace::<f64>(
 										"fast frame",
 										elapsed_time / 1000.0,
 									);
-									TARGET_FRAME_TIME - elapsed_time
+									target_frame_time - elapsed_time
 								},
 								false => {
 									DefaultTelemetry::trace::<f64>(
@@ -422,7 +629,7 @@ impl Window {
 									/*
 									warn!(
 										"Slow frame {} > {} (ms)",
-										elapsed_time, TARGET_FRAME_TIME
+										elapsed_time, target_frame_time
 									);
 									if slowest_frame_ms < elapsed_time {
 										slowest_frame_ms = elapsed_time;
@@ -436,9 +643,13 @@ impl Window {
 							//debug!("Waiting {}", wait_millis);
 							//let next_frame_time = std::time::Instant::now() + std::time::Duration::from_nanos(16_666_667);
 							let next_frame_time = std::time::Instant::now()
-								+ std::time::Duration::from_millis(wait_millis as u64);
-							*control_flow =
-								glutin::event_loop::ControlFlow::WaitUntil(next_frame_time);
+								+ std::time::Duration::from_secs_f64(wait_millis / 1000.0);
+							*control_flow = match frame_pacing.limiter() {
+								FrameLimiter::Unlimited => ControlFlow::Poll,
+								_ => ControlFlow::WaitUntil(
+									frame_pacing.wake_up_time(next_frame_time),
+								),
+							};
 							next_time = next_frame_time;
 							// *control_flow = glutin::event_loop::ControlFlow::Wait;
 							// *control_flow = glutin::event_loop::ControlFlow::Poll;
@@ -470,17 +681,31 @@ impl Window {
 			/*
 				*/
 		});
+
+		userdata
 	}
+
+	// returns the userdata once the event loop exits, e.g. when the window is closed, or update reports done
 	pub fn run(
 		&mut self,
 		parent_thread: Option<std::thread::Thread>,
 		userdata: Box<dyn WindowUserData>,
 		callbacks: WindowCallbacks,
-	) {
+	) -> Box<dyn WindowUserData> {
 		let el = self.el.take().unwrap();
 		let windowed_context = self.windowed_context.take().unwrap();
-
-		// glutin's EventLoop run hijacks the current thread and never returns it, so we have to work around that
-		Window::run_event_loop(parent_thread, el, windowed_context, userdata, callbacks);
+		let window_update_context = self.create_update_context();
+
+		Window::run_event_loop(
+			parent_thread,
+			el,
+			windowed_context,
+			userdata,
+			callbacks,
+			self.fixed_timestep,
+			self.frame_pacing,
+			self.focus_policy,
+			window_update_context,
+		)
 	}
 }
@@ -1,15 +1,19 @@
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use serde::{Deserialize, Serialize};
 use serde_yaml;
+use tracing::*;
 
 use crate::math::Vector2;
+use crate::window::{MonitorInfo, WindowMode, WindowUpdateContext};
 
 #[derive(Debug, Default, Deserialize, Serialize)]
 pub struct WindowLayoutWindowConfig {
-	pos:  Vector2,
+	pos:  Vector2, // of the last windowed mode
 	size: Vector2,
+	#[serde(default)]
+	mode: WindowMode,
 }
 
 impl WindowLayoutWindowConfig {
@@ -19,6 +23,9 @@ impl WindowLayoutWindowConfig {
 	pub fn size(&self) -> &Vector2 {
 		&self.size
 	}
+	pub fn mode(&self) -> &WindowMode {
+		&self.mode
+	}
 }
 
 #[derive(Debug, Default, Deserialize, Serialize)]
@@ -28,7 +35,7 @@ pub struct WindowLayout {
 
 impl WindowLayout {
 	pub fn load(&mut self, filename: &Path) -> anyhow::Result<()> {
-		let f = std::fs::File::open(&filename)?;
+		let f = std::fs::File::open(filename)?;
 		let c = serde_yaml::from_reader(&f)?;
 		*self = c;
 
@@ -36,26 +43,306 @@ impl WindowLayout {
 		Ok(())
 	}
 
+	// e.g. from where older versions saved it, as long as there is nothing at filename yet
+	pub fn load_with_fallback(&mut self, filename: &Path, fallback: &Path) -> anyhow::Result<()> {
+		if !filename.exists() && fallback.exists() {
+			debug!("Loading window layout from {:?}", fallback);
+			return self.load(fallback);
+		}
+		self.load(filename)
+	}
+
 	pub fn save(&self, filename: &Path) -> anyhow::Result<()> {
 		let s = serde_yaml::to_string(&self)?;
 		//		dbg!(&s);
 		//		let mut buffer = File::create( filename )?;
 		//		buffer.write_all( &s.as_bytes() );
-		std::fs::write(&filename, &s.as_bytes())?;
+		if let Some(dir) = filename.parent() {
+			if !dir.as_os_str().is_empty() {
+				std::fs::create_dir_all(dir)?;
+			}
+		}
+		std::fs::write(filename, s.as_bytes())?;
 		//		write!(buffer, &s);
 		Ok(())
 	}
 
 	pub fn set_window(&mut self, name: &str, pos: &Vector2, size: &Vector2) {
-		let mut window = self
-			.windows
-			.entry(name.to_string())
-			.or_insert(WindowLayoutWindowConfig::default());
+		let window = self.windows.entry(name.to_string()).or_default();
 		window.pos = *pos;
 		window.size = *size;
 	}
 
+	pub fn set_window_mode(&mut self, name: &str, mode: &WindowMode) {
+		let window = self.windows.entry(name.to_string()).or_default();
+		window.mode = mode.clone();
+	}
+
 	pub fn get_window(&self, name: &str) -> Option<&WindowLayoutWindowConfig> {
 		self.windows.get(name)
 	}
+
+	// keeps a rectangle that is at least partly visible, otherwise moves it onto the monitor it overlaps most
+	pub fn clamp_to_monitors(
+		pos: &Vector2,
+		size: &Vector2,
+		monitors: &[MonitorInfo],
+	) -> (Vector2, Vector2) {
+		const MIN_VISIBLE: Vector2 = Vector2 { x: 64.0, y: 32.0 };
+		let overlap = |m: &MonitorInfo| {
+			let w = (pos.x + size.x).min(m.pos.x + m.size.x) - pos.x.max(m.pos.x);
+			let h = (pos.y + size.y).min(m.pos.y + m.size.y) - pos.y.max(m.pos.y);
+			Vector2::new(w.max(0.0), h.max(0.0))
+		};
+
+		let is_visible = monitors.iter().any(|m| {
+			let o = overlap(m);
+			o.x >= MIN_VISIBLE.x.min(size.x) && o.y >= MIN_VISIBLE.y.min(size.y)
+		});
+		if is_visible {
+			return (*pos, *size);
+		}
+
+		// the first monitor wins ties, e.g. when nothing overlaps
+		let monitor = monitors.iter().rev().max_by(|a, b| {
+			let a = overlap(a);
+			let b = overlap(b);
+			(a.x * a.y).total_cmp(&(b.x * b.y))
+		});
+		match monitor {
+			Some(m) => {
+				let size = Vector2::new(size.x.min(m.size.x), size.y.min(m.size.y));
+				let pos = Vector2::new(
+					pos.x.clamp(m.pos.x, m.pos.x + m.size.x - size.x),
+					pos.y.clamp(m.pos.y, m.pos.y + m.size.y - size.y),
+				);
+				(pos, size)
+			},
+			None => (*pos, *size),
+		}
+	}
+}
+
+// writes the layout once the window has been left alone for a while, and when dropped
+#[derive(Debug)]
+pub struct WindowLayoutSaver {
+	layout:        WindowLayout,
+	filename:      PathBuf,
+	name:          String,
+	debounce_time: f64,
+	quiet_time:    f64,
+	is_pending:    bool,
+}
+
+impl WindowLayoutSaver {
+	pub fn new(layout: WindowLayout, filename: &Path, name: &str) -> Self {
+		Self {
+			layout,
+			filename: filename.to_path_buf(),
+			name: name.to_string(),
+			debounce_time: 0.5,
+			quiet_time: 0.0,
+			is_pending: false,
+		}
+	}
+
+	pub fn with_debounce_time(mut self, debounce_time: f64) -> Self {
+		self.debounce_time = debounce_time;
+		self
+	}
+
+	pub fn layout(&self) -> &WindowLayout {
+		&self.layout
+	}
+
+	pub fn is_pending(&self) -> bool {
+		self.is_pending
+	}
+
+	// call once per frame
+	pub fn update(&mut self, wuc: &WindowUpdateContext) {
+		if wuc.window_changed {
+			// keep the windowed size and position while in fullscreen
+			if !wuc.window_mode().is_fullscreen() {
+				// the size is restored with Window::set_size, which is logical
+				self.layout
+					.set_window(&self.name, &wuc.window_pos, &wuc.window_size_logical());
+			}
+			self.layout.set_window_mode(&self.name, wuc.window_mode());
+			self.is_pending = true;
+			self.quiet_time = 0.0;
+		} else if self.is_pending {
+			self.quiet_time += wuc.time_step();
+			if self.quiet_time >= self.debounce_time {
+				self.save_if_pending();
+			}
+		}
+	}
+
+	pub fn save_if_pending(&mut self) {
+		if !self.is_pending {
+			return;
+		}
+		self.is_pending = false;
+		match self.layout.save(&self.filename) {
+			Ok(_) => debug!("Saved window layout to {:?}", &self.filename),
+			Err(e) => warn!(
+				"Failed saving window layout to {:?}: {:?}",
+				&self.filename, e
+			),
+		}
+	}
+}
+
+impl Drop for WindowLayoutSaver {
+	fn drop(&mut self) {
+		self.save_if_pending();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn mode_is_saved_next_to_the_window_config() -> anyhow::Result<()> {
+		let filename =
+			std::env::temp_dir().join(format!("oml-game-layout-{}.yaml", std::process::id()));
+		let mut layout = WindowLayout::default();
+		layout.set_window(
+			"main",
+			&Vector2::new(10.0, 20.0),
+			&Vector2::new(640.0, 480.0),
+		);
+		let mode = WindowMode::Exclusive {
+			monitor:      Some("DP-1".to_string()),
+			size:         Some(Vector2::new(1920.0, 1080.0)),
+			refresh_rate: Some(144),
+		};
+		layout.set_window_mode("main", &mode);
+		layout.save(&filename)?;
+
+		let mut loaded = WindowLayout::default();
+		loaded.load(&filename)?;
+		let main = loaded.get_window("main").unwrap();
+		assert_eq!(&Vector2::new(640.0, 480.0), main.size());
+		assert_eq!(&mode, main.mode());
+
+		// layouts from before modes existed still load
+		std::fs::write(
+			&filename,
+			"windows:\n  main:\n    pos: {x: 1, y: 2}\n    size: {x: 3, y: 4}\n",
+		)?;
+		loaded.load(&filename)?;
+		assert_eq!(
+			&WindowMode::Windowed,
+			loaded.get_window("main").unwrap().mode()
+		);
+
+		std::fs::remove_file(&filename)?;
+		Ok(())
+	}
+
+	fn monitor(x: f32, y: f32, w: f32, h: f32) -> MonitorInfo {
+		MonitorInfo {
+			name:         String::new(),
+			pos:          Vector2::new(x, y),
+			size:         Vector2::new(w, h),
+			scale_factor: 1.0,
+		}
+	}
+
+	#[test]
+	fn off_screen_rectangles_are_moved_back() {
+		let monitors = [
+			monitor(0.0, 0.0, 1920.0, 1080.0),
+			monitor(1920.0, 0.0, 1280.0, 1024.0),
+		];
+		let size = Vector2::new(800.0, 600.0);
+
+		let pos = Vector2::new(1800.0, 100.0);
+		assert_eq!(
+			(pos, size),
+			WindowLayout::clamp_to_monitors(&pos, &size, &monitors)
+		);
+
+		// the right monitor was unplugged
+		let (pos, clamped_size) =
+			WindowLayout::clamp_to_monitors(&Vector2::new(2500.0, 100.0), &size, &monitors[0..1]);
+		assert_eq!(Vector2::new(1120.0, 100.0), pos);
+		assert_eq!(size, clamped_size);
+
+		let (pos, size) = WindowLayout::clamp_to_monitors(
+			&Vector2::new(-5000.0, 4000.0),
+			&Vector2::new(4000.0, 600.0),
+			&monitors,
+		);
+		assert_eq!(Vector2::new(0.0, 480.0), pos);
+		assert_eq!(Vector2::new(1920.0, 600.0), size);
+	}
+
+	#[test]
+	fn old_layouts_are_loaded_until_there_is_a_new_one() -> anyhow::Result<()> {
+		let dir =
+			std::env::temp_dir().join(format!("oml-game-layout-fallback-{}", std::process::id()));
+		let filename = dir.join("window_layout.yaml");
+		let fallback = dir.join("oml-game_layout.yaml");
+		let mut layout = WindowLayout::default();
+		assert!(layout.load_with_fallback(&filename, &fallback).is_err());
+
+		layout.set_window("main", &Vector2::new(1.0, 2.0), &Vector2::new(3.0, 4.0));
+		layout.save(&fallback)?;
+		let mut loaded = WindowLayout::default();
+		loaded.load_with_fallback(&filename, &fallback)?;
+		assert_eq!(
+			&Vector2::new(1.0, 2.0),
+			loaded.get_window("main").unwrap().pos()
+		);
+
+		layout.set_window("main", &Vector2::new(5.0, 6.0), &Vector2::new(3.0, 4.0));
+		layout.save(&filename)?;
+		loaded.load_with_fallback(&filename, &fallback)?;
+		assert_eq!(
+			&Vector2::new(5.0, 6.0),
+			loaded.get_window("main").unwrap().pos()
+		);
+
+		std::fs::remove_dir_all(&dir)?;
+		Ok(())
+	}
+
+	#[test]
+	fn saves_wait_until_the_window_is_left_alone() -> anyhow::Result<()> {
+		let filename = std::env::temp_dir()
+			.join(format!("oml-game-layout-saver-{}", std::process::id()))
+			.join("layout.yaml");
+		let mut saver = WindowLayoutSaver::new(WindowLayout::default(), &filename, "main");
+		let mut wuc = WindowUpdateContext::new();
+		wuc.time_step = 0.1;
+
+		for i in 0..10 {
+			wuc.window_changed = true;
+			wuc.window_pos = Vector2::new(i as f32, 0.0);
+			saver.update(&wuc);
+			wuc.update();
+		}
+		assert!(saver.is_pending());
+		assert!(!filename.exists());
+
+		for _ in 0..5 {
+			saver.update(&wuc);
+			wuc.update();
+		}
+		assert!(!saver.is_pending());
+
+		let mut loaded = WindowLayout::default();
+		loaded.load(&filename)?;
+		assert_eq!(
+			&Vector2::new(9.0, 0.0),
+			loaded.get_window("main").unwrap().pos()
+		);
+
+		std::fs::remove_dir_all(filename.parent().unwrap())?;
+		Ok(())
+	}
 }
@@ -0,0 +1,124 @@
+use glutin::monitor::{MonitorHandle, VideoMode};
+use glutin::window::Fullscreen;
+use serde::{Deserialize, Serialize};
+use tracing::*;
+
+use crate::math::Vector2;
+
+// monitors are chosen by name, and fall back to the current one when missing
+#[derive(Debug, Default, Clone, PartialEq, Deserialize, Serialize)]
+pub enum WindowMode {
+	#[default]
+	Windowed,
+	Borderless {
+		monitor: Option<String>,
+	},
+	// changes the monitor's video mode, size and refresh rate are picked as close as possible
+	Exclusive {
+		monitor:      Option<String>,
+		size:         Option<Vector2>,
+		refresh_rate: Option<u32>, // in Hz
+	},
+}
+
+impl WindowMode {
+	pub fn is_fullscreen(&self) -> bool {
+		!matches!(self, WindowMode::Windowed)
+	}
+
+	// e.g. for Alt+Enter, exclusive goes back to windowed too
+	pub fn toggled(&self) -> Self {
+		match self {
+			WindowMode::Windowed => WindowMode::Borderless { monitor: None },
+			_ => WindowMode::Windowed,
+		}
+	}
+
+	fn find_monitor(
+		window: &glutin::window::Window,
+		name: &Option<String>,
+	) -> Option<MonitorHandle> {
+		let named = name.as_ref().and_then(|name| {
+			window
+				.available_monitors()
+				.find(|m| m.name().as_ref() == Some(name))
+		});
+		if named.is_none() && name.is_some() {
+			warn!("Monitor {:?} not found, using the current one", name);
+		}
+		named
+			.or_else(|| window.current_monitor())
+			.or_else(|| window.primary_monitor())
+	}
+
+	fn find_video_mode(
+		monitor: &MonitorHandle,
+		size: &Option<Vector2>,
+		refresh_rate: &Option<u32>,
+	) -> Option<VideoMode> {
+		// closest size first, then closest refresh rate, biggest and fastest without a wish
+		let size_cost = |m: &VideoMode| match size {
+			Some(s) => {
+				(m.size().width as f32 - s.x).abs() as u64
+					+ (m.size().height as f32 - s.y).abs() as u64
+			},
+			None => u64::MAX - (m.size().width as u64 * m.size().height as u64),
+		};
+		let refresh_cost = |m: &VideoMode| match refresh_rate {
+			Some(r) => (m.refresh_rate_millihertz() as i64 - *r as i64 * 1000).unsigned_abs(),
+			None => u64::MAX - m.refresh_rate_millihertz() as u64,
+		};
+		monitor
+			.video_modes()
+			.min_by_key(|m| (size_cost(m), refresh_cost(m)))
+	}
+
+	pub(crate) fn apply(&self, window: &glutin::window::Window) {
+		let fullscreen = match self {
+			WindowMode::Windowed => None,
+			WindowMode::Borderless { monitor } => {
+				Some(Fullscreen::Borderless(Self::find_monitor(window, monitor)))
+			},
+			WindowMode::Exclusive {
+				monitor,
+				size,
+				refresh_rate,
+			} => {
+				let handle = Self::find_monitor(window, monitor);
+				match handle
+					.as_ref()
+					.and_then(|m| Self::find_video_mode(m, size, refresh_rate))
+				{
+					Some(video_mode) => {
+						debug!("Using video mode {:?}", video_mode);
+						Some(Fullscreen::Exclusive(video_mode))
+					},
+					None => {
+						warn!("No video mode found, falling back to borderless");
+						Some(Fullscreen::Borderless(handle))
+					},
+				}
+			},
+		};
+		window.set_fullscreen(fullscreen);
+	}
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MonitorInfo {
+	pub name:         String,
+	pub pos:          Vector2, // in pixels, on the virtual desktop
+	pub size:         Vector2,
+	pub scale_factor: f64,
+}
+
+impl MonitorInfo {
+	pub(crate) fn from_handle(handle: &MonitorHandle) -> Self {
+		Self {
+			name:         handle.name().unwrap_or_default(),
+			pos:          Vector2::new(handle.position().x as f32, handle.position().y as f32),
+			size:         Vector2::new(handle.size().width as f32, handle.size().height as f32),
+			scale_factor: handle.scale_factor(),
+		}
+	}
+}
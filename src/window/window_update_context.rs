@@ -1,4 +1,8 @@
 use crate::math::Vector2;
+use crate::window::{
+	GamepadAxis, GamepadButton, GamepadState, GestureConfig, GestureEvent, GestureRecognizer,
+	InputFrame, KeyCode, MonitorInfo, TextInputEvent, Touch, TouchPhase, WindowMode,
+};
 
 #[derive(Debug)]
 #[repr(u8)]
@@ -7,48 +11,121 @@ pub enum ModifierKey {
 	Ctrl,
 	Alt,
 }
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Clone)]
 pub struct WindowUpdateContext {
-	pub time_step:              f64,
-	pub is_escape_pressed:      bool,
-	pub is_space_pressed:       bool,
-	pub mouse_pos:              Vector2,
-	pub mouse_wheel_line_delta: Vector2,
-	pub mouse_buttons:          [bool; 3], // left middle right
-	pub is_key_pressed:         [bool; 256],
-	is_modifier_pressed:        [bool; 256],
-	pub window_size:            Vector2,
-	pub window_pos:             Vector2,
-	pub window_changed:         bool,
-
-	mouse_buttons_internal: [bool; 3], // left middle right
-	previous_mouse_buttons: [bool; 3],
-	previous_keys_pressed:  [bool; 256],
+	pub time_step:               f64,
+	pub is_escape_pressed:       bool,
+	pub is_space_pressed:        bool,
+	pub mouse_pos:               Vector2, // normalised 0..1, origin bottom left
+	pub mouse_pos_pixels:        Vector2, // origin bottom left, all pixels are physical, see scale_factor
+	pub mouse_wheel_line_delta:  Vector2,
+	pub mouse_wheel_pixel_delta: Vector2, // e.g. from trackpads
+	pub mouse_buttons:           [bool; WindowUpdateContext::MOUSE_BUTTON_COUNT], // left middle right, extra buttons
+	keys_down:                   [bool; KeyCode::COUNT],
+	is_modifier_pressed:         [bool; 256],
+	pub window_size:             Vector2,
+	pub window_pos:              Vector2,
+	pub window_changed:          bool,
+
+	mouse_buttons_internal: [bool; WindowUpdateContext::MOUSE_BUTTON_COUNT],
+	previous_mouse_buttons: [bool; WindowUpdateContext::MOUSE_BUTTON_COUNT],
+	mouse_delta_pixels:     Vector2,
+	mouse_pos_valid:        bool, // false until the first move after entering the window
+	mouse_inside:           bool,
+	previous_mouse_inside:  bool,
+	previous_keys_down:     [bool; KeyCode::COUNT],
 
 	pub is_function_key_pressed:    [bool; 16],
 	previous_function_keys_pressed: [bool; 16],
+
+	text:               String, // typed this frame
+	text_input_events:  Vec<TextInputEvent>,
+	ime_preedit:        Option<String>,
+	ime_preedit_cursor: Option<(usize, usize)>,
+
+	gestures:       GestureRecognizer,
+	gesture_events: Vec<GestureEvent>,
+
+	touches:                  Vec<Touch>, // active, and the ones that ended this frame
+	primary_touch:            Option<u64>,
+	emulate_mouse_from_touch: bool,
+
+	gamepads:          Vec<GamepadState>, // dead zones already applied
+	previous_gamepads: Vec<GamepadState>,
+
+	has_focus:              bool,
+	previous_has_focus:     bool,
+	is_minimized:           bool,
+	is_occluded:            bool,
+	is_os_suspended:        bool, // e.g. sent to the background on mobile
+	previous_was_suspended: bool,
+
+	window_mode:           WindowMode,
+	requested_window_mode: Option<WindowMode>,
+	monitors:              Vec<MonitorInfo>,
+
+	scale_factor:          f64,
+	previous_scale_factor: f64,
 }
 
 impl WindowUpdateContext {
+	// extra buttons keep the platform's numbering, offset by 3
+	pub const MOUSE_BUTTON_COUNT: usize = 16;
+
 	pub fn new() -> Self {
 		Self {
 			time_step:               0.0,
 			is_escape_pressed:       false,
 			is_space_pressed:        false,
 			mouse_pos:               Vector2::zero(),
+			mouse_pos_pixels:        Vector2::zero(),
 			mouse_wheel_line_delta:  Vector2::zero(),
-			mouse_buttons:           [false, false, false],
-			mouse_buttons_internal:  [false, false, false],
-			is_key_pressed:          [false; 256],
+			mouse_wheel_pixel_delta: Vector2::zero(),
+			mouse_buttons:           [false; Self::MOUSE_BUTTON_COUNT],
+			mouse_buttons_internal:  [false; Self::MOUSE_BUTTON_COUNT],
+			keys_down:               [false; KeyCode::COUNT],
 			is_function_key_pressed: [false; 16],
 			is_modifier_pressed:     [false; 256],
 			window_size:             Vector2::zero(),
 			window_pos:              Vector2::zero(),
 			window_changed:          false,
 
-			previous_mouse_buttons:         [false, false, false],
-			previous_keys_pressed:          [false; 256],
+			previous_mouse_buttons:         [false; Self::MOUSE_BUTTON_COUNT],
+			mouse_delta_pixels:             Vector2::zero(),
+			mouse_pos_valid:                false,
+			mouse_inside:                   false,
+			previous_mouse_inside:          false,
+			previous_keys_down:             [false; KeyCode::COUNT],
 			previous_function_keys_pressed: [false; 16],
+
+			text:               String::new(),
+			text_input_events:  Vec::new(),
+			ime_preedit:        None,
+			ime_preedit_cursor: None,
+
+			gestures:       GestureRecognizer::default(),
+			gesture_events: Vec::new(),
+
+			touches:                  Vec::new(),
+			primary_touch:            None,
+			emulate_mouse_from_touch: false,
+
+			gamepads:          Vec::new(),
+			previous_gamepads: Vec::new(),
+
+			has_focus:              true,
+			previous_has_focus:     true,
+			is_minimized:           false,
+			is_occluded:            false,
+			is_os_suspended:        false,
+			previous_was_suspended: false,
+
+			window_mode:           WindowMode::default(),
+			requested_window_mode: None,
+			monitors:              Vec::new(),
+
+			scale_factor:          1.0,
+			previous_scale_factor: 1.0,
 		}
 	}
 
@@ -56,8 +133,24 @@ impl WindowUpdateContext {
 		//		dbg!(&self);
 		self.previous_mouse_buttons = self.mouse_buttons_internal;
 		self.mouse_buttons = self.mouse_buttons_internal;
-		self.previous_keys_pressed = self.is_key_pressed;
+		self.mouse_delta_pixels = Vector2::zero();
+		self.mouse_wheel_line_delta = Vector2::zero();
+		self.mouse_wheel_pixel_delta = Vector2::zero();
+		self.previous_mouse_inside = self.mouse_inside;
+		self.previous_keys_down = self.keys_down;
+		self.previous_gamepads.clone_from(&self.gamepads);
+		self.previous_has_focus = self.has_focus;
+		self.previous_was_suspended = self.is_suspended();
+		self.previous_scale_factor = self.scale_factor;
+		self.window_changed = false;
 		self.previous_function_keys_pressed = self.is_function_key_pressed;
+		self.text.clear();
+		self.text_input_events.clear();
+		self.gesture_events.clear();
+		self.touches.retain(|t| t.is_active());
+		for t in self.touches.iter_mut() {
+			t.phase = TouchPhase::Stationary;
+		}
 		//		for i in 0..self.is_key_pressed.len() {
 		//			self.previous_keys_pressed[ i ] = self.is_key_pressed[ i ];
 		//		}
@@ -84,12 +177,106 @@ impl WindowUpdateContext {
 		self.mouse_buttons[button_index] = down;
 	}
 
-	pub fn was_key_pressed(&self, key: u8) -> bool {
-		self.is_key_pressed[key as usize] && !self.previous_keys_pressed[key as usize]
+	pub fn is_mouse_button_down(&self, button_index: usize) -> bool {
+		self.mouse_buttons[button_index]
+	}
+
+	// origin bottom left, also updates the normalised position and the frame's delta
+	pub fn set_mouse_pos_pixels(&mut self, pos: &Vector2) {
+		if self.mouse_pos_valid {
+			self.mouse_delta_pixels = self
+				.mouse_delta_pixels
+				.add(&pos.sub(&self.mouse_pos_pixels));
+		}
+		self.mouse_pos_valid = true;
+		self.mouse_pos_pixels = *pos;
+		if self.window_size.x > 0.0 && self.window_size.y > 0.0 {
+			self.mouse_pos = Vector2::new(pos.x / self.window_size.x, pos.y / self.window_size.y);
+		}
+	}
+
+	// movement since the last frame in pixels
+	pub fn mouse_delta_pixels(&self) -> Vector2 {
+		self.mouse_delta_pixels
+	}
+
+	// movement since the last frame, normalised like mouse_pos
+	pub fn mouse_delta(&self) -> Vector2 {
+		if self.window_size.x > 0.0 && self.window_size.y > 0.0 {
+			Vector2::new(
+				self.mouse_delta_pixels.x / self.window_size.x,
+				self.mouse_delta_pixels.y / self.window_size.y,
+			)
+		} else {
+			Vector2::zero()
+		}
+	}
+
+	pub fn add_mouse_wheel_line_delta(&mut self, delta: &Vector2) {
+		self.mouse_wheel_line_delta = self.mouse_wheel_line_delta.add(delta);
+	}
+
+	pub fn add_mouse_wheel_pixel_delta(&mut self, delta: &Vector2) {
+		self.mouse_wheel_pixel_delta = self.mouse_wheel_pixel_delta.add(delta);
+	}
+
+	pub fn set_mouse_inside(&mut self, inside: bool) {
+		self.mouse_inside = inside;
+		// avoid a jump in the delta when coming back at a different position
+		self.mouse_pos_valid = false;
+	}
+
+	pub fn is_mouse_inside(&self) -> bool {
+		self.mouse_inside
+	}
+
+	pub fn did_mouse_enter(&self) -> bool {
+		self.mouse_inside && !self.previous_mouse_inside
+	}
+
+	pub fn did_mouse_leave(&self) -> bool {
+		!self.mouse_inside && self.previous_mouse_inside
+	}
+
+	// also keeps the older escape, space, function key, and modifier states in sync
+	pub fn set_key_down(&mut self, key: KeyCode, down: bool) {
+		self.keys_down[key as usize] = down;
+		self.sync_legacy_keys();
+	}
+
+	fn sync_legacy_keys(&mut self) {
+		let down = |k: KeyCode| self.keys_down[k as usize];
+		self.is_escape_pressed = down(KeyCode::Escape);
+		self.is_space_pressed = down(KeyCode::Space);
+		let shift = down(KeyCode::LShift) || down(KeyCode::RShift);
+		let ctrl = down(KeyCode::LControl) || down(KeyCode::RControl);
+		let alt = down(KeyCode::LAlt) || down(KeyCode::RAlt);
+		for n in 1..self.is_function_key_pressed.len() {
+			self.is_function_key_pressed[n] = self.keys_down[KeyCode::F1 as usize + n - 1];
+		}
+		self.set_modifier_pressed(ModifierKey::Shift, shift);
+		self.set_modifier_pressed(ModifierKey::Ctrl, ctrl);
+		self.set_modifier_pressed(ModifierKey::Alt, alt);
+	}
+
+	// accepts KeyCode, or the ascii (u8) used by the older api
+	pub fn is_key_down(&self, key: impl Into<KeyCode>) -> bool {
+		self.keys_down[key.into() as usize]
+	}
+
+	pub fn was_key_pressed(&self, key: impl Into<KeyCode>) -> bool {
+		let key = key.into() as usize;
+		self.keys_down[key] && !self.previous_keys_down[key]
+	}
+
+	pub fn was_key_released(&self, key: impl Into<KeyCode>) -> bool {
+		let key = key.into() as usize;
+		!self.keys_down[key] && self.previous_keys_down[key]
 	}
 
-	pub fn is_key_pressed(&self, key: u8) -> bool {
-		self.is_key_pressed[key as usize]
+	// :DEPRECATED: use is_key_down
+	pub fn is_key_pressed(&self, key: impl Into<KeyCode>) -> bool {
+		self.is_key_down(key)
 	}
 
 	pub fn is_space_pressed(&self) -> bool {
@@ -116,4 +303,486 @@ impl WindowUpdateContext {
 	pub fn time_step(&self) -> f64 {
 		self.time_step
 	}
+
+	// can be used to fake typing
+	pub fn add_text_input_event(&mut self, event: TextInputEvent) {
+		match &event {
+			TextInputEvent::Character(c) => {
+				if !TextInputEvent::is_printable(*c) {
+					return;
+				}
+				self.text.push(*c);
+			},
+			TextInputEvent::Key(_) => {},
+			TextInputEvent::Preedit { text, cursor } => {
+				if text.is_empty() {
+					self.ime_preedit = None;
+					self.ime_preedit_cursor = None;
+				} else {
+					self.ime_preedit = Some(text.clone());
+					self.ime_preedit_cursor = *cursor;
+				}
+			},
+			TextInputEvent::Commit(text) => {
+				self.text.push_str(text);
+				self.ime_preedit = None;
+				self.ime_preedit_cursor = None;
+			},
+		}
+		self.text_input_events.push(event);
+	}
+
+	pub fn text(&self) -> &str {
+		&self.text
+	}
+
+	pub fn text_input_events(&self) -> &Vec<TextInputEvent> {
+		&self.text_input_events
+	}
+
+	// the IME composition that is still in progress
+	pub fn ime_preedit(&self) -> Option<&str> {
+		self.ime_preedit.as_deref()
+	}
+
+	pub fn ime_preedit_cursor(&self) -> Option<(usize, usize)> {
+		self.ime_preedit_cursor
+	}
+
+	pub fn set_gesture_config(&mut self, config: GestureConfig) {
+		self.gestures.set_config(config);
+	}
+
+	// called once per frame after all input is in, e.g. by Game before App::update
+	pub fn recognize_gestures(&mut self) {
+		let mut gestures = std::mem::take(&mut self.gestures);
+		self.gesture_events = gestures.process(self);
+		self.gestures = gestures;
+	}
+
+	pub fn gesture_events(&self) -> &Vec<GestureEvent> {
+		&self.gesture_events
+	}
+
+	pub fn was_clicked(&self, button_index: usize) -> bool {
+		self.gesture_events
+			.iter()
+			.any(|e| matches!(e, GestureEvent::Click { button, .. } if *button == button_index))
+	}
+
+	pub fn was_double_clicked(&self, button_index: usize) -> bool {
+		self.gesture_events.iter().any(
+			|e| matches!(e, GestureEvent::DoubleClick { button, .. } if *button == button_index),
+		)
+	}
+
+	pub fn was_long_pressed(&self, button_index: usize) -> bool {
+		self.gesture_events
+			.iter()
+			.any(|e| matches!(e, GestureEvent::LongPress { button, .. } if *button == button_index))
+	}
+
+	pub fn is_dragging(&self, button_index: usize) -> bool {
+		self.gestures.is_dragging(button_index)
+	}
+
+	// the primary touch, i.e. the first finger down, drives mouse button 0
+	pub fn set_emulate_mouse_from_touch(&mut self, emulate: bool) {
+		self.emulate_mouse_from_touch = emulate;
+	}
+
+	pub fn emulate_mouse_from_touch(&self) -> bool {
+		self.emulate_mouse_from_touch
+	}
+
+	// origin bottom left, used by the event loop, and by tests to fake touches
+	pub fn add_touch_event(&mut self, id: u64, phase: TouchPhase, pos_pixels: &Vector2) {
+		let window_size = self.window_size;
+		match phase {
+			TouchPhase::Began => {
+				self.touches.retain(|t| t.id != id);
+				if self.primary_touch.is_none() && !self.touches.iter().any(|t| t.is_active()) {
+					self.primary_touch = Some(id);
+				}
+				self.touches.push(Touch::new(id, pos_pixels, &window_size));
+			},
+			TouchPhase::Moved
+			| TouchPhase::Stationary
+			| TouchPhase::Ended
+			| TouchPhase::Cancelled => {
+				match self
+					.touches
+					.iter_mut()
+					.find(|t| t.id == id && t.is_active())
+				{
+					Some(t) => {
+						t.set_pos_pixels(pos_pixels, &window_size);
+						// a touch that began this frame keeps Began until the next frame
+						if phase != TouchPhase::Moved || t.phase != TouchPhase::Began {
+							t.phase = phase;
+						}
+					},
+					None => {
+						tracing::debug!("Ignoring {:?} for unknown touch {}", phase, id);
+						return;
+					},
+				}
+			},
+		}
+
+		if self.primary_touch == Some(id) {
+			if self.emulate_mouse_from_touch {
+				let active = phase.is_active();
+				if phase == TouchPhase::Began {
+					self.set_mouse_inside(true);
+				}
+				self.set_mouse_pos_pixels(pos_pixels);
+				self.set_mouse_button(0, active);
+			}
+			if !phase.is_active() {
+				self.primary_touch = None;
+			}
+		}
+	}
+
+	pub fn touches(&self) -> &Vec<Touch> {
+		&self.touches
+	}
+
+	pub fn touch(&self, id: u64) -> Option<&Touch> {
+		self.touches.iter().find(|t| t.id == id)
+	}
+
+	pub fn primary_touch(&self) -> Option<&Touch> {
+		self.primary_touch.and_then(|id| self.touch(id))
+	}
+
+	pub fn active_touch_count(&self) -> usize {
+		self.touches.iter().filter(|t| t.is_active()).count()
+	}
+
+	pub fn set_has_focus(&mut self, has_focus: bool) {
+		self.has_focus = has_focus;
+	}
+
+	pub fn has_focus(&self) -> bool {
+		self.has_focus
+	}
+
+	pub fn was_focus_gained(&self) -> bool {
+		self.has_focus && !self.previous_has_focus
+	}
+
+	pub fn was_focus_lost(&self) -> bool {
+		!self.has_focus && self.previous_has_focus
+	}
+
+	pub fn set_minimized(&mut self, minimized: bool) {
+		self.is_minimized = minimized;
+	}
+
+	pub fn set_occluded(&mut self, occluded: bool) {
+		self.is_occluded = occluded;
+	}
+
+	pub fn set_os_suspended(&mut self, suspended: bool) {
+		self.is_os_suspended = suspended;
+	}
+
+	pub fn is_minimized(&self) -> bool {
+		self.is_minimized
+	}
+
+	// minimised, fully covered, or suspended by the os
+	pub fn is_suspended(&self) -> bool {
+		self.is_minimized || self.is_occluded || self.is_os_suspended
+	}
+
+	pub fn was_suspended(&self) -> bool {
+		self.is_suspended() && !self.previous_was_suspended
+	}
+
+	pub fn was_resumed(&self) -> bool {
+		!self.is_suspended() && self.previous_was_suspended
+	}
+
+	// e.g. on focus loss, we will never see the key up events
+	pub fn release_all_keys(&mut self) {
+		self.keys_down = [false; KeyCode::COUNT];
+		self.sync_legacy_keys();
+		self.mouse_buttons_internal = [false; Self::MOUSE_BUTTON_COUNT];
+		self.mouse_buttons = self.mouse_buttons_internal;
+	}
+
+	pub fn window_mode(&self) -> &WindowMode {
+		&self.window_mode
+	}
+
+	// applied after the update, e.g. on Alt+Enter
+	pub fn set_window_mode(&mut self, mode: WindowMode) {
+		self.requested_window_mode = Some(mode);
+	}
+
+	pub fn toggle_fullscreen(&mut self) {
+		let mode = self
+			.requested_window_mode
+			.as_ref()
+			.unwrap_or(&self.window_mode)
+			.toggled();
+		self.set_window_mode(mode);
+	}
+
+	pub(crate) fn take_requested_window_mode(&mut self) -> Option<WindowMode> {
+		self.requested_window_mode.take()
+	}
+
+	pub(crate) fn set_current_window_mode(&mut self, mode: WindowMode) {
+		self.window_mode = mode;
+		self.window_changed = true;
+	}
+
+	pub fn monitors(&self) -> &Vec<MonitorInfo> {
+		&self.monitors
+	}
+
+	pub(crate) fn set_monitors(&mut self, monitors: Vec<MonitorInfo>) {
+		self.monitors = monitors;
+	}
+
+	// physical pixels per logical pixel, e.g. 2.0 on retina screens
+	pub fn scale_factor(&self) -> f64 {
+		self.scale_factor
+	}
+
+	pub fn set_scale_factor(&mut self, scale_factor: f64) {
+		self.scale_factor = scale_factor;
+	}
+
+	// without reporting a change, e.g. for a new window
+	pub(crate) fn set_initial_scale_factor(&mut self, scale_factor: f64) {
+		self.scale_factor = scale_factor;
+		self.previous_scale_factor = scale_factor;
+	}
+
+	pub fn was_scale_factor_changed(&self) -> bool {
+		self.scale_factor != self.previous_scale_factor
+	}
+
+	pub fn to_logical(&self, physical: &Vector2) -> Vector2 {
+		physical.scaled(1.0 / self.scale_factor as f32)
+	}
+
+	pub fn to_physical(&self, logical: &Vector2) -> Vector2 {
+		logical.scaled(self.scale_factor as f32)
+	}
+
+	pub fn window_size_logical(&self) -> Vector2 {
+		self.to_logical(&self.window_size)
+	}
+
+	pub fn mouse_pos_logical(&self) -> Vector2 {
+		self.to_logical(&self.mouse_pos_pixels)
+	}
+
+	// see Gamepads::poll
+	pub fn set_gamepad_state(&mut self, index: usize, state: GamepadState) {
+		if self.gamepads.len() <= index {
+			self.gamepads.resize(index + 1, GamepadState::default());
+		}
+		self.gamepads[index] = state;
+	}
+
+	pub fn gamepad_count(&self) -> usize {
+		self.gamepads.len()
+	}
+
+	pub fn gamepad(&self, index: usize) -> Option<&GamepadState> {
+		self.gamepads.get(index).filter(|g| g.connected)
+	}
+
+	fn previous_gamepad(&self, index: usize) -> Option<&GamepadState> {
+		self.previous_gamepads.get(index).filter(|g| g.connected)
+	}
+
+	pub fn is_gamepad_button_down(&self, index: usize, button: GamepadButton) -> bool {
+		self.gamepad(index)
+			.map(|g| g.is_button_down(button))
+			.unwrap_or(false)
+	}
+
+	fn was_gamepad_button_down(&self, index: usize, button: GamepadButton) -> bool {
+		self.previous_gamepad(index)
+			.map(|g| g.is_button_down(button))
+			.unwrap_or(false)
+	}
+
+	pub fn was_gamepad_button_pressed(&self, index: usize, button: GamepadButton) -> bool {
+		self.is_gamepad_button_down(index, button) && !self.was_gamepad_button_down(index, button)
+	}
+
+	pub fn was_gamepad_button_released(&self, index: usize, button: GamepadButton) -> bool {
+		!self.is_gamepad_button_down(index, button) && self.was_gamepad_button_down(index, button)
+	}
+
+	pub fn gamepad_axis(&self, index: usize, axis: GamepadAxis) -> f32 {
+		self.gamepad(index).map(|g| g.axis(axis)).unwrap_or(0.0)
+	}
+
+	pub(crate) fn previous_gamepad_axis(&self, index: usize, axis: GamepadAxis) -> f32 {
+		self.previous_gamepad(index)
+			.map(|g| g.axis(axis))
+			.unwrap_or(0.0)
+	}
+
+	pub(crate) fn input_frame(&self) -> InputFrame {
+		let mut keys = [0u32; InputFrame::KEY_WORDS];
+		for (i, _) in self.keys_down.iter().enumerate().filter(|(_, &d)| d) {
+			keys[i / 32] |= 1 << (i % 32);
+		}
+		InputFrame {
+			time_step: self.time_step,
+			mouse_pos: self.mouse_pos,
+			mouse_pos_pixels: self.mouse_pos_pixels,
+			mouse_delta_pixels: self.mouse_delta_pixels,
+			mouse_wheel_line_delta: self.mouse_wheel_line_delta,
+			mouse_wheel_pixel_delta: self.mouse_wheel_pixel_delta,
+			mouse_buttons: self.mouse_buttons_internal,
+			mouse_inside: self.mouse_inside,
+			keys,
+			window_size: self.window_size,
+			window_pos: self.window_pos,
+			window_changed: self.window_changed,
+			text_input_events: self.text_input_events.clone(),
+			touches: self.touches.clone(),
+			gamepads: self.gamepads.clone(),
+			has_focus: self.has_focus,
+			is_suspended: self.is_suspended(),
+			scale_factor: self.scale_factor,
+		}
+	}
+
+	// replaces the live input, previous states are kept, so pressed/released still work
+	pub(crate) fn apply_input_frame(&mut self, frame: &InputFrame) {
+		self.time_step = frame.time_step;
+		self.mouse_pos = frame.mouse_pos;
+		self.mouse_pos_pixels = frame.mouse_pos_pixels;
+		self.mouse_delta_pixels = frame.mouse_delta_pixels;
+		self.mouse_wheel_line_delta = frame.mouse_wheel_line_delta;
+		self.mouse_wheel_pixel_delta = frame.mouse_wheel_pixel_delta;
+		self.mouse_buttons_internal = frame.mouse_buttons;
+		self.mouse_buttons = frame.mouse_buttons;
+		self.mouse_inside = frame.mouse_inside;
+		for (i, k) in self.keys_down.iter_mut().enumerate() {
+			*k = frame.keys[i / 32] & (1 << (i % 32)) != 0;
+		}
+		self.sync_legacy_keys();
+		self.window_size = frame.window_size;
+		self.window_pos = frame.window_pos;
+		self.window_changed = frame.window_changed;
+		self.text.clear();
+		self.text_input_events.clear();
+		for e in frame.text_input_events.iter() {
+			self.add_text_input_event(e.clone());
+		}
+		// mouse emulation is already part of the recorded mouse state
+		self.touches = frame.touches.clone();
+		self.primary_touch = None;
+		self.gamepads.clone_from(&frame.gamepads);
+		self.has_focus = frame.has_focus;
+		self.is_minimized = false;
+		self.is_occluded = false;
+		self.is_os_suspended = frame.is_suspended;
+		self.scale_factor = frame.scale_factor;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn mouse_delta_and_wheel_are_per_frame() {
+		let mut wuc = WindowUpdateContext::new();
+		wuc.window_size = Vector2::new(200.0, 100.0);
+
+		wuc.set_mouse_inside(true);
+		wuc.set_mouse_pos_pixels(&Vector2::new(50.0, 50.0));
+		assert!(wuc.did_mouse_enter());
+		assert_eq!(Vector2::zero(), wuc.mouse_delta_pixels());
+		wuc.update();
+
+		wuc.set_mouse_pos_pixels(&Vector2::new(60.0, 40.0));
+		wuc.set_mouse_pos_pixels(&Vector2::new(70.0, 45.0));
+		wuc.add_mouse_wheel_pixel_delta(&Vector2::new(0.0, 3.5));
+		wuc.add_mouse_wheel_pixel_delta(&Vector2::new(0.0, 1.5));
+		assert_eq!(Vector2::new(20.0, -5.0), wuc.mouse_delta_pixels());
+		assert_eq!(Vector2::new(0.1, -0.05), wuc.mouse_delta());
+		assert_eq!(Vector2::new(0.35, 0.45), wuc.mouse_pos);
+		assert_eq!(Vector2::new(0.0, 5.0), wuc.mouse_wheel_pixel_delta);
+		wuc.update();
+
+		assert_eq!(Vector2::zero(), wuc.mouse_delta_pixels());
+		assert_eq!(Vector2::zero(), wuc.mouse_wheel_pixel_delta);
+
+		// coming back elsewhere doesn't count as movement
+		wuc.set_mouse_inside(false);
+		assert!(wuc.did_mouse_leave());
+		wuc.update();
+		wuc.set_mouse_inside(true);
+		wuc.set_mouse_pos_pixels(&Vector2::new(190.0, 10.0));
+		assert_eq!(Vector2::zero(), wuc.mouse_delta_pixels());
+	}
+
+	#[test]
+	fn pixels_are_physical_and_convert_to_logical() {
+		let mut wuc = WindowUpdateContext::new();
+		wuc.set_initial_scale_factor(2.0);
+		wuc.window_size = Vector2::new(400.0, 200.0);
+		wuc.set_mouse_pos_pixels(&Vector2::new(100.0, 50.0));
+		assert!(!wuc.was_scale_factor_changed());
+		assert_eq!(Vector2::new(200.0, 100.0), wuc.window_size_logical());
+		assert_eq!(Vector2::new(50.0, 25.0), wuc.mouse_pos_logical());
+		assert_eq!(Vector2::new(0.25, 0.25), wuc.mouse_pos);
+		wuc.update();
+
+		// moved to a standard dpi monitor
+		wuc.set_scale_factor(1.0);
+		assert!(wuc.was_scale_factor_changed());
+		assert_eq!(Vector2::new(400.0, 200.0), wuc.window_size_logical());
+		wuc.update();
+		assert!(!wuc.was_scale_factor_changed());
+	}
+
+	#[test]
+	fn touches_go_through_their_phases_and_emulate_the_mouse() {
+		let mut wuc = WindowUpdateContext::new();
+		wuc.window_size = Vector2::new(100.0, 100.0);
+		wuc.set_emulate_mouse_from_touch(true);
+
+		wuc.add_touch_event(7, TouchPhase::Began, &Vector2::new(10.0, 20.0));
+		wuc.add_touch_event(7, TouchPhase::Moved, &Vector2::new(12.0, 20.0));
+		wuc.add_touch_event(3, TouchPhase::Began, &Vector2::new(90.0, 90.0));
+		assert_eq!(2, wuc.active_touch_count());
+		assert_eq!(TouchPhase::Began, wuc.touch(7).unwrap().phase);
+		assert_eq!(7, wuc.primary_touch().unwrap().id);
+		assert!(wuc.was_mouse_button_pressed(0));
+		assert_eq!(Vector2::new(12.0, 20.0), wuc.mouse_pos_pixels);
+		wuc.update();
+
+		assert_eq!(TouchPhase::Stationary, wuc.touch(3).unwrap().phase);
+		wuc.add_touch_event(3, TouchPhase::Moved, &Vector2::new(80.0, 90.0));
+		wuc.add_touch_event(7, TouchPhase::Ended, &Vector2::new(15.0, 20.0));
+		assert_eq!(TouchPhase::Moved, wuc.touch(3).unwrap().phase);
+		assert_eq!(Vector2::new(0.8, 0.9), wuc.touch(3).unwrap().pos);
+		assert!(wuc.was_mouse_button_released(0));
+		assert!(wuc.primary_touch().is_none());
+		wuc.update();
+
+		// the second finger doesn't become primary, until all are up
+		wuc.add_touch_event(3, TouchPhase::Cancelled, &Vector2::new(80.0, 90.0));
+		assert_eq!(0, wuc.active_touch_count());
+		assert!(!wuc.is_mouse_button_down(0));
+		wuc.update();
+		assert!(wuc.touches().is_empty());
+	}
 }